@@ -0,0 +1,6 @@
+INSERT INTO "LoginThrottle" (ip, failed_count, last_failed_at)
+VALUES ($1, 1, $2)
+ON CONFLICT (ip) DO UPDATE SET failed_count   = CASE
+                                                    WHEN "LoginThrottle".last_failed_at < $3 THEN 1
+                                                    ELSE "LoginThrottle".failed_count + 1 END,
+                               last_failed_at = $2 RETURNING id, failed_count, locked_until;
\ No newline at end of file
@@ -0,0 +1,5 @@
+UPDATE "LoginThrottle"
+SET failed_count = failed_count - 1,
+    locked_until = CASE WHEN failed_count - 1 < $2 OR last_failed_at = $3 THEN NULL ELSE locked_until END
+WHERE user_id = $1
+  AND failed_count > 0;
\ No newline at end of file
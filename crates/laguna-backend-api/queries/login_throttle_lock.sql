@@ -0,0 +1,3 @@
+UPDATE "LoginThrottle"
+SET locked_until = $2
+WHERE id = $1;
\ No newline at end of file
@@ -0,0 +1,6 @@
+UPDATE "Peer"
+SET behaviour = 'Stopped'
+WHERE behaviour != 'Stopped'
+  AND COALESCE(updated_at, created_at) <= $1
+  AND info_hash = ANY ($2)
+RETURNING info_hash;
\ No newline at end of file
@@ -14,4 +14,5 @@ SELECT uuid,
        updated_at,
        created_by
 FROM "Peer"
-WHERE id = $1;
\ No newline at end of file
+WHERE id = $1
+  AND info_hash = $2;
\ No newline at end of file
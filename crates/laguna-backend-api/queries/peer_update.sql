@@ -4,7 +4,8 @@ SET uploaded_bytes   = $1,
     left_bytes       = $3,
     behaviour        = $4,
     updated_at       = $5
-WHERE id = $6 RETURNING
+WHERE id = $6
+  AND info_hash = $7 RETURNING
     uuid,
     id,
     md5_hash,
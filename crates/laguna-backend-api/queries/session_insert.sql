@@ -0,0 +1,2 @@
+INSERT INTO "Session" (id, user_id, token_hash, created_at, last_used_at, expires_at, user_agent, ip)
+VALUES ($1, $2, $3, $4, $4, $5, $6, $7) RETURNING id;
\ No newline at end of file
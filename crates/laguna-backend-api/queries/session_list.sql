@@ -0,0 +1,14 @@
+SELECT id,
+       user_id,
+       token_hash,
+       created_at,
+       last_used_at,
+       expires_at,
+       user_agent,
+       ip,
+       revoked_at
+FROM "Session"
+WHERE user_id = $1
+  AND revoked_at IS NULL
+  AND expires_at > $2
+ORDER BY last_used_at DESC;
\ No newline at end of file
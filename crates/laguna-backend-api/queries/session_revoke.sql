@@ -0,0 +1,4 @@
+UPDATE "Session"
+SET revoked_at = $2
+WHERE token_hash = $1
+  AND revoked_at IS NULL RETURNING id;
\ No newline at end of file
@@ -0,0 +1,5 @@
+UPDATE "Session"
+SET revoked_at = $3
+WHERE id = $1
+  AND token_hash != $2
+  AND revoked_at IS NULL;
\ No newline at end of file
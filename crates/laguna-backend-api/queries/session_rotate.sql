@@ -0,0 +1,10 @@
+UPDATE "Session"
+SET token_hash   = $1,
+    last_used_at = $2,
+    expires_at   = $3
+FROM "User"
+WHERE "Session".token_hash = $4
+  AND "Session".revoked_at IS NULL
+  AND "Session".expires_at > $2
+  AND "User".id = "Session".user_id
+  AND ("User".password_changed_at IS NULL OR "User".password_changed_at <= "Session".created_at) RETURNING "Session".id, "Session".user_id;
\ No newline at end of file
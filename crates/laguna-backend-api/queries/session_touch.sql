@@ -0,0 +1,9 @@
+UPDATE "Session"
+SET last_used_at = $2
+FROM "User"
+WHERE "Session".token_hash = $1
+  AND "Session".revoked_at IS NULL
+  AND "Session".expires_at > $2
+  AND "User".id = "Session".user_id
+  AND "User".is_enabled
+  AND ("User".password_changed_at IS NULL OR "User".password_changed_at <= "Session".created_at) RETURNING "Session".id;
\ No newline at end of file
@@ -0,0 +1,15 @@
+UPDATE "Torrent"
+SET seed_count      = (SELECT COUNT(*)
+                       FROM "Peer"
+                       WHERE "Peer".info_hash = "Torrent".info_hash
+                         AND behaviour NOT IN ('Stopped', 'Choked')
+                         AND COALESCE(updated_at, created_at) > $3
+                         AND left_bytes = 0),
+    leech_count     = (SELECT COUNT(*)
+                       FROM "Peer"
+                       WHERE "Peer".info_hash = "Torrent".info_hash
+                         AND behaviour NOT IN ('Stopped', 'Choked')
+                         AND COALESCE(updated_at, created_at) > $3
+                         AND left_bytes > 0),
+    completed_count = completed_count + $2
+WHERE info_hash = ANY ($1);
\ No newline at end of file
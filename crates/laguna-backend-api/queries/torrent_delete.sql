@@ -1,7 +1,9 @@
 DELETE
 FROM "Torrent"
-WHERE info_hash = $1 RETURNING
-    info_hash,
+WHERE info_hash = $1
+   OR info_hash_v2 = $1 RETURNING
+    CASE octet_length($1::BYTEA) WHEN 32 THEN info_hash_v2 ELSE info_hash END AS "info_hash!",
+    info_hash_v2 AS "info_hash_v2: InfoHash<SHA256_LENGTH>",
     raw,
     announce_url,
     length,
@@ -18,5 +20,8 @@ WHERE info_hash = $1 RETURNING
     uploaded_at,
     uploaded_by,
     modded_at,
-    modded_by
+    modded_by,
+    deleted_at,
+    deleted_by,
+    delete_reason
 ;
\ No newline at end of file
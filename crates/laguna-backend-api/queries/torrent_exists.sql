@@ -0,0 +1,4 @@
+SELECT EXISTS(SELECT 1
+              FROM "Torrent"
+              WHERE info_hash = $1
+                 OR info_hash_v2 = $2) AS "exists!";
\ No newline at end of file
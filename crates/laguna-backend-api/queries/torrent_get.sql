@@ -1,4 +1,5 @@
-SELECT info_hash,
+SELECT CASE octet_length($1::BYTEA) WHEN 32 THEN info_hash_v2 ELSE info_hash END AS "info_hash!",
+       info_hash_v2 AS "info_hash_v2: InfoHash<SHA256_LENGTH>",
        raw,
        announce_url,
        length,
@@ -15,6 +16,10 @@ SELECT info_hash,
        uploaded_at,
        uploaded_by,
        modded_at,
-       modded_by
+       modded_by,
+       deleted_at,
+       deleted_by,
+       delete_reason
 FROM "Torrent"
-WHERE info_hash = $1;
+WHERE (info_hash = $1 OR info_hash_v2 = $1)
+  AND deleted_at IS NULL;
\ No newline at end of file
@@ -15,7 +15,8 @@ INSERT INTO "Torrent" (info_hash,
                        uploaded_at,
                        uploaded_by,
                        modded_at,
-                       modded_by)
+                       modded_by,
+                       info_hash_v2)
 VALUES ($1,
         $2,
         $3,
@@ -33,8 +34,10 @@ VALUES ($1,
         $15,
         $16,
         $17,
-        $18) RETURNING
+        $18,
+        $19) RETURNING
     info_hash,
+    info_hash_v2 AS "info_hash_v2: InfoHash<SHA256_LENGTH>",
     raw,
     announce_url,
     length,
@@ -51,5 +54,8 @@ VALUES ($1,
     uploaded_at,
     uploaded_by,
     modded_at,
-    modded_by
+    modded_by,
+    deleted_at,
+    deleted_by,
+    delete_reason
 ;
\ No newline at end of file
@@ -0,0 +1,8 @@
+SELECT info_hash,
+       deleted_at IS NOT NULL AS "is_trashed!",
+       delete_reason
+FROM "Torrent"
+WHERE info_hash = $1
+   OR info_hash_v2 = $1
+   OR substring(info_hash_v2 FROM 1 FOR 20) = $1
+FOR UPDATE;
\ No newline at end of file
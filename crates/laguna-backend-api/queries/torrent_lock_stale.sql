@@ -0,0 +1,8 @@
+SELECT info_hash
+FROM "Torrent"
+WHERE info_hash IN (SELECT info_hash
+                    FROM "Peer"
+                    WHERE behaviour != 'Stopped'
+                      AND COALESCE(updated_at, created_at) <= $1)
+ORDER BY info_hash
+FOR UPDATE;
\ No newline at end of file
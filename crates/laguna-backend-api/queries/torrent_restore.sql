@@ -0,0 +1,29 @@
+UPDATE "Torrent"
+SET deleted_at    = NULL,
+    deleted_by    = NULL,
+    delete_reason = NULL
+WHERE (info_hash = $1 OR info_hash_v2 = $1)
+  AND deleted_at IS NOT NULL RETURNING
+    CASE octet_length($1::BYTEA) WHEN 32 THEN info_hash_v2 ELSE info_hash END AS "info_hash!",
+    info_hash_v2 AS "info_hash_v2: InfoHash<SHA256_LENGTH>",
+    raw,
+    announce_url,
+    length,
+    file_name,
+    nfo,
+    genre AS "genre: Genre",
+    leech_count,
+    seed_count,
+    completed_count,
+    speedlevel AS "speedlevel: SpeedLevel",
+    is_freeleech,
+    creation_date,
+    created_by,
+    uploaded_at,
+    uploaded_by,
+    modded_at,
+    modded_by,
+    deleted_at,
+    deleted_by,
+    delete_reason
+;
\ No newline at end of file
@@ -0,0 +1,5 @@
+-- Snapshot of current values has to be taken before torrent is updated.
+INSERT INTO "TorrentRevision" (info_hash, old_nfo, new_nfo, old_genre, new_genre, modded_at, modded_by)
+SELECT info_hash, nfo, $2, genre, $3, $4, $5
+FROM "Torrent"
+WHERE info_hash = $1;
\ No newline at end of file
@@ -0,0 +1,13 @@
+SELECT "TorrentRevision".id,
+       CASE octet_length($1::BYTEA) WHEN 32 THEN "Torrent".info_hash_v2 ELSE "Torrent".info_hash END AS "info_hash!",
+       "TorrentRevision".old_nfo,
+       "TorrentRevision".new_nfo,
+       "TorrentRevision".old_genre AS "old_genre: Genre",
+       "TorrentRevision".new_genre AS "new_genre: Genre",
+       "TorrentRevision".modded_at,
+       "TorrentRevision".modded_by
+FROM "TorrentRevision"
+         JOIN "Torrent" ON "Torrent".info_hash = "TorrentRevision".info_hash
+WHERE "Torrent".info_hash = $1
+   OR "Torrent".info_hash_v2 = $1
+ORDER BY "TorrentRevision".modded_at DESC;
\ No newline at end of file
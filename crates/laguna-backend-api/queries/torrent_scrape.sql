@@ -0,0 +1,17 @@
+SELECT CASE octet_length(($1::BYTEA[])[1]) WHEN 32 THEN info_hash_v2 ELSE info_hash END AS "info_hash!",
+       (SELECT COUNT(*)
+        FROM "Peer"
+        WHERE "Peer".info_hash = "Torrent".info_hash
+          AND behaviour NOT IN ('Stopped', 'Choked')
+          AND COALESCE(updated_at, created_at) > $2
+          AND left_bytes = 0)   AS "complete!",
+       (SELECT COUNT(*)
+        FROM "Peer"
+        WHERE "Peer".info_hash = "Torrent".info_hash
+          AND behaviour NOT IN ('Stopped', 'Choked')
+          AND COALESCE(updated_at, created_at) > $2
+          AND left_bytes > 0)   AS "incomplete!",
+       completed_count::BIGINT AS "downloaded!"
+FROM "Torrent"
+WHERE (info_hash = ANY ($1) OR info_hash_v2 = ANY ($1))
+  AND deleted_at IS NULL;
\ No newline at end of file
@@ -0,0 +1,41 @@
+SELECT "Torrent".info_hash AS "info_hash: InfoHash<SHA1_LENGTH>",
+       info_hash_v2 AS "info_hash_v2: InfoHash<SHA256_LENGTH>",
+       announce_url,
+       length,
+       file_name,
+       nfo,
+       genre        AS "genre: Genre",
+       leech_count,
+       seed_count,
+       completed_count,
+       speedlevel   AS "speedlevel: SpeedLevel",
+       is_freeleech,
+       creation_date,
+       created_by,
+       uploaded_at,
+       uploaded_by,
+       modded_at,
+       modded_by,
+       sort_key     AS "sort_key!"
+FROM "Torrent"
+         LEFT JOIN (SELECT info_hash, AVG(rating)::FLOAT8 AS average
+                    FROM "Rating"
+                    GROUP BY info_hash) AS rating ON rating.info_hash = "Torrent".info_hash
+         -- Descending order is ascending order of negated key, so single keyset condition works for both.
+         CROSS JOIN LATERAL (SELECT CASE $8::TEXT
+                                        WHEN 'seeders' THEN seed_count::FLOAT8
+                                        WHEN 'completed' THEN completed_count::FLOAT8
+                                        WHEN 'rating' THEN COALESCE(rating.average, 0)
+                                        ELSE EXTRACT(EPOCH FROM uploaded_at)::FLOAT8
+                                        END * CASE WHEN $9::BOOLEAN THEN -1 ELSE 1 END AS sort_key) AS sort
+WHERE deleted_at IS NULL
+  AND ($1::TEXT IS NULL OR search @@ websearch_to_tsquery('simple', $1))
+  AND ($2::Genre IS NULL OR genre = $2)
+  AND ($3::SpeedLevel IS NULL OR speedlevel = $3)
+  AND ($4::BOOLEAN IS NULL OR is_freeleech = $4)
+  AND ($5::UUID IS NULL OR uploaded_by = $5)
+  AND ($6::TIMESTAMPTZ IS NULL OR uploaded_at >= $6)
+  AND ($7::TIMESTAMPTZ IS NULL OR uploaded_at < $7)
+  AND ($10::FLOAT8 IS NULL OR (sort_key, "Torrent".info_hash) > ($10, $11::BYTEA))
+ORDER BY sort_key, "Torrent".info_hash
+LIMIT $12;
\ No newline at end of file
@@ -1,18 +1,20 @@
-SELECT uuid,
-       id,
-       md5_hash,
-       info_hash,
-       ip,
-       port,
-       is_origin,
-       agent,
-       uploaded_bytes,
-       downloaded_bytes,
-       left_bytes,
-       behaviour AS "behaviour: Behaviour",
-       created_at,
-       updated_at,
-       created_by
+SELECT "Peer".uuid,
+       "Peer".id,
+       "Peer".md5_hash,
+       CASE octet_length($1::BYTEA) WHEN 32 THEN "Torrent".info_hash_v2 ELSE "Peer".info_hash END AS "info_hash!",
+       "Peer".ip,
+       "Peer".port,
+       "Peer".is_origin,
+       "Peer".agent,
+       "Peer".uploaded_bytes,
+       "Peer".downloaded_bytes,
+       "Peer".left_bytes,
+       "Peer".behaviour AS "behaviour: Behaviour",
+       "Peer".created_at,
+       "Peer".updated_at,
+       "Peer".created_by
 FROM "Peer"
-WHERE info_hash = $1
-  AND behaviour NOT IN ('Stopped', 'Choked');
\ No newline at end of file
+JOIN "Torrent" ON "Torrent".info_hash = "Peer".info_hash
+WHERE ("Torrent".info_hash = $1 OR "Torrent".info_hash_v2 = $1)
+  AND "Peer".behaviour NOT IN ('Stopped', 'Choked')
+  AND COALESCE("Peer".updated_at, "Peer".created_at) > $2;
\ No newline at end of file
@@ -16,4 +16,5 @@ SELECT uuid,
 FROM "Peer"
 WHERE info_hash = $1
   AND id != $2
-  AND behaviour NOT IN ('Stopped', 'Choked');
\ No newline at end of file
+  AND behaviour NOT IN ('Stopped', 'Choked')
+  AND COALESCE(updated_at, created_at) > $3;
\ No newline at end of file
@@ -1,8 +1,12 @@
 UPDATE "Torrent"
-SET nfo   = $1,
-    genre = $2
-WHERE info_hash = $3 RETURNING
-    info_hash,
+SET nfo       = $1,
+    genre     = $2,
+    modded_at = $4,
+    modded_by = $5
+WHERE info_hash = $3
+   OR info_hash_v2 = $3 RETURNING
+    CASE octet_length($3::BYTEA) WHEN 32 THEN info_hash_v2 ELSE info_hash END AS "info_hash!",
+    info_hash_v2 AS "info_hash_v2: InfoHash<SHA256_LENGTH>",
     raw,
     announce_url,
     length,
@@ -19,5 +23,8 @@ WHERE info_hash = $3 RETURNING
     uploaded_at,
     uploaded_by,
     modded_at,
-    modded_by
+    modded_by,
+    deleted_at,
+    deleted_by,
+    delete_reason
 ;
\ No newline at end of file
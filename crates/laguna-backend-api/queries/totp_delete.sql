@@ -0,0 +1,4 @@
+DELETE
+FROM "Totp"
+WHERE user_id = $1
+  AND confirmed_at IS NOT NULL RETURNING user_id;
\ No newline at end of file
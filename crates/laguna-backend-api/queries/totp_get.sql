@@ -0,0 +1,7 @@
+SELECT user_id,
+       secret,
+       created_at,
+       confirmed_at,
+       last_used_step
+FROM "Totp"
+WHERE user_id = $1;
\ No newline at end of file
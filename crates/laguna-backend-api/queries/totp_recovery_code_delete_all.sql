@@ -0,0 +1,3 @@
+DELETE
+FROM "TotpRecoveryCode"
+WHERE user_id = $1;
\ No newline at end of file
@@ -0,0 +1,3 @@
+INSERT INTO "TotpRecoveryCode" (user_id, code_hash)
+SELECT $1, code_hash
+FROM UNNEST($2::BYTEA[]) AS code_hash;
\ No newline at end of file
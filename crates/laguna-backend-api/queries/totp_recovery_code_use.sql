@@ -0,0 +1,5 @@
+UPDATE "TotpRecoveryCode"
+SET used_at = $3
+WHERE user_id = $1
+  AND code_hash = $2
+  AND used_at IS NULL RETURNING id;
\ No newline at end of file
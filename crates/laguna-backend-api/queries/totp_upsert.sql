@@ -0,0 +1,6 @@
+INSERT INTO "Totp" (user_id, secret, created_at)
+VALUES ($1, $2, $3)
+ON CONFLICT (user_id) DO UPDATE SET secret         = $2,
+                                    created_at     = $3,
+                                    last_used_step = NULL
+WHERE "Totp".confirmed_at IS NULL RETURNING user_id;
\ No newline at end of file
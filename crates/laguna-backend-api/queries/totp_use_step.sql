@@ -0,0 +1,5 @@
+UPDATE "Totp"
+SET last_used_step = $2,
+    confirmed_at   = COALESCE(confirmed_at, $3)
+WHERE user_id = $1
+  AND (last_used_step IS NULL OR last_used_step < $2) RETURNING user_id;
\ No newline at end of file
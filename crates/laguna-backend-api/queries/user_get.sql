@@ -12,6 +12,7 @@ SELECT id,
        is_enabled,
        is_donator,
        has_verified_email,
-       is_profile_private
+       is_profile_private,
+       passkey   AS "passkey: Passkey"
 FROM "User"
 WHERE id = $1;
\ No newline at end of file
@@ -40,5 +40,6 @@ VALUES ($1,
     is_enabled,
     is_donator,
     has_verified_email,
-    is_profile_private
+    is_profile_private,
+    passkey AS "passkey: Passkey"
 ;
\ No newline at end of file
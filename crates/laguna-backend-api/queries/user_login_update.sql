@@ -15,5 +15,6 @@ WHERE id = $2 RETURNING
     is_enabled,
     is_donator,
     has_verified_email,
-    is_profile_private
+    is_profile_private,
+    passkey AS "passkey: Passkey"
 ;
\ No newline at end of file
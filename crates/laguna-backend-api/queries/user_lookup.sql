@@ -12,7 +12,8 @@ SELECT id,
        is_enabled,
        is_donator,
        has_verified_email,
-       is_profile_private
+       is_profile_private,
+       passkey   AS "passkey: Passkey"
 FROM "User"
 WHERE username = $1
    OR email = $2;
\ No newline at end of file
@@ -0,0 +1,18 @@
+SELECT id,
+       username,
+       email,
+       password,
+       first_login,
+       last_login,
+       avatar_url,
+       salt,
+       role      AS "role: Role",
+       hnr_count,
+       behaviour AS "behaviour: Behaviour",
+       is_enabled,
+       is_donator,
+       has_verified_email,
+       is_profile_private,
+       passkey   AS "passkey: Passkey"
+FROM "User"
+WHERE passkey = $1;
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT passkey
+FROM "User"
+WHERE id = $1;
\ No newline at end of file
@@ -0,0 +1,3 @@
+UPDATE "User"
+SET passkey = DEFAULT
+WHERE id = $1 RETURNING passkey;
\ No newline at end of file
@@ -0,0 +1,23 @@
+UPDATE "User"
+SET password            = $1,
+    salt                = $2,
+    password_changed_at = $3
+WHERE id = $4
+  AND password = $5 RETURNING
+    id,
+    username,
+    email,
+    password,
+    first_login,
+    last_login,
+    avatar_url,
+    salt,
+    role AS "role: Role",
+    hnr_count,
+    behaviour AS "behaviour: Behaviour",
+    is_enabled,
+    is_donator,
+    has_verified_email,
+    is_profile_private,
+    passkey AS "passkey: Passkey"
+;
\ No newline at end of file
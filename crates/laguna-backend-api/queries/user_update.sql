@@ -17,5 +17,6 @@ WHERE id = $4 RETURNING
     is_enabled,
     is_donator,
     has_verified_email,
-    is_profile_private
+    is_profile_private,
+    passkey AS "passkey: Passkey"
 ;
\ No newline at end of file
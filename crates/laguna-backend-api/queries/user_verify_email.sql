@@ -0,0 +1,21 @@
+UPDATE "User"
+SET has_verified_email = TRUE
+WHERE id = $1
+  AND email = $2 RETURNING
+    id,
+    username,
+    email,
+    password,
+    first_login,
+    last_login,
+    avatar_url,
+    salt,
+    role AS "role: Role",
+    hnr_count,
+    behaviour AS "behaviour: Behaviour",
+    is_enabled,
+    is_donator,
+    has_verified_email,
+    is_profile_private,
+    passkey AS "passkey: Passkey"
+;
\ No newline at end of file
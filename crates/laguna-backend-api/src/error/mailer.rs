@@ -0,0 +1,39 @@
+use actix_web::body::BoxBody;
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use actix_web::{http::header::ContentType, HttpResponse};
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MailerError {
+  InvalidAddress(String),
+  /// Reason is only logged, it may contain transport details.
+  NotSent(String),
+}
+
+impl fmt::Display for MailerError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidAddress(address) => {
+        f.write_fmt(format_args!("Neveljaven elektronski naslov {}.", address))
+      },
+      Self::NotSent(_) => f.write_str("Elektronsko sporočilo ni bilo poslano."),
+    }
+  }
+}
+
+impl ResponseError for MailerError {
+  fn status_code(&self) -> StatusCode {
+    match self {
+      Self::InvalidAddress(_) => StatusCode::BAD_REQUEST,
+      Self::NotSent(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+  }
+
+  fn error_response(&self) -> HttpResponse<BoxBody> {
+    HttpResponse::build(self.status_code())
+      .content_type(ContentType::plaintext())
+      .body(self.to_string())
+  }
+}
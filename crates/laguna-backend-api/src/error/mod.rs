@@ -1,4 +1,4 @@
-pub mod download;
+pub mod mailer;
 pub mod peer;
 pub mod rating;
 pub mod torrent;
@@ -15,7 +15,6 @@ use core::fmt;
 use std::fmt::Formatter;
 use std::io;
 
-use self::download::DownloadError;
 use self::rating::RatingError;
 
 #[derive(Debug)]
@@ -28,7 +27,7 @@ pub enum APIError {
   BencodeEncodeError(encoding::Error),
   TorrentError(torrent::TorrentError),
   RatingError(rating::RatingError),
-  DownloadError(download::DownloadError),
+  MailerError(mailer::MailerError),
 }
 
 impl From<io::Error> for APIError {
@@ -79,9 +78,9 @@ impl From<RatingError> for APIError {
   }
 }
 
-impl From<DownloadError> for APIError {
-  fn from(value: DownloadError) -> Self {
-    Self::DownloadError(value)
+impl From<mailer::MailerError> for APIError {
+  fn from(value: mailer::MailerError) -> Self {
+    Self::MailerError(value)
   }
 }
 
@@ -100,7 +99,7 @@ impl fmt::Display for APIError {
         f.write_fmt(format_args!("{}", bencode_encode_error))
       },
       Self::RatingError(rating_error) => f.write_fmt(format_args!("{}", rating_error)),
-      Self::DownloadError(download_error) => f.write_fmt(format_args!("{}", download_error)),
+      Self::MailerError(mailer_error) => f.write_fmt(format_args!("{}", mailer_error)),
     }
   }
 }
@@ -116,7 +115,7 @@ impl ResponseError for APIError {
       Self::BencodeDecodeError(_) => StatusCode::UNPROCESSABLE_ENTITY,
       Self::BencodeEncodeError(_) => StatusCode::UNPROCESSABLE_ENTITY,
       Self::RatingError(rating_error) => rating_error.status_code(),
-      Self::DownloadError(download_error) => download_error.status_code(),
+      Self::MailerError(mailer_error) => mailer_error.status_code(),
     }
   }
 
@@ -138,7 +137,7 @@ impl ResponseError for APIError {
         .content_type(ContentType::plaintext())
         .body(bencode_encode_error.to_string()),
       Self::RatingError(rating_error) => rating_error.error_response(),
-      Self::DownloadError(download_error) => download_error.error_response(),
+      Self::MailerError(mailer_error) => mailer_error.error_response(),
     }
   }
 }
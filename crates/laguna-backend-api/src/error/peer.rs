@@ -17,9 +17,17 @@ use std::fmt::Formatter;
 #[derive(Debug)]
 pub enum PeerError<const N: usize> {
   NotFound(PeerId),
+  PeerIdTaken(PeerId),
+  TooManyInfoHashes(usize),
   DownloadNotFound(DownloadHash),
+  MissingPasskey,
+  UnknownPasskey,
+  MissingField(&'static str),
+  AnnounceTooFrequent(u64),
   UnknownTorrent(InfoHash<N>),
+  TrashedTorrent(String),
   UnknownUser(Uuid),
+  DisabledUser,
   UnexpectedEvent {
     event: AnnounceEvent,
     message: String,
@@ -61,6 +69,9 @@ impl<const N: usize> fmt::Display for PeerError<N> {
         "Torrent z info_hash {} ne obstaja na strežniku. Za dodajanje torrenta uporabite `api/torrent/put`.",
         info_hash
       )),
+      Self::TrashedTorrent(reason) => {
+        f.write_fmt(format_args!("Torrent je bil odstranjen: {}.", reason))
+      },
       // NOTE: Don't output this.
       Self::SqlxError(_) => f.write_str("Napaka v PB."),
       Self::BencodeDecodeError(_) => f.write_str("Napaka pri dekodiranju bencode."),
@@ -68,13 +79,32 @@ impl<const N: usize> fmt::Display for PeerError<N> {
       Self::NotFound(peer_id) => {
         f.write_fmt(format_args!("Peer z peer_id {} ne obstaja.", peer_id))
       },
+      Self::PeerIdTaken(peer_id) => f.write_fmt(format_args!(
+        "Peer z peer_id {} je že povezan na drugi povezavi.",
+        peer_id
+      )),
+      Self::TooManyInfoHashes(max) => f.write_fmt(format_args!(
+        "Preveč info_hash v scrape. Največje dovoljeno število je {}.",
+        max
+      )),
       Self::DownloadNotFound(download_hash) => {
         f.write_fmt(format_args!("Torrent download hash {} ne obstaja.", download_hash))
       },
+      Self::MissingField(field) => f.write_fmt(format_args!("Manjka polje {}.", field)),
+      Self::AnnounceTooFrequent(min_interval) => f.write_fmt(format_args!(
+        "Prepogost announce. Najmanjši interval je {} sekund.",
+        min_interval
+      )),
+      Self::MissingPasskey => f.write_str("Manjka passkey v announce URL."),
+      // NOTE: Don't output passkey, it is a secret.
+      Self::UnknownPasskey => {
+        f.write_str("Neveljaven passkey. Ponovno prenesite torrent.")
+      },
       Self::NotUpdated => f.write_str("Peer ni bil posodobljen."),
       Self::UnknownUser(id) => {
         f.write_fmt(format_args!("Uporabnik z id {} ne obstaja.", id))
-      }
+      },
+      Self::DisabledUser => f.write_str("Uporabniški račun je onemogočen."),
     }
   }
 }
@@ -99,6 +129,7 @@ impl<const N: usize> ResponseError for PeerError<N> {
           min_interval: None,
           tracker_id: None,
           peers: PeerStream::Dict(vec![]),
+          peers6: None,
         }
         .to_bencode()
         .unwrap(),
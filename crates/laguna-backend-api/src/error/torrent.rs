@@ -8,33 +8,112 @@ use std::fmt::Formatter;
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TorrentError {
   NotFound,
+  RevisionNotFound,
   NotCreated,
   NotUpdated,
   Invalid,
+  Metainfo(Vec<MetainfoError>),
+}
+
+/// Violated rule of uploaded torrent metainfo.
+/// Serialized as `{"rule": "<rule>", "details": <details>}`, rules without details have none.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", content = "details", rename_all = "snake_case")]
+pub enum MetainfoError {
+  /// Length of `pieces` (in bytes) is not a multiple of 20.
+  PiecesLength(usize),
+  /// `piece length` is not a positive power of two.
+  PieceLength(i64),
+  /// Number of `pieces` doesn't match number of pieces needed for total length.
+  PieceCount { expected: i64, actual: i64 },
+  /// File has negative length.
+  FileLength { path: Vec<String>, length: i64 },
+  /// Total length of files doesn't fit in 64 bits.
+  TotalLength,
+  /// File path is empty or has empty, `.`, `..` or separator containing components.
+  FilePath(Vec<String>),
+  /// Torrent has more files than allowed.
+  FileCount { max: usize, actual: usize },
+  /// `encoding` is not UTF-8.
+  Encoding(String),
 }
 
 impl fmt::Display for TorrentError {
   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
     match self {
       Self::NotFound => f.write_str("Torrent ne obstaja."),
+      Self::RevisionNotFound => f.write_str("Revizija torrenta ne obstaja."),
       Self::NotCreated => f.write_str("Torrent ni bil ustvarjen."),
       Self::NotUpdated => f.write_str("Torrent ni bil posodobljen."),
       Self::Invalid => f.write_str("Torrent ni v pravilni obliki."),
+      Self::Metainfo(metainfo_errors) => {
+        f.write_str("Torrent ni v pravilni obliki.")?;
+        for metainfo_error in metainfo_errors {
+          f.write_fmt(format_args!(" {}", metainfo_error))?;
+        }
+        Ok(())
+      },
     }
   }
 }
 
+impl fmt::Display for MetainfoError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::PiecesLength(length) => f.write_fmt(format_args!(
+        "Dolžina pieces ({}) ni večkratnik 20.",
+        length
+      )),
+      Self::PieceLength(piece_length) => f.write_fmt(format_args!(
+        "Piece length ({}) ni pozitivna potenca števila 2.",
+        piece_length
+      )),
+      Self::PieceCount { expected, actual } => f.write_fmt(format_args!(
+        "Število kosov ({}) se ne ujema z velikostjo torrenta (pričakovano {}).",
+        actual, expected
+      )),
+      Self::FileLength { path, length } => f.write_fmt(format_args!(
+        "Datoteka {} ima negativno dolžino ({}).",
+        path.join("/"),
+        length
+      )),
+      Self::TotalLength => f.write_str("Skupna dolžina datotek je prevelika."),
+      Self::FilePath(path) => f.write_fmt(format_args!("Neveljavna pot datoteke {:?}.", path)),
+      Self::FileCount { max, actual } => f.write_fmt(format_args!(
+        "Preveč datotek ({}). Največje dovoljeno število je {}.",
+        actual, max
+      )),
+      Self::Encoding(encoding) => f.write_fmt(format_args!(
+        "Kodiranje {} ni podprto. Podprto je le UTF-8.",
+        encoding
+      )),
+    }
+  }
+}
+
+impl From<Vec<MetainfoError>> for TorrentError {
+  fn from(metainfo_errors: Vec<MetainfoError>) -> Self {
+    Self::Metainfo(metainfo_errors)
+  }
+}
+
 impl ResponseError for TorrentError {
   fn status_code(&self) -> StatusCode {
     match self {
       Self::NotFound => StatusCode::BAD_REQUEST,
+      Self::RevisionNotFound => StatusCode::BAD_REQUEST,
       Self::NotCreated => StatusCode::BAD_REQUEST,
       Self::NotUpdated => StatusCode::BAD_REQUEST,
       Self::Invalid => StatusCode::UNPROCESSABLE_ENTITY,
+      Self::Metainfo(_) => StatusCode::UNPROCESSABLE_ENTITY,
     }
   }
 
   fn error_response(&self) -> HttpResponse<BoxBody> {
+    // Violations are returned one by one, so that clients can tell which rules were broken.
+    if let Self::Metainfo(metainfo_errors) = self {
+      return HttpResponse::build(self.status_code()).json(metainfo_errors);
+    }
     HttpResponse::build(self.status_code())
       .content_type(ContentType::plaintext())
       .body(self.to_string())
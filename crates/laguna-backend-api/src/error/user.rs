@@ -1,7 +1,10 @@
 use actix_web::body::BoxBody;
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{
+  http::header::{self, ContentType},
+  HttpResponse,
+};
 use laguna_backend_model::role::Role;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -14,6 +17,22 @@ pub enum UserError {
   Exclusive,
   NotCreated,
   NotUpdated,
+  InvalidToken,
+  SessionRevoked,
+  SessionNotFound,
+  Disabled,
+  BanNotAllowed {
+    banner: Role,
+    bannee: Role,
+  },
+  InvalidTotpCode,
+  TotpAlreadyEnabled,
+  TotpNotEnabled,
+  TotpRequired,
+  /// Too many failed logins of account or from IP address.
+  LoginLocked {
+    retry_after_seconds: i64,
+  },
   RoleChangeNotAllowed {
     changer: Role,
     changee_from: Role,
@@ -31,6 +50,24 @@ impl fmt::Display for UserError {
       Self::NotFound => f.write_str("Zahtevan uporabnik ne obstaja."),
       Self::NotCreated => f.write_str("Uporabnik ni bil ustvarjen."),
       Self::NotUpdated => f.write_str("Uporabnik ni bil posodobljen."),
+      Self::InvalidToken => f.write_str("Žeton je neveljaven ali potekel."),
+      Self::SessionRevoked => f.write_str("Seja ni več veljavna. Ponovno se prijavite."),
+      Self::SessionNotFound => f.write_str("Zahtevana seja ne obstaja."),
+      Self::Disabled => f.write_str("Uporabniški račun je onemogočen."),
+      Self::BanNotAllowed { banner, bannee } => f.write_fmt(format_args!(
+        "Kot {:?} onemogočanje uporabnika z rolo {:?} ni dovoljeno.",
+        banner, bannee
+      )),
+      Self::InvalidTotpCode => f.write_str("Koda za dvostopenjsko preverjanje je napačna."),
+      Self::TotpAlreadyEnabled => f.write_str("Dvostopenjsko preverjanje je že vklopljeno."),
+      Self::TotpNotEnabled => f.write_str("Dvostopenjsko preverjanje ni vklopljeno."),
+      Self::TotpRequired => f.write_str("Dvostopenjsko preverjanje je obvezno."),
+      Self::LoginLocked {
+        retry_after_seconds,
+      } => f.write_fmt(format_args!(
+        "Preveč neuspelih prijav. Poskusite znova čez {} s.",
+        retry_after_seconds
+      )),
       Self::RoleChangeNotAllowed {
         changer,
         changee_from,
@@ -51,12 +88,29 @@ impl ResponseError for UserError {
       Self::NotFound => StatusCode::BAD_REQUEST,
       Self::NotCreated => StatusCode::BAD_REQUEST,
       Self::NotUpdated => StatusCode::BAD_REQUEST,
+      Self::InvalidToken => StatusCode::BAD_REQUEST,
+      Self::SessionRevoked => StatusCode::UNAUTHORIZED,
+      Self::SessionNotFound => StatusCode::BAD_REQUEST,
+      Self::Disabled => StatusCode::FORBIDDEN,
+      Self::BanNotAllowed { .. } => StatusCode::FORBIDDEN,
+      Self::InvalidTotpCode => StatusCode::UNAUTHORIZED,
+      Self::TotpAlreadyEnabled => StatusCode::BAD_REQUEST,
+      Self::TotpNotEnabled => StatusCode::BAD_REQUEST,
+      Self::TotpRequired => StatusCode::FORBIDDEN,
+      Self::LoginLocked { .. } => StatusCode::TOO_MANY_REQUESTS,
       Self::RoleChangeNotAllowed { .. } => StatusCode::FORBIDDEN,
     }
   }
 
   fn error_response(&self) -> HttpResponse<BoxBody> {
-    HttpResponse::build(self.status_code())
+    let mut res = HttpResponse::build(self.status_code());
+    if let Self::LoginLocked {
+      retry_after_seconds,
+    } = self
+    {
+      res.insert_header((header::RETRY_AFTER, retry_after_seconds.to_string()));
+    }
+    res
       .content_type(ContentType::plaintext())
       .body(self.to_string())
   }
@@ -0,0 +1,104 @@
+use laguna_backend_dto::torrent::{TorrentFile, TorrentInfo};
+use laguna_backend_model::consts::TORRENT_FILE_COUNT_MAX;
+
+use crate::error::torrent::MetainfoError;
+
+/// Checks uploaded torrent against all metainfo rules, all violated rules are returned.
+pub(crate) fn validate_metainfo(torrent_file: &TorrentFile) -> Result<(), Vec<MetainfoError>> {
+  let mut errors = Vec::new();
+  validate_encoding(torrent_file, &mut errors);
+  validate_files(&torrent_file.info, &mut errors);
+  validate_pieces(&torrent_file.info, &mut errors);
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors)
+  }
+}
+
+/// Rewrites torrent to form served by tracker: private, announcing only to domestic tracker.
+/// Info dictionary changes, so info hashes have to be computed from re-encoded torrent.
+pub(crate) fn canonicalize_metainfo(torrent_file: &mut TorrentFile, domestic_announce_url: &str) {
+  torrent_file.announce_url = Some(String::from(domestic_announce_url));
+  torrent_file.announce_list = None;
+  torrent_file.nodes = None;
+  torrent_file.info.private = Some(1);
+}
+
+fn validate_encoding(torrent_file: &TorrentFile, errors: &mut Vec<MetainfoError>) {
+  if let Some(ref encoding) = torrent_file.encoding {
+    if !encoding.eq_ignore_ascii_case("UTF-8") && !encoding.eq_ignore_ascii_case("UTF8") {
+      errors.push(MetainfoError::Encoding(encoding.clone()));
+    }
+  }
+}
+
+fn validate_files(info: &TorrentInfo, errors: &mut Vec<MetainfoError>) {
+  let file_list = info.file_list();
+  if file_list.len() > TORRENT_FILE_COUNT_MAX {
+    // Files are not checked one by one, so that response size stays bounded.
+    errors.push(MetainfoError::FileCount {
+      max: TORRENT_FILE_COUNT_MAX,
+      actual: file_list.len(),
+    });
+  } else {
+    // Paths start with torrent name, so name is checked as well.
+    for (path, length) in file_list {
+      // Files of multi file torrent need at least one component besides name.
+      let is_empty = info.files.is_some() && path.len() == 1;
+      if is_empty || !path.iter().all(|component| is_valid_component(component)) {
+        errors.push(MetainfoError::FilePath(path.clone()));
+      }
+      if length < 0 {
+        errors.push(MetainfoError::FileLength { path, length });
+      }
+    }
+  }
+  if info.total_length().is_none() {
+    errors.push(MetainfoError::TotalLength);
+  }
+}
+
+/// Path components must not be able to escape download directory.
+fn is_valid_component(component: &str) -> bool {
+  !component.is_empty()
+    && component != "."
+    && component != ".."
+    && !component.contains(['/', '\\', '\0'])
+}
+
+fn validate_pieces(info: &TorrentInfo, errors: &mut Vec<MetainfoError>) {
+  let piece_length_valid =
+    info.piece_length > 0 && (info.piece_length & (info.piece_length - 1)) == 0;
+  if !piece_length_valid {
+    errors.push(MetainfoError::PieceLength(info.piece_length));
+  }
+  // v2 only torrents have piece hashes in `piece layers`, these are checked by clients.
+  let Some(ref pieces) = info.pieces else {
+    return;
+  };
+  if pieces.len() % 20 != 0 {
+    errors.push(MetainfoError::PiecesLength(pieces.len()));
+    return;
+  }
+  // Piece count is checked only with valid piece length and non-negative total length,
+  // otherwise violations were already reported.
+  let lengths_unchecked = errors.iter().any(|error| {
+    matches!(
+      error,
+      MetainfoError::FileLength { .. } | MetainfoError::FileCount { .. }
+    )
+  });
+  let Some(total_length) = info
+    .total_length()
+    .filter(|_| piece_length_valid && !lengths_unchecked)
+  else {
+    return;
+  };
+  let expected =
+    total_length / info.piece_length + i64::from(total_length % info.piece_length != 0);
+  let actual = info.piece_count();
+  if expected != actual {
+    errors.push(MetainfoError::PieceCount { expected, actual });
+  }
+}
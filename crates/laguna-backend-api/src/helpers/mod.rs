@@ -1 +1,4 @@
+pub mod metainfo;
 pub mod register;
+pub mod request;
+pub mod token;
@@ -0,0 +1,28 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use actix_web::http::header::X_FORWARDED_FOR;
+use actix_web::HttpRequest;
+use laguna_backend_config::TrustedProxies;
+use sqlx::types::ipnetwork::IpNetwork;
+
+/// Client's IP address from connection.
+/// Behind trusted proxies it is the rightmost `X-Forwarded-For` address not added by them,
+/// addresses left of it are set by client and can't be trusted.
+pub(crate) fn request_ip(req: &HttpRequest, trusted_proxies: &TrustedProxies) -> Option<IpNetwork> {
+  let mut ip = req.peer_addr()?.ip();
+  let mut forwarded_for = req
+    .headers()
+    .get_all(X_FORWARDED_FOR)
+    .filter_map(|header_value| header_value.to_str().ok())
+    .flat_map(|header_value| header_value.split(','))
+    .map(str::trim)
+    .collect::<Vec<_>>();
+  while trusted_proxies.contains(&ip) {
+    match forwarded_for.pop().map(IpAddr::from_str) {
+      Some(Ok(forwarded_ip)) => ip = forwarded_ip,
+      _ => break,
+    }
+  }
+  Some(IpNetwork::from(ip))
+}
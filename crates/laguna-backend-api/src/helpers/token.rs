@@ -0,0 +1,99 @@
+//! Signed, expiring tokens sent to users by mail or handed out between login steps.
+//!
+//! Token is `<user id>.<expiry unix timestamp>.<signature>`, signature covers token purpose and binding.
+//! Binding is user state token was issued for (for example email), once it changes token is invalid.
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::error::user::UserError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenPurpose {
+  EmailVerification,
+  PasswordReset,
+  LoginChallenge,
+}
+
+impl TokenPurpose {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Self::EmailVerification => "email-verification",
+      Self::PasswordReset => "password-reset",
+      Self::LoginChallenge => "login-challenge",
+    }
+  }
+}
+
+pub(crate) fn sign_token(
+  secret_key: &str,
+  purpose: TokenPurpose,
+  user_id: Uuid,
+  binding: &str,
+  expires_at: DateTime<Utc>,
+) -> String {
+  let expires_at = expires_at.timestamp();
+  let signature = mac(secret_key, purpose, user_id, expires_at, binding)
+    .finalize()
+    .into_bytes();
+  format!("{}.{}.{}", user_id, expires_at, hex::encode(signature))
+}
+
+/// Id of user token claims to be issued for, signature has to be checked with [`verify_token`].
+pub(crate) fn token_user_id(token: &str) -> Result<Uuid, UserError> {
+  parse_token(token).map(|(user_id, _, _)| user_id)
+}
+
+/// Checks that token is not expired and was signed for `purpose` and `binding`.
+pub(crate) fn verify_token(
+  secret_key: &str,
+  purpose: TokenPurpose,
+  token: &str,
+  binding: &str,
+) -> Result<Uuid, UserError> {
+  let (user_id, expires_at, signature) = parse_token(token)?;
+  if expires_at < Utc::now().timestamp() {
+    return Err(UserError::InvalidToken);
+  }
+  mac(secret_key, purpose, user_id, expires_at, binding)
+    .verify_slice(&signature)
+    .map_err(|_| UserError::InvalidToken)?;
+  Ok(user_id)
+}
+
+fn parse_token(token: &str) -> Result<(Uuid, i64, Vec<u8>), UserError> {
+  let mut parts = token.splitn(3, '.');
+  let (Some(user_id), Some(expires_at), Some(signature)) =
+    (parts.next(), parts.next(), parts.next())
+  else {
+    return Err(UserError::InvalidToken);
+  };
+  Ok((
+    Uuid::parse_str(user_id).map_err(|_| UserError::InvalidToken)?,
+    expires_at.parse().map_err(|_| UserError::InvalidToken)?,
+    hex::decode(signature).map_err(|_| UserError::InvalidToken)?,
+  ))
+}
+
+fn mac(
+  secret_key: &str,
+  purpose: TokenPurpose,
+  user_id: Uuid,
+  expires_at: i64,
+  binding: &str,
+) -> Hmac<Sha256> {
+  let mut mac =
+    Hmac::<Sha256>::new_from_slice(secret_key.as_bytes()).expect("HMAC accepts keys of any size");
+  mac.update(
+    format!(
+      "{}.{}.{}.{}",
+      purpose.as_str(),
+      user_id,
+      expires_at,
+      binding
+    )
+    .as_bytes(),
+  );
+  mac
+}
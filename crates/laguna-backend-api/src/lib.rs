@@ -7,10 +7,19 @@ extern crate core;
 pub mod error;
 pub mod helpers;
 pub mod login;
+pub mod mailer;
 pub mod meta;
+pub mod password;
 pub mod peer;
 pub mod rating;
+pub mod reaper;
 pub mod register;
+pub mod session;
 pub mod stats;
+pub mod throttle;
 pub mod torrent;
+pub mod totp;
+pub mod udp;
 pub mod user;
+pub mod verify;
+pub mod ws;
@@ -1,10 +1,11 @@
 use actix_jwt_auth_middleware::TokenSigner;
 
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_validator::Json;
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use chrono::Utc;
-use jwt_compact::alg::Hs256;
+use jwt_compact::alg::{Hs256, Hs256Key};
+use laguna_backend_config::AuthSettings;
 use laguna_backend_dto::login::LoginDTO;
 use laguna_backend_dto::user::UserDTO;
 use laguna_backend_middleware::{
@@ -13,12 +14,17 @@ use laguna_backend_middleware::{
 };
 use laguna_backend_model::behaviour::Behaviour;
 use laguna_backend_model::role::Role;
-use laguna_backend_model::user::{User, UserSafe};
+use laguna_backend_model::user::{Passkey, User, UserSafe};
 
 use secrecy::ExposeSecret;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 use crate::error::{user::UserError, APIError};
+use crate::helpers::request::request_ip;
+use crate::session::create_session;
+use crate::throttle::{clear_login_failures, forgive_login_attempt, record_login_attempt};
+use crate::totp::login_challenge;
 
 #[utoipa::path(
   post,
@@ -28,15 +34,21 @@ use crate::error::{user::UserError, APIError};
       ("X-Access-Token" = String, description = "Access token."),
       ("X-Refresh-Token" = String, description = "Refresh token.")
     )),
+    (status = 202, description = "Password is correct, TOTP code is required to log in, see `/api/user/auth/login/totp`.", body = LoginChallengeDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 400, description = "Bad request.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
-    (status = 401, description = "Invalid credentials.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json")
+    (status = 401, description = "Invalid credentials.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 429, description = "Too many failed logins, retry after time in Retry-After header.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 403, description = "User is disabled.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json")
   ),
 )]
 pub async fn login(
+  req: HttpRequest,
   login_dto: Json<LoginDTO>,
   pool: web::Data<PgPool>,
   signer: web::Data<TokenSigner<UserDTO, Hs256>>,
+  secret_key: web::Data<Hs256Key>,
   argon_context: web::Data<Argon2<'static>>,
+  auth_settings: web::Data<AuthSettings>,
 ) -> Result<HttpResponse, APIError> {
   let user = sqlx::query_file_as!(
     User,
@@ -46,8 +58,19 @@ pub async fn login(
   )
   .fetch_optional(pool.get_ref())
   .await?
-  .map(UserSafe::from)
-  .ok_or(UserError::InvalidCredentials)?;
+  .map(UserSafe::from);
+
+  let ip = request_ip(&req, &auth_settings.trusted_proxies);
+  let attempted_at = record_login_attempt(
+    pool.get_ref(),
+    auth_settings.get_ref(),
+    ip,
+    user.as_ref().map(|user| user.id),
+  )
+  .await?;
+  let Some(user) = user else {
+    return Err(UserError::InvalidCredentials.into());
+  };
 
   let password_hash = PasswordHash::new(user.password.expose_secret()).unwrap();
   if argon_context
@@ -59,25 +82,67 @@ pub async fn login(
     // return Ok(HttpResponse::Unauthorized().body("Uporabniško ime ali geslo napačno"));
   }
 
+  forgive_login_attempt(
+    pool.get_ref(),
+    auth_settings.get_ref(),
+    ip,
+    Some(user.id),
+    attempted_at,
+  )
+  .await?;
+
+  // Checked only after password, so it doesn't reveal which accounts are disabled.
+  if !user.is_enabled {
+    return Err(UserError::Disabled.into());
+  }
+
+  if let Some(login_challenge_dto) =
+    login_challenge(pool.get_ref(), auth_settings.get_ref(), &user).await?
+  {
+    return Ok(
+      HttpResponse::Accepted()
+        .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+        .json(login_challenge_dto),
+    );
+  }
+
+  login_complete(
+    pool.get_ref(),
+    signer.get_ref(),
+    secret_key.get_ref(),
+    auth_settings.get_ref(),
+    &req,
+    user.id,
+  )
+  .await
+}
+
+/// Last login step, forgets failed logins, updates last login and starts new session.
+pub(crate) async fn login_complete(
+  pool: &PgPool,
+  signer: &TokenSigner<UserDTO, Hs256>,
+  secret_key: &Hs256Key,
+  auth_settings: &AuthSettings,
+  req: &HttpRequest,
+  user_id: Uuid,
+) -> Result<HttpResponse, APIError> {
+  clear_login_failures(pool, user_id).await?;
+
   // Update last_login
-  let user = sqlx::query_file_as!(User, "queries/user_login_update.sql", Utc::now(), user.id)
-    .fetch_optional(pool.get_ref())
+  let user = sqlx::query_file_as!(User, "queries/user_login_update.sql", Utc::now(), user_id)
+    .fetch_optional(pool)
     .await?
     .map(UserSafe::from)
     .map(UserDTO::from)
     .ok_or(UserError::NotUpdated)?;
 
+  let (access_token, refresh_token) =
+    create_session(pool, signer, secret_key, auth_settings, req, &user).await?;
+
   Ok(
     HttpResponse::Ok()
-      // TODO: get rid of clones
-      .append_header((
-        ACCESS_TOKEN_HEADER_NAME,
-        signer.create_access_header_value(&user.clone())?,
-      ))
-      .append_header((
-        REFRESH_TOKEN_HEADER_NAME,
-        signer.create_refresh_header_value(&user.clone())?,
-      ))
+      .append_header((ACCESS_TOKEN_HEADER_NAME, access_token))
+      .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token))
       .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
       .json(user),
   )
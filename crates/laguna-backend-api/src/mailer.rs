@@ -0,0 +1,156 @@
+//! Outgoing mail, delivered by transport chosen in [`MailSettings`].
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::web;
+use futures::future::BoxFuture;
+use laguna_backend_config::{MailSettings, MailTransport};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+
+use crate::error::mailer::MailerError;
+
+/// Plaintext mail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+  pub to: String,
+  pub subject: String,
+  pub body: String,
+}
+
+pub trait Mailer: Send + Sync {
+  fn send(&self, mail: Mail) -> BoxFuture<'_, Result<(), MailerError>>;
+}
+
+/// Sends mail in background and only logs failure, for responses that must not reveal
+/// whether recipient has an account, neither by status nor by time taken.
+pub(crate) fn send_detached(mailer: Arc<dyn Mailer>, mail: Mail) {
+  tokio::spawn(async move {
+    if let Err(err) = mailer.send(mail).await {
+      log::warn!("Cannot send mail: {:?}", err);
+    }
+  });
+}
+
+/// Creates mailer for transport configured in `mail_settings`.
+pub fn mailer_from_settings(mail_settings: &MailSettings) -> Arc<dyn Mailer> {
+  match mail_settings.transport {
+    MailTransport::Smtp => Arc::new(SmtpMailer::new(mail_settings)),
+    MailTransport::File => Arc::new(FileMailer::new(mail_settings.file_dir())),
+    MailTransport::Stdout => Arc::new(StdoutMailer),
+  }
+}
+
+pub struct SmtpMailer {
+  transport: AsyncSmtpTransport<Tokio1Executor>,
+  from: Mailbox,
+}
+
+impl SmtpMailer {
+  pub fn new(mail_settings: &MailSettings) -> Self {
+    let mut transport =
+      AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&mail_settings.smtp_host)
+        .expect("Cannot create SMTP transport")
+        .port(mail_settings.smtp_port);
+    if !mail_settings.smtp_username.is_empty() {
+      transport = transport.credentials(Credentials::new(
+        mail_settings.smtp_username.clone(),
+        mail_settings.smtp_password.expose_secret().clone(),
+      ));
+    }
+    Self {
+      transport: transport.build(),
+      from: mail_settings
+        .from
+        .parse()
+        .expect("Cannot parse mail sender address"),
+    }
+  }
+}
+
+impl Mailer for SmtpMailer {
+  fn send(&self, mail: Mail) -> BoxFuture<'_, Result<(), MailerError>> {
+    Box::pin(async move {
+      let to = mail
+        .to
+        .parse::<Mailbox>()
+        .map_err(|_| MailerError::InvalidAddress(mail.to.clone()))?;
+      let message = Message::builder()
+        .from(self.from.clone())
+        .to(to)
+        .subject(mail.subject)
+        .body(mail.body)
+        .map_err(|err| MailerError::NotSent(err.to_string()))?;
+      self
+        .transport
+        .send(message)
+        .await
+        .map(drop)
+        .map_err(|err| MailerError::NotSent(err.to_string()))
+    })
+  }
+}
+
+/// Appends mail to `<dir>/<recipient>.eml`.
+pub struct FileMailer {
+  dir: PathBuf,
+}
+
+impl FileMailer {
+  pub fn new(dir: PathBuf) -> Self {
+    Self { dir }
+  }
+
+  /// File all mail to `to` is appended to.
+  pub fn path(&self, to: &str) -> PathBuf {
+    self.dir.join(format!("{}.eml", to))
+  }
+}
+
+impl Mailer for FileMailer {
+  fn send(&self, mail: Mail) -> BoxFuture<'_, Result<(), MailerError>> {
+    Box::pin(async move {
+      // Recipient is used as file name, so it must not escape mail directory.
+      if mail.to.contains(['/', '\\', '\0']) {
+        return Err(MailerError::InvalidAddress(mail.to));
+      }
+      let dir = self.dir.clone();
+      let path = self.path(&mail.to);
+      let text = format_mail(&mail);
+      // File I/O blocks, so it runs on blocking thread pool instead of worker.
+      web::block(move || {
+        fs::create_dir_all(dir)?;
+        OpenOptions::new()
+          .create(true)
+          .append(true)
+          .open(path)?
+          .write_all(text.as_bytes())
+      })
+      .await
+      .map_err(|err| MailerError::NotSent(err.to_string()))?
+      .map_err(|err| MailerError::NotSent(err.to_string()))
+    })
+  }
+}
+
+pub struct StdoutMailer;
+
+impl Mailer for StdoutMailer {
+  fn send(&self, mail: Mail) -> BoxFuture<'_, Result<(), MailerError>> {
+    Box::pin(async move {
+      print!("{}", format_mail(&mail));
+      Ok(())
+    })
+  }
+}
+
+fn format_mail(mail: &Mail) -> String {
+  format!(
+    "To: {}\nSubject: {}\n\n{}\n\n",
+    mail.to, mail.subject, mail.body
+  )
+}
@@ -0,0 +1,241 @@
+use actix_jwt_auth_middleware::TokenSigner;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_validator::Json;
+use argon2::{
+  password_hash::{rand_core::OsRng, SaltString},
+  Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+};
+use chrono::{Duration, Utc};
+use jwt_compact::alg::{Hs256, Hs256Key};
+use laguna_backend_config::{AuthSettings, FrontendSettings};
+use laguna_backend_dto::password::{PasswordChangeDTO, PasswordForgotDTO, PasswordResetDTO};
+use laguna_backend_dto::user::UserDTO;
+use laguna_backend_middleware::{
+  consts::{ACCESS_TOKEN_HEADER_NAME, REFRESH_TOKEN_HEADER_NAME},
+  mime::APPLICATION_LAGUNA_JSON_VERSIONED,
+};
+use laguna_backend_model::behaviour::Behaviour;
+use laguna_backend_model::role::Role;
+use laguna_backend_model::user::{Passkey, User, UserSafe};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::error::{user::UserError, APIError};
+use crate::helpers::request::request_ip;
+use crate::helpers::token::{sign_token, token_user_id, verify_token, TokenPurpose};
+use crate::mailer::{send_detached, Mail, Mailer};
+use crate::session::{create_session, revoke_sessions};
+use crate::throttle::{forgive_login_attempt, record_login_attempt};
+
+/// Hashes plaintext password with new salt, returns hash and salt.
+pub(crate) fn hash_password(argon_context: &Argon2<'static>, password: &str) -> (String, String) {
+  let salt = SaltString::generate(&mut OsRng);
+  let password_hash = argon_context
+    .hash_password(password.as_bytes(), salt.as_salt())
+    .unwrap()
+    .to_string();
+  (password_hash, salt.to_string())
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/me/password",
+  request_body = PasswordChangeDTO,
+  responses(
+    (status = 200, description = "Password changed, other sessions have to log in again.", body = UserDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json", headers(
+      ("X-Access-Token" = String, description = "New access token."),
+      ("X-Refresh-Token" = String, description = "New refresh token.")
+    )),
+    (status = 400, description = "Bad request.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Current password is wrong or not logged in.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 429, description = "Too many wrong passwords, retry after time in Retry-After header.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn user_me_password_change(
+  req: HttpRequest,
+  password_change_dto: Json<PasswordChangeDTO>,
+  user: UserDTO,
+  pool: web::Data<PgPool>,
+  argon_context: web::Data<Argon2<'static>>,
+  signer: web::Data<TokenSigner<UserDTO, Hs256>>,
+  secret_key: web::Data<Hs256Key>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::NotFound)?;
+
+  // Counted as login attempt, so stolen session can't brute-force password.
+  let ip = request_ip(&req, &auth_settings.trusted_proxies);
+  let attempted_at =
+    record_login_attempt(pool.get_ref(), auth_settings.get_ref(), ip, Some(user.id)).await?;
+  let password_hash = PasswordHash::new(user.password.expose_secret()).unwrap();
+  if argon_context
+    .verify_password(password_change_dto.password.as_bytes(), &password_hash)
+    .is_err()
+  {
+    return Err(UserError::InvalidCredentials.into());
+  }
+  forgive_login_attempt(
+    pool.get_ref(),
+    auth_settings.get_ref(),
+    ip,
+    Some(user.id),
+    attempted_at,
+  )
+  .await?;
+
+  let user = update_password(
+    pool.get_ref(),
+    argon_context.get_ref(),
+    &user,
+    &password_change_dto.new_password,
+  )
+  .await?
+  .ok_or(UserError::NotUpdated)?;
+
+  // This session was revoked as well, client continues in new one.
+  let (access_token, refresh_token) = create_session(
+    pool.get_ref(),
+    signer.get_ref(),
+    secret_key.get_ref(),
+    auth_settings.get_ref(),
+    &req,
+    &user,
+  )
+  .await?;
+  Ok(
+    HttpResponse::Ok()
+      .append_header((ACCESS_TOKEN_HEADER_NAME, access_token))
+      .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token))
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(user),
+  )
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/password/forgot",
+  request_body = PasswordForgotDTO,
+  responses(
+    (status = 200, description = "Password reset mail sent, if user with this email exists."),
+    (status = 400, description = "Bad request.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn password_forgot(
+  password_forgot_dto: Json<PasswordForgotDTO>,
+  pool: web::Data<PgPool>,
+  auth_settings: web::Data<AuthSettings>,
+  frontend_settings: web::Data<FrontendSettings>,
+  mailer: web::Data<dyn Mailer>,
+) -> Result<HttpResponse, APIError> {
+  let user = sqlx::query_file_as!(
+    User,
+    "queries/user_lookup.sql",
+    password_forgot_dto.email,
+    password_forgot_dto.email
+  )
+  .fetch_optional(pool.get_ref())
+  .await?
+  .map(UserSafe::from);
+
+  // SECURITY: Same response whether user exists or not, so emails can't be enumerated.
+  if let Some(user) = user {
+    // Token is bound to current password hash, so it can be used only once.
+    let token = sign_token(
+      auth_settings.secret_key.expose_secret(),
+      TokenPurpose::PasswordReset,
+      user.id,
+      user.password.expose_secret(),
+      Utc::now() + Duration::seconds(auth_settings.password_reset_token_lifetime_seconds),
+    );
+    send_detached(
+      mailer.into_inner(),
+      Mail {
+        to: user.email.expose_secret().clone(),
+        subject: String::from("Ponastavitev gesla"),
+        body: format!(
+          "Pozdravljeni {},\n\ngeslo ponastavite na {}/password/reset?token={}\n\nČe ponastavitve niste zahtevali, to sporočilo prezrite.",
+          user.username,
+          frontend_settings.url(),
+          token
+        ),
+      },
+    );
+  }
+
+  Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/password/reset",
+  request_body = PasswordResetDTO,
+  responses(
+    (status = 200, description = "Password reset, all sessions have to log in again."),
+    (status = 400, description = "Invalid, expired or already used token.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn password_reset(
+  password_reset_dto: Json<PasswordResetDTO>,
+  pool: web::Data<PgPool>,
+  argon_context: web::Data<Argon2<'static>>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let user_id = token_user_id(&password_reset_dto.token)?;
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", user_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::InvalidToken)?;
+  verify_token(
+    auth_settings.secret_key.expose_secret(),
+    TokenPurpose::PasswordReset,
+    &password_reset_dto.token,
+    user.password.expose_secret(),
+  )?;
+
+  update_password(
+    pool.get_ref(),
+    argon_context.get_ref(),
+    &user,
+    &password_reset_dto.new_password,
+  )
+  .await?
+  .map(drop)
+  .ok_or(UserError::InvalidToken)?;
+
+  Ok(HttpResponse::Ok().finish())
+}
+
+/// Replaces password of `user`, unless it was changed concurrently, and revokes all its sessions.
+async fn update_password(
+  pool: &PgPool,
+  argon_context: &Argon2<'static>,
+  user: &UserSafe,
+  new_password: &str,
+) -> Result<Option<UserDTO>, APIError> {
+  let (password_hash, salt) = hash_password(argon_context, new_password);
+  let mut tx = pool.begin().await?;
+  let user = sqlx::query_file_as!(
+    User,
+    "queries/user_password_update.sql",
+    password_hash,
+    salt,
+    Utc::now(),
+    user.id,
+    user.password.expose_secret()
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .map(UserSafe::from)
+  .map(UserDTO::from);
+  if let Some(user) = &user {
+    revoke_sessions(&mut *tx, user.id).await?;
+  }
+  tx.commit().await?;
+  Ok(user)
+}
@@ -5,87 +5,342 @@ use actix_web::http::header::USER_AGENT;
 use actix_web::{web, HttpRequest, HttpResponse};
 
 use bendy::encoding::ToBencode;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 
+use laguna_backend_model::consts::SCRAPE_INFO_HASH_COUNT_MAX;
 use laguna_backend_model::peer::Peer;
-use laguna_backend_model::user::User;
+use laguna_backend_model::user::{Passkey, User};
 
-use laguna_backend_model::download::Download;
-use laguna_backend_model::speedlevel::SpeedLevel;
-use laguna_backend_model::torrent::Torrent;
+use laguna_backend_model::download::{Download, DownloadHash};
+use laguna_backend_model::torrent_scrape::TorrentScrape;
 use laguna_backend_tracker::http::announce::{Announce, AnnounceReply};
+use laguna_backend_tracker::http::scrape::{Scrape, ScrapeFile, ScrapeReply};
 
-use laguna_backend_model::genre::Genre;
 use laguna_backend_tracker_common::announce::AnnounceEvent;
+use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH};
 
+use laguna_backend_config::TrackerSettings;
 use laguna_backend_model::behaviour::Behaviour;
 use laguna_backend_model::role::Role;
-use laguna_backend_tracker_common::peer::{PeerBin, PeerDict, PeerStream};
+use laguna_backend_tracker_common::peer::{PeerBin, PeerBin6, PeerDict, PeerStream};
+use rand::seq::SliceRandom;
+use rand::Rng;
 use sqlx::types::ipnetwork::IpNetwork;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 
 use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 
+/// Protocol independent result of an announce.
+/// Each tracker protocol encodes it into its own reply.
+#[derive(Debug)]
+pub struct AnnounceOutcome {
+  pub complete: u64,
+  pub incomplete: u64,
+  pub peers: Vec<Peer<SHA1_LENGTH>>,
+}
+
 #[utoipa::path(
   get,
-  path = "/peer/announce",
+  path = "/peer/{passkey}/announce",
   responses((status = 200, body = String, description = "Returns bencoded `AnnounceReply`", content_type = "text/plain"))
 )]
 pub async fn peer_announce<const N: usize>(
   peer_addr: PeerAddr,
   req: HttpRequest,
+  passkey: Option<web::Path<Passkey>>,
   announce_data: web::Query<Announce<N>>,
   pool: web::Data<PgPool>,
+  tracker_settings: web::Data<TrackerSettings>,
 ) -> Result<HttpResponse, PeerError<N>> {
-  let download = sqlx::query_file_as!(
-    Download::<N>,
-    "queries/download_lookup_byhash.sql",
-    announce_data.down_hash as _
+  let ip = peer_ip(&req, peer_addr.0, announce_data.ip);
+
+  let user_agent = req
+    .headers()
+    .get(USER_AGENT)
+    .map(|hv| hv.to_str().expect("Cannot convert header value to str"))
+    .map(String::from);
+
+  let compact = announce_data.compact.unwrap_or(true);
+  let no_peer_id = announce_data.no_peer_id.unwrap_or_default();
+
+  let outcome = announce(
+    pool.get_ref(),
+    tracker_settings.get_ref(),
+    passkey.map(web::Path::into_inner).as_ref(),
+    announce_data.into_inner(),
+    ip,
+    user_agent,
   )
-  .fetch_optional(pool.get_ref())
-  .await?
-  .ok_or(PeerError::DownloadNotFound(announce_data.down_hash.clone()))?;
+  .await?;
+  let (peers, peers6) = peer_streams(compact, no_peer_id, outcome.peers);
 
-  let user = sqlx::query_file_as!(User, "queries/user_get.sql", download.user_id as _)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or(PeerError::UnknownUser(download.user_id))?;
+  Ok(
+    HttpResponse::Ok().body(
+      AnnounceReply {
+        failure_reason: None,
+        warning_message: None,
+        complete: outcome.complete,
+        incomplete: outcome.incomplete,
+        tracker_id: None,
+        min_interval: Some(tracker_settings.announce_min_interval),
+        interval: announce_interval(tracker_settings.get_ref()),
+        peers,
+        peers6,
+      }
+      .to_bencode()?,
+    ),
+  )
+}
 
-  // Check if torrent exists on tracker
-  sqlx::query_file_as!(
-    Torrent,
-    "queries/torrent_get.sql",
-    announce_data.info_hash as _
+#[utoipa::path(
+  get,
+  path = "/peer/{passkey}/scrape",
+  responses((status = 200, body = String, description = "Returns bencoded `ScrapeReply`", content_type = "text/plain"))
+)]
+pub async fn peer_scrape<const N: usize>(
+  passkey: Option<web::Path<Passkey>>,
+  scrape_data: web::Query<Scrape<N>>,
+  pool: web::Data<PgPool>,
+  tracker_settings: web::Data<TrackerSettings>,
+) -> Result<HttpResponse, PeerError<N>> {
+  let scrape_data = scrape_data.into_inner();
+  // Scrape is private as well, full scrape (without info hashes) is not supported.
+  if scrape_data.info_hashes.is_empty() {
+    return Err(PeerError::MissingField("info_hash"));
+  }
+
+  announcing_user::<N>(
+    pool.get_ref(),
+    tracker_settings.get_ref(),
+    passkey.map(web::Path::into_inner).as_ref(),
+    scrape_data.down_hash.as_ref(),
   )
-  .fetch_optional(pool.get_ref())
-  .await?
-  .map(drop)
-  .ok_or(PeerError::UnknownTorrent(announce_data.info_hash.clone()))?;
+  .await?;
+
+  let scrapes = scrape(
+    pool.get_ref(),
+    tracker_settings.get_ref(),
+    &scrape_data.info_hashes,
+  )
+  .await?;
+
+  Ok(
+    HttpResponse::Ok().body(
+      ScrapeReply {
+        files: scrapes
+          .into_iter()
+          .map(|scrape| ScrapeFile {
+            info_hash: scrape.info_hash,
+            complete: scrape.complete,
+            incomplete: scrape.incomplete,
+            downloaded: scrape.downloaded,
+          })
+          .collect(),
+      }
+      .to_bencode()?,
+    ),
+  )
+}
+
+/// Resolves ip of peer.
+/// If ip was specified by client, prefer it over the one in the request.
+/// If proxy is used, prefer the original ip.
+pub fn peer_ip(req: &HttpRequest, peer_addr: SocketAddr, announced_ip: Option<IpAddr>) -> IpAddr {
+  if let Some(ip) = announced_ip {
+    ip
+  } else if let Some(ip) = req.connection_info().realip_remote_addr() {
+    // Forwarded by proxy
+    if let Ok(ip) = IpAddr::from_str(ip) {
+      ip
+    } else {
+      peer_addr.ip()
+    }
+  } else {
+    peer_addr.ip()
+  }
+}
+
+/// Peers that didn't announce since this time are stale.
+/// Stale peers are excluded from swarms and eventually stopped by [`crate::reaper::stale_peer_reaper`].
+pub fn stale_peer_cutoff(tracker_settings: &TrackerSettings) -> DateTime<Utc> {
+  Utc::now() - Duration::seconds(tracker_settings.stale_peer_timeout() as i64)
+}
 
-  let maybe_peer = sqlx::query_file_as!(Peer, "queries/peer_get.sql", announce_data.peer_id as _)
-    .fetch_optional(pool.get_ref())
-    .await?;
+/// Interval of regular announces with random jitter.
+pub fn announce_interval(tracker_settings: &TrackerSettings) -> u64 {
+  tracker_settings.announce_interval
+    + rand::thread_rng().gen_range(0..=tracker_settings.announce_jitter)
+}
 
-  handle_peer_request(
-    req,
+/// Resolves user announcing (or scraping) by passkey in announce URL path.
+/// Legacy announce URLs with `down_hash` are resolved through their download,
+/// but only while [`TrackerSettings::accept_down_hash`] is set.
+pub async fn announcing_user<const N: usize>(
+  pool: &PgPool,
+  tracker_settings: &TrackerSettings,
+  passkey: Option<&Passkey>,
+  down_hash: Option<&DownloadHash>,
+) -> Result<User, PeerError<N>> {
+  if let Some(passkey) = passkey {
+    return sqlx::query_file_as!(User, "queries/user_lookup_bypasskey.sql", passkey as _)
+      .fetch_optional(pool)
+      .await?
+      .ok_or(PeerError::UnknownPasskey);
+  }
+  match down_hash {
+    Some(down_hash) if tracker_settings.accept_down_hash => {
+      let download = sqlx::query_file_as!(
+        Download::<SHA1_LENGTH>,
+        "queries/download_lookup_byhash.sql",
+        down_hash as _
+      )
+      .fetch_optional(pool)
+      .await?
+      .ok_or(PeerError::DownloadNotFound(down_hash.clone()))?;
+      sqlx::query_file_as!(User, "queries/user_get.sql", download.user_id as _)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(PeerError::UnknownUser(download.user_id))
+    },
+    _ => Err(PeerError::MissingPasskey),
+  }
+}
+
+/// Handles announce of any tracker protocol.
+/// `ip` is the already resolved address of the peer, `user_agent` is the client, if known.
+pub async fn announce<const N: usize>(
+  pool: &PgPool,
+  tracker_settings: &TrackerSettings,
+  passkey: Option<&Passkey>,
+  announce_data: Announce<N>,
+  ip: IpAddr,
+  user_agent: Option<String>,
+) -> Result<AnnounceOutcome, PeerError<N>> {
+  let user = announcing_user(
+    pool,
+    tracker_settings,
+    passkey,
+    announce_data.down_hash.as_ref(),
+  )
+  .await?;
+  // Banned users keep their passkey, so they have to be turned away here.
+  if !user.is_enabled {
+    return Err(PeerError::DisabledUser);
+  }
+
+  let mut tx = pool.begin().await?;
+
+  // Check if torrent exists on tracker and resolve info hash it is stored under,
+  // v2 announces of hybrid torrents use (truncated) v2 info hash.
+  // Torrent is locked until commit, so counts of concurrent announces don't race.
+  let lock = sqlx::query_file!("queries/torrent_lock.sql", announce_data.info_hash as _)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(PeerError::UnknownTorrent(announce_data.info_hash.clone()))?;
+  if lock.is_trashed {
+    return Err(PeerError::TrashedTorrent(
+      lock.delete_reason.unwrap_or_default(),
+    ));
+  }
+  let info_hash = InfoHash::<SHA1_LENGTH>::from(lock.info_hash);
+
+  let maybe_peer = sqlx::query_file_as!(
+    Peer::<SHA1_LENGTH>,
+    "queries/peer_get.sql",
+    announce_data.peer_id as _,
+    &info_hash as _
+  )
+  .fetch_optional(&mut *tx)
+  .await?;
+
+  // Events are always accepted, only regular announces have to respect min interval.
+  if let (Some(peer), None | Some(AnnounceEvent::Empty | AnnounceEvent::Updated)) =
+    (&maybe_peer, announce_data.event)
+  {
+    let last_announce = peer.updated_at.unwrap_or(peer.created_at);
+    let min_interval = tracker_settings.announce_min_interval;
+    if Utc::now() - last_announce < Duration::seconds(min_interval as i64) {
+      log::warn!(
+        "Peer {} announced faster than min interval ({}s).",
+        peer.id,
+        min_interval
+      );
+      return Err(PeerError::AnnounceTooFrequent(min_interval));
+    }
+  }
+
+  let stale_cutoff = stale_peer_cutoff(tracker_settings);
+  let seeding = announce_data.left == 0;
+  let numwant = tracker_settings.numwant(announce_data.numwant);
+  // Only count first completion of a peer.
+  let completed = announce_data.event == Some(AnnounceEvent::Completed)
+    && matches!(maybe_peer, Some(ref peer) if peer.left_bytes > 0);
+  let mut outcome = handle_peer_request(
     maybe_peer,
-    announce_data.into_inner(),
+    announce_data,
+    &info_hash,
     user,
-    pool,
-    peer_addr.0,
+    &mut tx,
+    stale_cutoff,
+    ip,
+    user_agent,
   )
-  .await
+  .await?;
+
+  sqlx::query_file!(
+    "queries/torrent_counts_update.sql",
+    &[info_hash.0.to_vec()],
+    i32::from(completed),
+    stale_cutoff
+  )
+  .execute(&mut *tx)
+  .await?;
+  tx.commit().await?;
+
+  // WebTorrent peers (port 0) can only be reached through WebSocket tracker signaling.
+  outcome.peers.retain(|peer| peer.port != 0);
+  outcome.peers = select_peers(outcome.peers, seeding, numwant);
+  Ok(outcome)
 }
 
+/// Scrapes torrents of any tracker protocol, at most [`SCRAPE_INFO_HASH_COUNT_MAX`] at once.
+/// Torrents unknown to tracker are omitted.
+pub async fn scrape<const N: usize>(
+  pool: &PgPool,
+  tracker_settings: &TrackerSettings,
+  info_hashes: &[InfoHash<N>],
+) -> Result<Vec<TorrentScrape<N>>, PeerError<N>> {
+  if info_hashes.len() > SCRAPE_INFO_HASH_COUNT_MAX {
+    return Err(PeerError::TooManyInfoHashes(SCRAPE_INFO_HASH_COUNT_MAX));
+  }
+  let info_hashes = info_hashes
+    .iter()
+    .map(|info_hash| info_hash.0.to_vec())
+    .collect::<Vec<Vec<u8>>>();
+  Ok(
+    sqlx::query_file_as!(
+      TorrentScrape::<N>,
+      "queries/torrent_scrape.sql",
+      &info_hashes,
+      stale_peer_cutoff(tracker_settings)
+    )
+    .fetch_all(pool)
+    .await?,
+  )
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn handle_peer_request<const N: usize>(
-  req: HttpRequest,
-  maybe_peer: Option<Peer>,
+  maybe_peer: Option<Peer<SHA1_LENGTH>>,
   announce_data: Announce<N>,
+  info_hash: &InfoHash<SHA1_LENGTH>,
   user: User,
-  pool: web::Data<PgPool>,
-  peer_addr: SocketAddr,
-) -> Result<HttpResponse, PeerError<N>> {
+  conn: &mut PgConnection,
+  stale_cutoff: DateTime<Utc>,
+  ip: IpAddr,
+  user_agent: Option<String>,
+) -> Result<AnnounceOutcome, PeerError<N>> {
   let event = announce_data.event.unwrap_or(AnnounceEvent::Empty);
   match (event, maybe_peer) {
     (AnnounceEvent::Started, Some(_)) => {
@@ -95,34 +350,49 @@ async fn handle_peer_request<const N: usize>(
         "Peer {} sent started event, treating as update.",
         announce_data.peer_id
       );
-      handle_peer_updated(announce_data, pool).await
+      handle_peer_updated(announce_data, info_hash, conn, stale_cutoff).await
     },
     (AnnounceEvent::Started, None) => {
       log::info!(
         "Peer {} sent started event, treating as start.",
         announce_data.peer_id
       );
-      handle_peer_started(req, announce_data, user, pool, peer_addr).await
+      handle_peer_started(
+        announce_data,
+        info_hash,
+        user,
+        conn,
+        stale_cutoff,
+        ip,
+        user_agent,
+      )
+      .await
     },
     (AnnounceEvent::Completed, Some(_)) => {
       log::info!("Peer {} sent completed event.", announce_data.peer_id);
-      handle_peer_completed(announce_data, pool).await
+      handle_peer_completed(announce_data, info_hash, conn, stale_cutoff).await
     },
     (AnnounceEvent::Completed, None) => Err(PeerError::<N>::UnexpectedEvent {
       event: AnnounceEvent::Completed,
       message: String::from("Inexistant peer sent completion."),
     }),
-    (AnnounceEvent::Stopped, Some(_peer)) => handle_peer_stopped(announce_data, pool).await,
+    (AnnounceEvent::Stopped, Some(_peer)) => {
+      handle_peer_stopped(announce_data, info_hash, conn).await
+    },
     (AnnounceEvent::Stopped, None) => Err(PeerError::<N>::UnexpectedEvent {
       event: AnnounceEvent::Stopped,
       message: String::from("Inexistant peer sent stop."),
     }),
-    (AnnounceEvent::Updated, Some(_)) => handle_peer_updated(announce_data, pool).await,
+    (AnnounceEvent::Updated, Some(_)) => {
+      handle_peer_updated(announce_data, info_hash, conn, stale_cutoff).await
+    },
     (AnnounceEvent::Updated, None) => Err(PeerError::<N>::UnexpectedEvent {
       event: AnnounceEvent::Updated,
       message: String::from("Inexistant peer sent update."),
     }),
-    (AnnounceEvent::Paused, Some(peer)) => handle_peer_paused(peer, announce_data, pool).await,
+    (AnnounceEvent::Paused, Some(peer)) => {
+      handle_peer_paused(peer, announce_data, info_hash, conn, stale_cutoff).await
+    },
     (AnnounceEvent::Paused, None) => Err(PeerError::<N>::UnexpectedEvent {
       event: AnnounceEvent::Paused,
       message: String::from("Inexistant peer sent pause."),
@@ -133,7 +403,7 @@ async fn handle_peer_request<const N: usize>(
         "Peer {} sent empty event, treating as update.",
         announce_data.peer_id
       );
-      handle_peer_updated(announce_data, pool).await
+      handle_peer_updated(announce_data, info_hash, conn, stale_cutoff).await
     },
     (AnnounceEvent::Empty, None) => {
       // Empty event with no peer, assume it's a start.
@@ -141,39 +411,30 @@ async fn handle_peer_request<const N: usize>(
         "Peer {} sent empty event, treating as start.",
         announce_data.peer_id
       );
-      handle_peer_started(req, announce_data, user, pool, peer_addr).await
+      handle_peer_started(
+        announce_data,
+        info_hash,
+        user,
+        conn,
+        stale_cutoff,
+        ip,
+        user_agent,
+      )
+      .await
     },
   }
 }
 
 async fn handle_peer_started<const N: usize>(
-  req: HttpRequest,
   announce_data: Announce<N>,
+  info_hash: &InfoHash<SHA1_LENGTH>,
   user: User,
-  pool: web::Data<PgPool>,
-  peer_addr: SocketAddr,
-) -> Result<HttpResponse, PeerError<N>> {
-  // If ip was specified by client, prefer it over the one in the request.
-  // If proxy is used, prefer the original ip.
-  let ip = IpNetwork::from(if let Some(ip) = announce_data.ip {
-    ip
-  } else if let Some(ip) = req.connection_info().realip_remote_addr() {
-    // Forwarded by proxy
-    if let Ok(ip) = IpAddr::from_str(ip) {
-      ip
-    } else {
-      peer_addr.ip()
-    }
-  } else {
-    peer_addr.ip()
-  });
-
-  let user_agent = req
-    .headers()
-    .get(USER_AGENT)
-    .map(|hv| hv.to_str().expect("Cannot convert header value to str"));
-
-  let swarm = torrent_swarm(pool.get_ref(), &announce_data).await?;
+  conn: &mut PgConnection,
+  stale_cutoff: DateTime<Utc>,
+  ip: IpAddr,
+  user_agent: Option<String>,
+) -> Result<AnnounceOutcome, PeerError<N>> {
+  let swarm = torrent_swarm(&mut *conn, info_hash, &announce_data, stale_cutoff).await?;
 
   let behaviour = if announce_data.left == 0 {
     Behaviour::Seed
@@ -182,12 +443,12 @@ async fn handle_peer_started<const N: usize>(
   };
 
   sqlx::query_file_as!(
-    Peer,
+    Peer::<SHA1_LENGTH>,
     "queries/peer_insert.sql",
     announce_data.peer_id as _,
     None::<String>,
-    announce_data.info_hash as _,
-    ip,
+    info_hash as _,
+    IpNetwork::from(ip),
     announce_data.port as i32,
     swarm.is_empty(), // if we are the first peer, we are origin
     user_agent,
@@ -199,112 +460,64 @@ async fn handle_peer_started<const N: usize>(
     Utc::now(),
     user.id
   )
-  .fetch_optional(pool.get_ref())
+  .fetch_optional(&mut *conn)
   .await?
   .map(drop)
   .ok_or(PeerError::NotCreated)?;
 
   let (complete, incomplete) = complete_incomplete_counts(&swarm).await;
 
-  Ok(
-    HttpResponse::Ok().body(
-      AnnounceReply {
-        failure_reason: None,
-        warning_message: None,
-        complete,
-        incomplete,
-        tracker_id: None,
-        min_interval: None,
-        interval: 1,
-        peers: peer_stream(
-          announce_data.compact.unwrap_or(true),
-          announce_data.no_peer_id.unwrap_or_default(),
-          if behaviour == Behaviour::Seed {
-            // report non-seeds to seed
-            swarm
-              .into_iter()
-              .filter(|peer| peer.behaviour != Behaviour::Seed)
-              .collect()
-          } else {
-            swarm
-          },
-        ),
-      }
-      .to_bencode()?,
-    ),
-  )
+  Ok(AnnounceOutcome {
+    complete,
+    incomplete,
+    peers: swarm,
+  })
 }
 
 async fn handle_peer_stopped<const N: usize>(
   announce_data: Announce<N>,
-  pool: web::Data<PgPool>,
-) -> Result<HttpResponse, PeerError<N>> {
+  info_hash: &InfoHash<SHA1_LENGTH>,
+  conn: &mut PgConnection,
+) -> Result<AnnounceOutcome, PeerError<N>> {
   sqlx::query_file_as!(
-    Peer,
+    Peer::<SHA1_LENGTH>,
     "queries/peer_update.sql",
     announce_data.uploaded,
     announce_data.downloaded,
     announce_data.left,
     Behaviour::Stopped as _,
     Utc::now(),
-    announce_data.peer_id as _
+    announce_data.peer_id as _,
+    info_hash as _
   )
-  .fetch_optional(pool.get_ref())
+  .fetch_optional(&mut *conn)
   .await?
   .ok_or(PeerError::NotUpdated)?;
-  Ok(
-    HttpResponse::Ok().body(
-      AnnounceReply {
-        failure_reason: None,
-        warning_message: None,
-        complete: 0,
-        incomplete: 0,
-        tracker_id: None,
-        min_interval: None,
-        interval: 1,
-        peers: peer_stream(
-          announce_data.compact.unwrap_or(true),
-          announce_data.no_peer_id.unwrap_or_default(),
-          vec![],
-        ),
-      }
-      .to_bencode()?,
-    ),
-  )
+  Ok(AnnounceOutcome {
+    complete: 0,
+    incomplete: 0,
+    peers: vec![],
+  })
 }
 
 async fn handle_peer_completed<const N: usize>(
   announce_data: Announce<N>,
-  pool: web::Data<PgPool>,
-) -> Result<HttpResponse, PeerError<N>> {
-  let swarm = torrent_swarm(pool.get_ref(), &announce_data).await?;
-  let (complete, incomplete) = complete_incomplete_counts(&swarm).await;
-  Ok(
-    HttpResponse::Ok().body(
-      AnnounceReply {
-        failure_reason: None,
-        warning_message: None,
-        complete,
-        incomplete,
-        tracker_id: None,
-        min_interval: None,
-        interval: 1,
-        peers: peer_stream(
-          announce_data.compact.unwrap_or(true),
-          announce_data.no_peer_id.unwrap_or_default(),
-          swarm,
-        ),
-      }
-      .to_bencode()?,
-    ),
-  )
+  info_hash: &InfoHash<SHA1_LENGTH>,
+  conn: &mut PgConnection,
+  stale_cutoff: DateTime<Utc>,
+) -> Result<AnnounceOutcome, PeerError<N>> {
+  // Completed peer is updated as any other, it becomes a seed since nothing is left.
+  // Torrent completed_count is bumped by caller.
+  handle_peer_updated(announce_data, info_hash, conn, stale_cutoff).await
 }
 
 async fn handle_peer_updated<const N: usize>(
   announce_data: Announce<N>,
-  pool: web::Data<PgPool>,
-) -> Result<HttpResponse, PeerError<N>> {
-  let swarm = torrent_swarm(pool.get_ref(), &announce_data).await?;
+  info_hash: &InfoHash<SHA1_LENGTH>,
+  conn: &mut PgConnection,
+  stale_cutoff: DateTime<Utc>,
+) -> Result<AnnounceOutcome, PeerError<N>> {
+  let swarm = torrent_swarm(&mut *conn, info_hash, &announce_data, stale_cutoff).await?;
 
   let behaviour = if announce_data.left == 0 {
     Behaviour::Seed
@@ -313,98 +526,67 @@ async fn handle_peer_updated<const N: usize>(
   };
 
   sqlx::query_file_as!(
-    Peer,
+    Peer::<SHA1_LENGTH>,
     "queries/peer_update.sql",
     announce_data.uploaded,
     announce_data.downloaded,
     announce_data.left,
     behaviour as _,
     Utc::now(),
-    announce_data.peer_id as _
+    announce_data.peer_id as _,
+    info_hash as _
   )
-  .fetch_optional(pool.get_ref())
+  .fetch_optional(&mut *conn)
   .await?
   .map(drop)
   .ok_or(PeerError::NotUpdated)?;
 
   let (complete, incomplete) = complete_incomplete_counts(&swarm).await;
 
-  Ok(
-    HttpResponse::Ok().body(
-      AnnounceReply {
-        failure_reason: None,
-        warning_message: None,
-        complete,
-        incomplete,
-        tracker_id: None,
-        min_interval: None,
-        interval: 1,
-        peers: peer_stream(
-          announce_data.compact.unwrap_or(true),
-          announce_data.no_peer_id.unwrap_or_default(),
-          if behaviour == Behaviour::Seed {
-            // report non-seeds to seed
-            swarm
-              .into_iter()
-              .filter(|peer| peer.behaviour != Behaviour::Seed)
-              .collect()
-          } else {
-            swarm
-          },
-        ),
-      }
-      .to_bencode()?,
-    ),
-  )
+  Ok(AnnounceOutcome {
+    complete,
+    incomplete,
+    peers: swarm,
+  })
 }
 
 async fn handle_peer_paused<const N: usize>(
-  _peer: Peer,
+  _peer: Peer<SHA1_LENGTH>,
   announce_data: Announce<N>,
-  pool: web::Data<PgPool>,
-) -> Result<HttpResponse, PeerError<N>> {
-  let swarm = torrent_swarm(pool.get_ref(), &announce_data).await?;
+  info_hash: &InfoHash<SHA1_LENGTH>,
+  conn: &mut PgConnection,
+  stale_cutoff: DateTime<Utc>,
+) -> Result<AnnounceOutcome, PeerError<N>> {
+  let swarm = torrent_swarm(&mut *conn, info_hash, &announce_data, stale_cutoff).await?;
   let (complete, incomplete) = complete_incomplete_counts(&swarm).await;
-
-  Ok(
-    HttpResponse::Ok().body(
-      AnnounceReply {
-        failure_reason: None,
-        warning_message: None,
-        complete,
-        incomplete,
-        tracker_id: None,
-        min_interval: None,
-        interval: 1,
-        peers: peer_stream(
-          announce_data.compact.unwrap_or(true),
-          announce_data.no_peer_id.unwrap_or_default(),
-          swarm,
-        ),
-      }
-      .to_bencode()?,
-    ),
-  )
+  Ok(AnnounceOutcome {
+    complete,
+    incomplete,
+    peers: swarm,
+  })
 }
 
 #[inline]
 async fn torrent_swarm<const N: usize>(
-  pool: &PgPool,
+  conn: &mut PgConnection,
+  info_hash: &InfoHash<SHA1_LENGTH>,
   announce_data: &Announce<N>,
-) -> Result<Vec<Peer>, PeerError<N>> {
+  stale_cutoff: DateTime<Utc>,
+) -> Result<Vec<Peer<SHA1_LENGTH>>, PeerError<N>> {
   Ok(
     sqlx::query_file_as!(
-      Peer,
+      Peer::<SHA1_LENGTH>,
       "queries/torrent_swarm_noself.sql",
-      &announce_data.info_hash as _,
-      &announce_data.peer_id as _
+      info_hash as _,
+      &announce_data.peer_id as _,
+      stale_cutoff
     )
-    .fetch_all(pool)
+    .fetch_all(conn)
     .await?,
   )
 }
 
-async fn complete_incomplete_counts(torrent_swarm: &[Peer]) -> (u64, u64) {
+async fn complete_incomplete_counts(torrent_swarm: &[Peer<SHA1_LENGTH>]) -> (u64, u64) {
   torrent_swarm
     .iter()
     .fold((0, 0), |(complete, incomplete), peer| {
@@ -415,24 +597,53 @@ async fn complete_incomplete_counts(torrent_swarm: &[Peer]) -> (u64, u64) {
     })
 }
 
-fn peer_stream(compact: bool, no_peer_id: bool, torrent_swarm: Vec<Peer>) -> PeerStream {
-  if !compact || torrent_swarm.iter().any(|peer| peer.ip.is_ipv6()) {
-    PeerStream::Dict(
-      torrent_swarm
-        .into_iter()
-        .map(|peer| PeerDict {
-          peer_id: if no_peer_id { None } else { Some(peer.id) },
-          ip: peer.ip.ip(),
-          port: peer.port as u16,
-        })
-        .collect::<Vec<PeerDict>>(),
-    )
+/// Picks at most `numwant` random peers from swarm.
+/// Seeds are reported to leechers first, seeds only get non-seeds.
+fn select_peers(
+  mut swarm: Vec<Peer<SHA1_LENGTH>>,
+  seeding: bool,
+  numwant: usize,
+) -> Vec<Peer<SHA1_LENGTH>> {
+  swarm.shuffle(&mut rand::thread_rng());
+  if seeding {
+    swarm.retain(|peer| peer.behaviour != Behaviour::Seed);
   } else {
-    PeerStream::Bin(
-      torrent_swarm
-        .into_iter()
-        .flat_map(|peer| PeerBin::from_socket(peer.ip.ip(), peer.port as u16).0)
-        .collect::<Vec<u8>>(),
-    )
+    // Stable sort, so peers stay shuffled within each group.
+    swarm.sort_by_key(|peer| peer.behaviour != Behaviour::Seed);
+  }
+  swarm.truncate(numwant);
+  swarm
+}
+
+/// Encodes swarm as `peers` and, in compact mode, `peers6`.
+/// IPv4-mapped IPv6 addresses are treated as IPv4.
+fn peer_streams(
+  compact: bool,
+  no_peer_id: bool,
+  torrent_swarm: Vec<Peer<SHA1_LENGTH>>,
+) -> (PeerStream, Option<Vec<u8>>) {
+  if !compact {
+    return (
+      PeerStream::Dict(
+        torrent_swarm
+          .into_iter()
+          .map(|peer| PeerDict {
+            peer_id: if no_peer_id { None } else { Some(peer.id) },
+            ip: peer.ip.ip(),
+            port: peer.port as u16,
+          })
+          .collect::<Vec<PeerDict>>(),
+      ),
+      None,
+    );
+  }
+  let mut peers = Vec::new();
+  let mut peers6 = Vec::new();
+  for peer in torrent_swarm {
+    match peer.ip.ip().to_canonical() {
+      IpAddr::V4(ip) => peers.extend(PeerBin::from_socket(ip, peer.port as u16).0),
+      IpAddr::V6(ip) => peers6.extend(PeerBin6::from_socket(ip, peer.port as u16).0),
+    }
   }
+  (PeerStream::Bin(peers), Some(peers6))
 }
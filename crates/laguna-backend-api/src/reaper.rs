@@ -0,0 +1,51 @@
+//! Background expiry of stale peers.
+//! Crashed clients never send stopped event, so their peers are stopped here instead.
+use std::time::Duration;
+
+use laguna_backend_config::TrackerSettings;
+use sqlx::PgPool;
+
+use crate::peer::stale_peer_cutoff;
+
+/// Stops stale peers every announce interval, forever.
+/// Must be spawned on actix (local) runtime.
+pub async fn stale_peer_reaper(pool: PgPool, tracker_settings: TrackerSettings) {
+  let mut interval =
+    actix_web::rt::time::interval(Duration::from_secs(tracker_settings.announce_interval));
+  loop {
+    interval.tick().await;
+    match expire_stale_peers(&pool, &tracker_settings).await {
+      Ok(0) => {},
+      Ok(expired) => log::info!("Stopped {} stale peers.", expired),
+      Err(err) => log::warn!("Cannot stop stale peers: {}", err),
+    }
+  }
+}
+
+/// Stops peers that didn't announce for too long and updates counts of their torrents.
+/// Returns number of stopped peers.
+pub async fn expire_stale_peers(
+  pool: &PgPool,
+  tracker_settings: &TrackerSettings,
+) -> Result<u64, sqlx::Error> {
+  let mut tx = pool.begin().await?;
+  let stale_cutoff = stale_peer_cutoff(tracker_settings);
+  // Torrents are locked before their peers, same as in announce, otherwise they could deadlock.
+  let info_hashes = sqlx::query_file_scalar!("queries/torrent_lock_stale.sql", stale_cutoff)
+    .fetch_all(&mut *tx)
+    .await?;
+  let expired = sqlx::query_file_scalar!("queries/peer_expire.sql", stale_cutoff, &info_hashes)
+    .fetch_all(&mut *tx)
+    .await?
+    .len() as u64;
+  sqlx::query_file!(
+    "queries/torrent_counts_update.sql",
+    &info_hashes,
+    0,
+    stale_cutoff
+  )
+  .execute(&mut *tx)
+  .await?;
+  tx.commit().await?;
+  Ok(expired)
+}
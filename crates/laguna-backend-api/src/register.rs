@@ -1,15 +1,13 @@
 use actix_web::{web, HttpResponse};
 use actix_web_validator::Json;
-use argon2::{
-  password_hash::{rand_core::OsRng, SaltString},
-  Argon2, PasswordHasher,
-};
+use argon2::Argon2;
 
 use chrono::Utc;
+use laguna_backend_config::{AuthSettings, FrontendSettings};
 use laguna_backend_dto::{already_exists::AlreadyExistsDTO, register::RegisterDTO};
 use laguna_backend_model::behaviour::Behaviour;
 use laguna_backend_model::role::Role;
-use laguna_backend_model::user::{User, UserSafe};
+use laguna_backend_model::user::{Passkey, User, UserSafe};
 
 use secrecy::ExposeSecret;
 use sqlx::PgPool;
@@ -17,6 +15,9 @@ use sqlx::PgPool;
 use crate::{
   error::{user::UserError, APIError},
   helpers::register::generate_username_recommendations,
+  mailer::Mailer,
+  password::hash_password,
+  verify::send_verification_mail,
 };
 
 #[utoipa::path(
@@ -32,6 +33,9 @@ pub async fn register(
   register_dto: Json<RegisterDTO>,
   pool: web::Data<PgPool>,
   argon_context: web::Data<Argon2<'static>>,
+  auth_settings: web::Data<AuthSettings>,
+  frontend_settings: web::Data<FrontendSettings>,
+  mailer: web::Data<dyn Mailer>,
 ) -> Result<HttpResponse, APIError> {
   let register_dto = register_dto.into_inner();
 
@@ -58,14 +62,9 @@ pub async fn register(
     }));
   }
 
-  let salt = SaltString::generate(&mut OsRng);
-  let password_hash = argon_context
-    .hash_password(register_dto.password.as_bytes(), salt.as_salt())
-    .unwrap()
-    .to_string();
+  let (password_hash, salt) = hash_password(&argon_context, &register_dto.password);
 
-  // TODO: Verify email
-  sqlx::query_file_as!(
+  let user = sqlx::query_file_as!(
     User,
     "queries/user_insert.sql",
     register_dto.username,
@@ -74,7 +73,7 @@ pub async fn register(
     Utc::now(),
     Utc::now(),
     None::<String>,
-    salt.to_string(),
+    salt,
     Role::Normie as _,
     0,
     Behaviour::Lurker as _,
@@ -85,8 +84,19 @@ pub async fn register(
   )
   .fetch_optional(pool.get_ref())
   .await?
-  .map(drop)
+  .map(UserSafe::from)
   .ok_or(UserError::NotCreated)?;
 
+  // User is already registered, verification mail can be requested again if this fails.
+  if let Err(err) =
+    send_verification_mail(&user, &auth_settings, &frontend_settings, mailer.get_ref()).await
+  {
+    log::warn!(
+      "Cannot send verification mail to user {}: {:?}",
+      user.id,
+      err
+    );
+  }
+
   Ok(HttpResponse::Ok().finish())
 }
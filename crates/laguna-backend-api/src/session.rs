@@ -0,0 +1,384 @@
+use actix_jwt_auth_middleware::{AuthError, TokenSigner};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use digest::Digest;
+use jwt_compact::alg::{Hs256, Hs256Key};
+use jwt_compact::{AlgorithmExt, Claims, Header, TimeOptions, UntrustedToken};
+use laguna_backend_config::AuthSettings;
+use laguna_backend_dto::session::SessionDTO;
+use laguna_backend_dto::user::UserDTO;
+use laguna_backend_middleware::{
+  consts::{ACCESS_TOKEN_HEADER_NAME, REFRESH_TOKEN_HEADER_NAME},
+  mime::APPLICATION_LAGUNA_JSON_VERSIONED,
+};
+use laguna_backend_model::behaviour::Behaviour;
+use laguna_backend_model::role::Role;
+use laguna_backend_model::session::Session;
+use laguna_backend_model::user::{Passkey, User, UserSafe};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::error::{user::UserError, APIError};
+use crate::helpers::request::request_ip;
+
+/// Refresh token claims, authority reads them as [`UserDTO`] and ignores the rest.
+#[derive(Serialize, Deserialize)]
+struct RefreshClaims {
+  #[serde(flatten)]
+  user: UserDTO,
+  /// Session this token belongs to.
+  sid: Uuid,
+  /// Makes every refresh token unique, even when issued within the same second.
+  nonce: Uuid,
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/refresh",
+  responses(
+    (status = 200, description = "Session renewed, refresh token sent with request can't be used anymore.", body = UserDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json", headers(
+      ("X-Access-Token" = String, description = "New access token."),
+      ("X-Refresh-Token" = String, description = "New refresh token.")
+    )),
+    (status = 401, description = "Session expired or revoked.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 403, description = "User is disabled.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn session_refresh(
+  req: HttpRequest,
+  pool: web::Data<PgPool>,
+  signer: web::Data<TokenSigner<UserDTO, Hs256>>,
+  secret_key: web::Data<Hs256Key>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let refresh_token = request_refresh_token(&req).ok_or(UserError::SessionRevoked)?;
+  // Sent refresh token is invalidated first, so it can't be used concurrently.
+  // Placeholder hash is replaced once new refresh token is issued.
+  let placeholder_hash = token_hash(&Uuid::new_v4().to_string());
+  let rotated = rotate_session_hash(
+    pool.get_ref(),
+    &auth_settings,
+    &token_hash(&refresh_token),
+    &placeholder_hash,
+  )
+  .await?;
+  let Some((session_id, user_id)) = rotated else {
+    revoke_reused_session(pool.get_ref(), &secret_key, &refresh_token).await?;
+    return Err(UserError::SessionRevoked.into());
+  };
+  // User may have changed since refresh token was issued, so claims are re-read from DB.
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", user_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::SessionRevoked)?;
+  if !user.is_enabled {
+    revoke_sessions(pool.get_ref(), user.id).await?;
+    return Err(UserError::Disabled.into());
+  }
+  let user = UserDTO::from(user);
+
+  let (access_token, refresh_token) =
+    create_tokens(&signer, &secret_key, &auth_settings, &user, session_id)?;
+  rotate_session_hash(
+    pool.get_ref(),
+    &auth_settings,
+    &placeholder_hash,
+    &token_hash(&refresh_token),
+  )
+  .await?;
+  Ok(
+    HttpResponse::Ok()
+      .append_header((ACCESS_TOKEN_HEADER_NAME, access_token))
+      .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token))
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(user),
+  )
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/logout",
+  responses(
+    (status = 200, description = "Session of refresh token sent with request is revoked."),
+  ),
+)]
+pub async fn session_logout(
+  req: HttpRequest,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  // Logging out of revoked or unknown session is not an error.
+  if let Some(refresh_token) = request_refresh_token(&req) {
+    sqlx::query_file_scalar!(
+      "queries/session_revoke.sql",
+      token_hash(&refresh_token),
+      Utc::now()
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+  }
+  Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+  get,
+  path = "/api/user/me/sessions",
+  responses(
+    (status = 200, description = "Returns active sessions of logged in user, most recently used first.", body = Vec<SessionDTO>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn user_me_sessions(
+  req: HttpRequest,
+  user: UserDTO,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let current_token_hash = request_refresh_token(&req).map(|token| token_hash(&token));
+  let sessions = sqlx::query_file_as!(Session, "queries/session_list.sql", user.id, Utc::now())
+    .fetch_all(pool.get_ref())
+    .await?
+    .into_iter()
+    .map(|session| SessionDTO::new(session, current_token_hash.as_deref()))
+    .collect::<Vec<_>>();
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(sessions),
+  )
+}
+
+#[utoipa::path(
+  delete,
+  path = "/api/user/me/sessions/{id}",
+  responses(
+    (status = 200, description = "Session revoked."),
+    (status = 400, description = "Session not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+  params(
+    ("id", Path, description = "Session's id.", format = Uuid)
+  )
+)]
+pub async fn user_me_session_revoke(
+  id: web::Path<Uuid>,
+  user: UserDTO,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  sqlx::query_file_scalar!(
+    "queries/session_revoke_byid.sql",
+    id.into_inner(),
+    user.id,
+    Utc::now()
+  )
+  .fetch_optional(pool.get_ref())
+  .await?
+  .ok_or(UserError::SessionNotFound)?;
+  Ok(HttpResponse::Ok().finish())
+}
+
+/// Rejects refresh tokens without active session, of sessions started before last password change
+/// and of disabled users.
+/// Reusing already rotated refresh token revokes its session.
+/// Called by authority only when access token is missing or expired,
+/// so access tokens of revoked sessions stay valid until they expire.
+pub async fn refresh_authorizer(
+  req: HttpRequest,
+  pool: web::Data<PgPool>,
+  secret_key: web::Data<Hs256Key>,
+) -> Result<(), actix_web::Error> {
+  let Some(refresh_token) = request_refresh_token(&req) else {
+    // Authority rejects request without token.
+    return Ok(());
+  };
+  let session_id = sqlx::query_file_scalar!(
+    "queries/session_touch.sql",
+    token_hash(&refresh_token),
+    Utc::now()
+  )
+  .fetch_optional(pool.get_ref())
+  .await
+  .map_err(APIError::from)?;
+  if session_id.is_none() {
+    revoke_reused_session(pool.get_ref(), &secret_key, &refresh_token).await?;
+    return Err(UserError::SessionRevoked.into());
+  }
+  Ok(())
+}
+
+/// Starts new session for `user` and returns its access and refresh token.
+pub(crate) async fn create_session(
+  pool: &PgPool,
+  signer: &TokenSigner<UserDTO, Hs256>,
+  secret_key: &Hs256Key,
+  auth_settings: &AuthSettings,
+  req: &HttpRequest,
+  user: &UserDTO,
+) -> Result<(HeaderValue, String), APIError> {
+  let session_id = Uuid::new_v4();
+  let (access_token, refresh_token) =
+    create_tokens(signer, secret_key, auth_settings, user, session_id)?;
+  let now = Utc::now();
+  let user_agent = req
+    .headers()
+    .get(header::USER_AGENT)
+    .and_then(|user_agent| user_agent.to_str().ok())
+    .map(String::from);
+  let ip = request_ip(req, &auth_settings.trusted_proxies);
+  sqlx::query_file_scalar!(
+    "queries/session_insert.sql",
+    session_id,
+    user.id,
+    token_hash(&refresh_token),
+    now,
+    now + Duration::seconds(auth_settings.refresh_token_lifetime_seconds),
+    user_agent,
+    ip
+  )
+  .fetch_one(pool)
+  .await?;
+  Ok((access_token, refresh_token))
+}
+
+/// Issues new tokens for `user`, continuing session of request's refresh token if it is active.
+pub(crate) async fn renew_session(
+  pool: &PgPool,
+  signer: &TokenSigner<UserDTO, Hs256>,
+  secret_key: &Hs256Key,
+  auth_settings: &AuthSettings,
+  req: &HttpRequest,
+  user: &UserDTO,
+) -> Result<(HeaderValue, String), APIError> {
+  if let Some(old_refresh_token) = request_refresh_token(req) {
+    // Placeholder hash is replaced once new refresh token is issued.
+    let placeholder_hash = token_hash(&Uuid::new_v4().to_string());
+    let rotated = rotate_session_hash(
+      pool,
+      auth_settings,
+      &token_hash(&old_refresh_token),
+      &placeholder_hash,
+    )
+    .await?;
+    if let Some((session_id, session_user_id)) = rotated {
+      if session_user_id == user.id {
+        let (access_token, refresh_token) =
+          create_tokens(signer, secret_key, auth_settings, user, session_id)?;
+        rotate_session_hash(
+          pool,
+          auth_settings,
+          &placeholder_hash,
+          &token_hash(&refresh_token),
+        )
+        .await?;
+        return Ok((access_token, refresh_token));
+      }
+    }
+  }
+  create_session(pool, signer, secret_key, auth_settings, req, user).await
+}
+
+/// Revokes all sessions of user, for example when password changes or user is disabled.
+pub(crate) async fn revoke_sessions(
+  executor: impl PgExecutor<'_>,
+  user_id: Uuid,
+) -> Result<(), APIError> {
+  sqlx::query_file!("queries/session_revoke_all.sql", user_id, Utc::now())
+    .execute(executor)
+    .await?;
+  Ok(())
+}
+
+/// Replaces refresh token hash of active session, returns id of session and its user.
+async fn rotate_session_hash(
+  pool: &PgPool,
+  auth_settings: &AuthSettings,
+  old_token_hash: &[u8],
+  new_token_hash: &[u8],
+) -> Result<Option<(Uuid, Uuid)>, APIError> {
+  let now = Utc::now();
+  Ok(
+    sqlx::query_file!(
+      "queries/session_rotate.sql",
+      new_token_hash,
+      now,
+      now + Duration::seconds(auth_settings.refresh_token_lifetime_seconds),
+      old_token_hash
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|session| (session.id, session.user_id)),
+  )
+}
+
+/// Revokes session of validly signed refresh token that was already rotated,
+/// since either its owner or whoever stole it holds the newer token.
+async fn revoke_reused_session(
+  pool: &PgPool,
+  secret_key: &Hs256Key,
+  refresh_token: &str,
+) -> Result<(), APIError> {
+  let Some(session_id) = UntrustedToken::new(refresh_token)
+    .ok()
+    .and_then(|token| {
+      Hs256
+        .validate_integrity::<RefreshClaims>(&token, secret_key)
+        .ok()
+    })
+    .map(|token| token.claims().custom.sid)
+  else {
+    return Ok(());
+  };
+  sqlx::query_file!(
+    "queries/session_revoke_reused.sql",
+    session_id,
+    token_hash(refresh_token),
+    Utc::now()
+  )
+  .execute(pool)
+  .await?;
+  Ok(())
+}
+
+fn create_tokens(
+  signer: &TokenSigner<UserDTO, Hs256>,
+  secret_key: &Hs256Key,
+  auth_settings: &AuthSettings,
+  user: &UserDTO,
+  session_id: Uuid,
+) -> Result<(HeaderValue, String), APIError> {
+  let claims = Claims::new(RefreshClaims {
+    user: user.clone(),
+    sid: session_id,
+    nonce: Uuid::new_v4(),
+  })
+  .set_duration_and_issuance(
+    &TimeOptions::default(),
+    Duration::seconds(auth_settings.refresh_token_lifetime_seconds),
+  );
+  Ok((
+    signer.create_access_header_value(user)?,
+    Hs256
+      .token(Header::default(), &claims, secret_key)
+      .map_err(AuthError::TokenCreation)?,
+  ))
+}
+
+/// Refresh token from header or cookie.
+fn request_refresh_token(req: &HttpRequest) -> Option<String> {
+  req
+    .headers()
+    .get(REFRESH_TOKEN_HEADER_NAME)
+    .and_then(|header_value| header_value.to_str().ok())
+    .map(String::from)
+    .or_else(|| {
+      req
+        .cookie(REFRESH_TOKEN_HEADER_NAME)
+        .map(|cookie| cookie.value().to_string())
+    })
+}
+
+fn token_hash(token: &str) -> Vec<u8> {
+  Sha256::digest(token.as_bytes()).to_vec()
+}
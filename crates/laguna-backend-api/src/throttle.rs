@@ -0,0 +1,184 @@
+//! Login brute-force protection.
+//!
+//! Failed logins are counted per account and per IP address, once there are too many of them
+//! login is locked for a while, doubling with each further failure.
+//! Password change, TOTP codes of logged in users and verification mail resends are throttled the same way.
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use laguna_backend_config::AuthSettings;
+use laguna_backend_model::behaviour::Behaviour;
+use laguna_backend_model::role::Role;
+use laguna_backend_model::user::{Passkey, User, UserSafe};
+use sqlx::types::ipnetwork::IpNetwork;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::error::{user::UserError, APIError};
+
+#[utoipa::path(
+  delete,
+  path = "/api/user/{id}/lock",
+  responses(
+    (status = 200, description = "Failed logins of user are forgotten and it can log in again."),
+    (status = 400, description = "User not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+  params(
+    ("id", Path, description = "User's id.", format = Uuid)
+  )
+)]
+pub async fn user_unlock(
+  user_id: web::Path<Uuid>,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", user_id.into_inner())
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::NotFound)?;
+  clear_login_failures(pool.get_ref(), user.id).await?;
+  Ok(HttpResponse::Ok().finish())
+}
+
+/// Counts login attempt of account, if it exists, and IP address before credentials are verified,
+/// so concurrent attempts can't get past the limit, rejects it while either is locked.
+/// Attempt is counted as failed until it is forgiven with [`forgive_login_attempt`],
+/// returned time of attempt identifies it there.
+pub(crate) async fn record_login_attempt(
+  pool: &PgPool,
+  auth_settings: &AuthSettings,
+  ip: Option<IpNetwork>,
+  user_id: Option<Uuid>,
+) -> Result<DateTime<Utc>, APIError> {
+  let now = Utc::now();
+  let forget_before = now - Duration::seconds(auth_settings.login_lockout_max_seconds);
+  // Counted rows stay locked until commit, so concurrent attempts see lock set by this one.
+  // Account is always counted before IP address, so attempts can't deadlock.
+  let mut tx = pool.begin().await?;
+  if let Some(user_id) = user_id {
+    let attempt = sqlx::query_file!(
+      "queries/login_throttle_attempt_user.sql",
+      user_id,
+      now,
+      forget_before
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    check_lock(attempt.locked_until, now)?;
+    lock(
+      &mut tx,
+      auth_settings,
+      attempt.id,
+      attempt.failed_count,
+      auth_settings.login_max_failed_attempts,
+      now,
+    )
+    .await?;
+  }
+  if let Some(ip) = ip {
+    let attempt = sqlx::query_file!(
+      "queries/login_throttle_attempt_ip.sql",
+      ip,
+      now,
+      forget_before
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    check_lock(attempt.locked_until, now)?;
+    lock(
+      &mut tx,
+      auth_settings,
+      attempt.id,
+      attempt.failed_count,
+      auth_settings.login_ip_max_failed_attempts,
+      now,
+    )
+    .await?;
+  }
+  tx.commit().await?;
+  Ok(now)
+}
+
+/// Uncounts attempt whose credentials were correct, lifting lock it may have set.
+/// Lock is also lifted while attempt is the last one counted, as any lock is then either
+/// set by it (once earlier lock expired, attempt doubles it) or expired already.
+pub(crate) async fn forgive_login_attempt(
+  pool: &PgPool,
+  auth_settings: &AuthSettings,
+  ip: Option<IpNetwork>,
+  user_id: Option<Uuid>,
+  attempted_at: DateTime<Utc>,
+) -> Result<(), APIError> {
+  if let Some(user_id) = user_id {
+    sqlx::query_file!(
+      "queries/login_throttle_forgive_user.sql",
+      user_id,
+      auth_settings.login_max_failed_attempts,
+      attempted_at
+    )
+    .execute(pool)
+    .await?;
+  }
+  if let Some(ip) = ip {
+    sqlx::query_file!(
+      "queries/login_throttle_forgive_ip.sql",
+      ip,
+      auth_settings.login_ip_max_failed_attempts,
+      attempted_at
+    )
+    .execute(pool)
+    .await?;
+  }
+  Ok(())
+}
+
+/// Forgets failed logins of account after successful login or when admin unlocks it.
+/// Failures from IP address are only forgotten with time, so one known account can't reset them.
+pub(crate) async fn clear_login_failures(pool: &PgPool, user_id: Uuid) -> Result<(), APIError> {
+  sqlx::query_file!("queries/login_throttle_unlock_user.sql", user_id)
+    .execute(pool)
+    .await?;
+  Ok(())
+}
+
+/// Rejects attempt while lock set by earlier attempts lasts, counting it is rolled back.
+fn check_lock(locked_until: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<(), APIError> {
+  match locked_until.filter(|locked_until| *locked_until > now) {
+    Some(locked_until) => Err(
+      UserError::LoginLocked {
+        // Rounded up, so retrying after it is never too early.
+        retry_after_seconds: ((locked_until - now).num_milliseconds() + 999) / 1000,
+      }
+      .into(),
+    ),
+    None => Ok(()),
+  }
+}
+
+/// Locks account or IP address once attempts reach the limit.
+async fn lock(
+  conn: &mut PgConnection,
+  auth_settings: &AuthSettings,
+  id: Uuid,
+  failed_count: i32,
+  max_failed_attempts: i32,
+  now: DateTime<Utc>,
+) -> Result<(), APIError> {
+  if failed_count < max_failed_attempts {
+    return Ok(());
+  }
+  // Exponent is capped so it can't overflow, lockout is capped by settings long before that.
+  let doublings = (failed_count - max_failed_attempts).min(30) as u32;
+  let lockout_seconds = auth_settings
+    .login_lockout_seconds
+    .saturating_mul(1 << doublings)
+    .min(auth_settings.login_lockout_max_seconds);
+  sqlx::query_file!(
+    "queries/login_throttle_lock.sql",
+    id,
+    now + Duration::seconds(lockout_seconds)
+  )
+  .execute(conn)
+  .await?;
+  Ok(())
+}
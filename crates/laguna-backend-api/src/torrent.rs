@@ -1,39 +1,52 @@
 use actix_web::{web, HttpResponse};
-use actix_web_validator::Json;
+use actix_web_validator::{Json, Query};
 use bendy::decoding::FromBencode;
 use bendy::encoding::ToBencode;
 
-use laguna_backend_tracker_common::info_hash::SHA1_LENGTH;
+use laguna_backend_tracker_common::info_hash::{SHA1_LENGTH, SHA256_LENGTH};
 use sha1::Sha1;
 
 use actix_multipart_extract::Multipart;
 use chrono::{DateTime, Utc};
-use laguna_backend_dto::torrent::{TorrentDTO, TorrentFile, TorrentPatchDTO, TorrentPutDTO};
+use laguna_backend_dto::torrent::{
+  MagnetDTO, SortOrder, TorrentCursor, TorrentDTO, TorrentFile, TorrentFilesDTO, TorrentPageDTO,
+  TorrentPatchDTO, TorrentPutDTO, TorrentSearchDTO, TorrentSort, TorrentSummaryDTO,
+  TorrentTrashDTO,
+};
+use laguna_backend_dto::torrent_revision::TorrentRevisionDTO;
 use laguna_backend_dto::user::UserDTO;
 use laguna_backend_middleware::mime::{APPLICATION_LAGUNA_JSON_VERSIONED, APPLICATION_XBITTORRENT};
 use laguna_backend_model::behaviour::Behaviour;
+use laguna_backend_model::consts::TORRENT_PAGE_SIZE_DEFAULT;
 use laguna_backend_model::genre::Genre;
 use laguna_backend_model::peer::Peer;
 use laguna_backend_model::torrent::Torrent;
+use laguna_backend_model::torrent_revision::TorrentRevision;
 
 use digest::Digest;
 use laguna_backend_model::speedlevel::SpeedLevel;
 
-use laguna_backend_model::download::{Download, DownloadHash};
+use laguna_backend_model::user::Passkey;
 use sqlx::PgPool;
 
+use laguna_backend_config::TrackerSettings;
 use laguna_backend_tracker::prelude::info_hash::InfoHash;
 use uuid::Uuid;
 
-use crate::error::download::DownloadError;
-use crate::error::{torrent::TorrentError, APIError};
+use crate::error::user::UserError;
+use crate::error::{
+  torrent::{MetainfoError, TorrentError},
+  APIError,
+};
+use crate::helpers::metainfo::{canonicalize_metainfo, validate_metainfo};
+use crate::peer::stale_peer_cutoff;
 use sha2::Sha256;
 
 #[utoipa::path(
   get,
   path = "/api/torrent/{info_hash}",
   responses(
-    (status = 200, description = "Returns torrent.", body = Torrent, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 200, description = "Returns torrent.", body = TorrentDTO<N>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
   )
@@ -42,19 +55,20 @@ pub async fn torrent_get<const N: usize>(
   info_hash: web::Path<InfoHash<N>>,
   pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, APIError> {
-  let torrent = sqlx::query_file_as!(
-    Torrent,
+  let torrent_dto = sqlx::query_file_as!(
+    Torrent::<N>,
     "queries/torrent_get.sql",
     info_hash.into_inner() as _
   )
   .fetch_optional(pool.get_ref())
   .await?
-  .map(TorrentDTO::from)
+  .map(TorrentDTO::try_from)
+  .transpose()?
   .ok_or(TorrentError::NotFound)?;
   Ok(
     HttpResponse::Ok()
       .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
-      .json(torrent),
+      .json(torrent_dto),
   )
 }
 
@@ -73,71 +87,21 @@ pub async fn torrent_get_raw<const N: usize>(
   user: UserDTO,
   domestic_announce_url: web::Data<String>,
 ) -> Result<HttpResponse, APIError> {
-  let info_hash = info_hash.into_inner();
-  let download = sqlx::query_file_as!(
-    Download::<N>,
-    "queries/download_lookup.sql",
-    info_hash as _,
-    user.id
+  let torrent_bytes = sqlx::query_file_as!(
+    Torrent::<N>,
+    "queries/torrent_get.sql",
+    info_hash.into_inner() as _
   )
   .fetch_optional(pool.get_ref())
-  .await?;
-
-  let torrent_bytes = sqlx::query_file_as!(Torrent, "queries/torrent_get.sql", info_hash as _)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or(TorrentError::NotFound)?
-    .raw;
+  .await?
+  .ok_or(TorrentError::NotFound)?
+  .raw;
 
   let mut torrent = TorrentFile::from_bencode(&torrent_bytes)?;
 
-  let down_ts = Utc::now();
-  let down_hash = Sha256::digest(
-    [
-      &info_hash.0,
-      &user.id.to_bytes_le()[..],
-      &down_ts.timestamp().to_le_bytes(),
-    ]
-    .concat(),
-  );
-
-  // overwrite annouce url by adding down_hash
-  torrent.announce_url = Some(format!(
-    "{}?down_hash={}",
-    domestic_announce_url.into_inner(),
-    DownloadHash::from(down_hash.to_vec())
-  ));
-
-  match download {
-    Some(_) => {
-      sqlx::query_file_as!(
-        Download::<N>,
-        "queries/download_update.sql",
-        down_ts,
-        down_hash.as_slice(),
-        info_hash as _,
-        user.id
-      )
-      .fetch_optional(pool.get_ref())
-      .await?
-      .map(drop)
-      .ok_or(DownloadError::NotUpdated)?;
-    },
-    None => {
-      sqlx::query_file_as!(
-        Download::<N>,
-        "queries/download_insert.sql",
-        info_hash as _,
-        user.id,
-        down_ts,
-        down_hash.as_slice()
-      )
-      .fetch_optional(pool.get_ref())
-      .await?
-      .map(drop)
-      .ok_or(DownloadError::NotCreated)?;
-    },
-  }
+  // overwrite annouce url by embedding user's passkey
+  torrent.announce_url =
+    Some(user_announce_url(pool.get_ref(), &user, &domestic_announce_url).await?);
 
   let torrent_bytes = torrent.to_bencode()?;
 
@@ -148,11 +112,124 @@ pub async fn torrent_get_raw<const N: usize>(
   )
 }
 
+#[utoipa::path(
+  get,
+  path = "/api/torrent/{info_hash}/files",
+  responses(
+    (status = 200, description = "Returns directory tree of files in torrent.", body = TorrentFilesDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  )
+)]
+pub async fn torrent_files<const N: usize>(
+  info_hash: web::Path<InfoHash<N>>,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let torrent_bytes = sqlx::query_file_as!(
+    Torrent::<N>,
+    "queries/torrent_get.sql",
+    info_hash.into_inner() as _
+  )
+  .fetch_optional(pool.get_ref())
+  .await?
+  .ok_or(TorrentError::NotFound)?
+  .raw;
+
+  let torrent = TorrentFile::from_bencode(&torrent_bytes)?;
+
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(TorrentFilesDTO::from(&torrent.info)),
+  )
+}
+
+/// Magnet link of torrent, with user's passkey in tracker URL.
+#[utoipa::path(
+  get,
+  path = "/api/torrent/{info_hash}/magnet",
+  responses(
+    (status = 200, description = "Returns magnet link of torrent.", body = MagnetDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  )
+)]
+pub async fn torrent_magnet<const N: usize>(
+  info_hash: web::Path<InfoHash<N>>,
+  pool: web::Data<PgPool>,
+  user: UserDTO,
+  domestic_announce_url: web::Data<String>,
+) -> Result<HttpResponse, APIError> {
+  let torrent = sqlx::query_file_as!(
+    Torrent::<N>,
+    "queries/torrent_get.sql",
+    info_hash.into_inner() as _
+  )
+  .fetch_optional(pool.get_ref())
+  .await?
+  .ok_or(TorrentError::NotFound)?;
+
+  let torrent_file = TorrentFile::from_bencode(&torrent.raw)?;
+  let raw_info = TorrentFile::raw_info(&torrent.raw)?;
+
+  // Hybrid torrents get both exact topics (BEP 9, BEP 52).
+  let mut magnet = String::from("magnet:?");
+  if torrent_file.info.is_v1() {
+    let info_hash_v1 = InfoHash::<SHA1_LENGTH>(Sha1::digest(raw_info).into());
+    magnet.push_str(&format!("xt=urn:btih:{}&", info_hash_v1));
+  }
+  if let Some(info_hash_v2) = torrent.info_hash_v2 {
+    // Multihash prefix: 0x12 is sha2-256, 0x20 is digest length.
+    magnet.push_str(&format!("xt=urn:btmh:1220{}&", info_hash_v2));
+  }
+
+  // Same personalized announce url as in downloaded .torrent, so announces are attributed to user.
+  let announce_url = user_announce_url(pool.get_ref(), &user, &domestic_announce_url).await?;
+  magnet.push_str(
+    &serde_urlencoded::to_string([
+      ("dn", torrent.file_name),
+      ("xl", torrent.length.to_string()),
+      ("tr", announce_url),
+    ])
+    .map_err(|_| TorrentError::Invalid)?,
+  );
+
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(MagnetDTO { magnet }),
+  )
+}
+
+/// Announce url of domestic tracker with user's passkey embedded.
+async fn user_announce_url(
+  pool: &PgPool,
+  user: &UserDTO,
+  domestic_announce_url: &str,
+) -> Result<String, APIError> {
+  // UserDTO doesn't carry passkey, it has to be looked up.
+  let passkey = sqlx::query_file_scalar!("queries/user_passkey_get.sql", user.id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(UserError::NotFound)?;
+  let passkey = Passkey::try_from(passkey).map_err(|err| sqlx::Error::Decode(err.into()))?;
+  Ok(passkey_announce_url(domestic_announce_url, &passkey))
+}
+
+/// Embeds passkey in announce URL before its last segment,
+/// eg. `http://tracker/peer/announce` becomes `http://tracker/peer/{passkey}/announce`.
+fn passkey_announce_url(announce_url: &str, passkey: &Passkey) -> String {
+  match announce_url.rsplit_once('/') {
+    Some((base, endpoint)) => format!("{}/{}/{}", base, passkey, endpoint),
+    None => format!("{}/{}", passkey, announce_url),
+  }
+}
+
 #[utoipa::path(
   patch,
   path = "/api/torrent/{info_hash}",
   responses(
-    (status = 200, description = "Returns updated torrent.", body = Torrent, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 200, description = "Returns updated torrent.", body = TorrentDTO<N>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
   ),
@@ -162,19 +239,139 @@ pub async fn torrent_patch<const N: usize>(
   info_hash: web::Path<InfoHash<N>>,
   torrent_dto: Json<TorrentPatchDTO>,
   pool: web::Data<PgPool>,
+  user: UserDTO,
 ) -> Result<HttpResponse, APIError> {
   let torrent_patch = torrent_dto.into_inner();
+  let torrent_dto = torrent_update(
+    pool.get_ref(),
+    info_hash.into_inner(),
+    torrent_patch.nfo,
+    torrent_patch.genre,
+    &user,
+  )
+  .await?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(torrent_dto),
+  )
+}
+
+/// Updates editable metadata of torrent and records change as [`TorrentRevision`].
+async fn torrent_update<const N: usize>(
+  pool: &PgPool,
+  info_hash: InfoHash<N>,
+  nfo: Option<String>,
+  genre: Option<Genre>,
+  user: &UserDTO,
+) -> Result<TorrentDTO<N>, APIError> {
+  let mut tx = pool.begin().await?;
+
+  // Torrent is locked until commit, so revision snapshots values it is going to overwrite.
+  let lock = sqlx::query_file!("queries/torrent_lock.sql", info_hash as _)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(TorrentError::NotUpdated)?;
+  // Trashed torrents are hidden, they have to be restored before editing.
+  if lock.is_trashed {
+    return Err(TorrentError::NotUpdated.into());
+  }
+  let stored_info_hash = lock.info_hash;
+
+  let modded_at = Utc::now();
+  sqlx::query_file!(
+    "queries/torrent_revision_insert.sql",
+    stored_info_hash,
+    nfo,
+    genre as _,
+    modded_at,
+    user.id
+  )
+  .execute(&mut *tx)
+  .await?;
+
   let torrent_dto = sqlx::query_file_as!(
-    Torrent,
+    Torrent::<N>,
     "queries/torrent_update.sql",
-    torrent_patch.nfo,
-    torrent_patch.genre as _,
+    nfo,
+    genre as _,
+    info_hash as _,
+    modded_at,
+    user.id
+  )
+  .fetch_optional(&mut *tx)
+  .await?
+  .map(TorrentDTO::try_from)
+  .transpose()?
+  .ok_or(TorrentError::NotUpdated)?;
+
+  tx.commit().await?;
+  Ok(torrent_dto)
+}
+
+#[utoipa::path(
+  get,
+  path = "/api/torrent/{info_hash}/revisions",
+  responses(
+    (status = 200, description = "Returns revisions of torrent, newest first.", body = Vec<TorrentRevisionDTO<N>>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  )
+)]
+pub async fn torrent_revisions<const N: usize>(
+  info_hash: web::Path<InfoHash<N>>,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let revisions = sqlx::query_file_as!(
+    TorrentRevision::<N>,
+    "queries/torrent_revision_list.sql",
     info_hash.into_inner() as _
   )
+  .fetch_all(pool.get_ref())
+  .await?
+  .into_iter()
+  .map(TorrentRevisionDTO::from)
+  .collect::<Vec<_>>();
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(revisions),
+  )
+}
+
+/// Reverts torrent to state before given revision, undoing it and all later revisions.
+/// Revert is recorded as a new revision, so it can be reverted as well.
+#[utoipa::path(
+  post,
+  path = "/api/torrent/{info_hash}/revisions/{id}/revert",
+  responses(
+    (status = 200, description = "Returns reverted torrent.", body = TorrentDTO<N>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Torrent or revision not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  )
+)]
+pub async fn torrent_revision_revert<const N: usize>(
+  path: web::Path<(InfoHash<N>, Uuid)>,
+  pool: web::Data<PgPool>,
+  user: UserDTO,
+) -> Result<HttpResponse, APIError> {
+  let (info_hash, id) = path.into_inner();
+  let revision = sqlx::query_file_as!(
+    TorrentRevision::<N>,
+    "queries/torrent_revision_get.sql",
+    id,
+    info_hash.clone() as _
+  )
   .fetch_optional(pool.get_ref())
   .await?
-  .map(TorrentDTO::from)
-  .ok_or(TorrentError::NotUpdated)?;
+  .ok_or(TorrentError::RevisionNotFound)?;
+  let torrent_dto = torrent_update(
+    pool.get_ref(),
+    info_hash,
+    revision.old_nfo,
+    revision.old_genre,
+    &user,
+  )
+  .await?;
   Ok(
     HttpResponse::Ok()
       .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
@@ -182,13 +379,103 @@ pub async fn torrent_patch<const N: usize>(
   )
 }
 
+#[utoipa::path(
+  get,
+  path = "/api/torrent",
+  responses(
+    (status = 200, description = "Returns page of torrents matching query.", body = TorrentPageDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Invalid query.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+  params(TorrentSearchDTO)
+)]
+pub async fn torrent_search(
+  search: Query<TorrentSearchDTO>,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let search = search.into_inner();
+  let limit = search.limit.unwrap_or(TORRENT_PAGE_SIZE_DEFAULT);
+  let sort = match search.sort {
+    TorrentSort::Seeders => "seeders",
+    TorrentSort::Completed => "completed",
+    TorrentSort::Rating => "rating",
+    TorrentSort::Date => "date",
+  };
+  let (cursor_key, cursor_info_hash) = search
+    .cursor
+    .map(|cursor| (cursor.key, cursor.info_hash))
+    .unzip();
+  // Fetch one more than requested to know if there is a next page.
+  let mut rows = sqlx::query_file!(
+    "queries/torrent_search.sql",
+    search.q,
+    search.genre as _,
+    search.speedlevel as _,
+    search.is_freeleech,
+    search.uploaded_by,
+    search.uploaded_after,
+    search.uploaded_before,
+    sort,
+    search.order == SortOrder::Desc,
+    cursor_key,
+    cursor_info_hash as _,
+    limit + 1
+  )
+  .fetch_all(pool.get_ref())
+  .await?;
+
+  let next_cursor = if rows.len() as i64 > limit {
+    rows.truncate(limit as usize);
+    rows.last().map(|row| TorrentCursor {
+      key: row.sort_key,
+      info_hash: row.info_hash.clone(),
+    })
+  } else {
+    None
+  };
+  let torrents = rows
+    .into_iter()
+    .map(|row| TorrentSummaryDTO {
+      info_hash: row.info_hash,
+      info_hash_v2: row.info_hash_v2,
+      announce_url: row.announce_url,
+      length: row.length,
+      file_name: row.file_name,
+      nfo: row.nfo,
+      is_freeleech: row.is_freeleech,
+      genre: row.genre,
+      leech_count: row.leech_count,
+      seed_count: row.seed_count,
+      completed_count: row.completed_count,
+      speedlevel: row.speedlevel,
+      creation_date: row.creation_date,
+      created_by: row.created_by,
+      uploaded_at: row.uploaded_at,
+      uploaded_by: row.uploaded_by,
+      modded_at: row.modded_at,
+      modded_by: row.modded_by,
+    })
+    .collect();
+
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(TorrentPageDTO {
+        torrents,
+        next_cursor,
+      }),
+  )
+}
+
 #[utoipa::path(
   put,
   path = "/api/torrent",
   responses(
-    (status = 200, description = "Returns created torrent.", body = Torrent, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 200, description = "Returns created torrent, info hash is of canonical (tracker-ready) torrent.", body = TorrentDTO<N>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 208, description = "Torrent with same v1 or v2 info hash already exists."),
     (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 422, description = "Torrent metainfo is invalid, returns every violated rule with its details.", content_type = "application/json"),
   ),
   request_body(content = TorrentPutDTO, content_type = "multipart/form-data"),
 )]
@@ -201,51 +488,53 @@ pub async fn torrent_put<const N: usize>(
   /*if user.role < Role::Verified {
     return Err(UserError::InvalidCredentials.into());
   }*/
-  // TODO: Bytes scanning middleware
   if form.torrent.content_type != APPLICATION_XBITTORRENT {
     return Ok(HttpResponse::UnsupportedMediaType().finish());
   }
-  let torrent_file = TorrentFile::from_bencode(&form.torrent.bytes)?;
-  // Deny torrents with announce list present
-  if torrent_file.announce_list.is_some() {
-    return Err(TorrentError::Invalid.into());
-  }
+  let mut torrent_file = TorrentFile::from_bencode(&form.torrent.bytes)?;
+  validate_metainfo(&torrent_file).map_err(TorrentError::from)?;
+  canonicalize_metainfo(&mut torrent_file, &domestic_announce_url);
+  let torrent_bytes = torrent_file.to_bencode()?;
 
-  // Deny torrents with foreign announce url
-  let domestic_announce_url = domestic_announce_url.into_inner();
-  match torrent_file.announce_url {
-    Some(announce_url_inner) if announce_url_inner != *domestic_announce_url => {
-      eprintln!("{} ::: {}", announce_url_inner, *domestic_announce_url);
-      return Err(TorrentError::Invalid.into());
-    },
-    // TODO: Remove `None` and adjust tests so that torrents with domestic announce url are used.
-    Some(_) | None => (),
-  }
-
-  match torrent_file.info.private {
-    Some(private) if private != 1 => return Err(TorrentError::Invalid.into()),
-    // TODO: Remove `None` and adjust tests so that torrents with private flag are used.
-    Some(_) | None => (),
-  }
-
-  let info_hash = Sha1::digest(torrent_file.info.to_bencode()?);
-  // TODO: BitTorrent v2 needs SHA256_LENGTH
-  let info_hash = InfoHash::<SHA1_LENGTH>(info_hash.try_into().unwrap());
-  let maybe_torrent =
-    sqlx::query_file_as!(Torrent, "queries/torrent_get.sql", info_hash.clone() as _)
-      .fetch_optional(pool.get_ref())
-      .await?;
-  if maybe_torrent.is_some() {
+  // Info hashes are computed over info dictionary of canonical torrent (BEP 3, BEP 52).
+  // Hybrid torrents have both, they are stored under v1 info hash.
+  let raw_info = TorrentFile::raw_info(&torrent_bytes)?;
+  let info_hash_v1 = torrent_file
+    .info
+    .is_v1()
+    .then(|| InfoHash::<SHA1_LENGTH>(Sha1::digest(raw_info).into()));
+  let info_hash_v2 = torrent_file
+    .info
+    .is_v2()
+    .then(|| InfoHash::<SHA256_LENGTH>(Sha256::digest(raw_info).into()));
+  let info_hash = match (info_hash_v1, &info_hash_v2) {
+    (Some(info_hash_v1), _) => info_hash_v1,
+    // v2 only torrents are stored under truncated v2 info hash, as used by v1 tracker protocol.
+    (None, Some(info_hash_v2)) => InfoHash::from(info_hash_v2.0[..SHA1_LENGTH].to_vec()),
+    (None, None) => return Err(TorrentError::Invalid.into()),
+  };
+  // Both info hashes are unique, so either of them being known means torrent is a duplicate.
+  let exists = sqlx::query_file_scalar!(
+    "queries/torrent_exists.sql",
+    info_hash.clone() as _,
+    info_hash_v2.clone() as _
+  )
+  .fetch_one(pool.get_ref())
+  .await?;
+  if exists {
     return Ok(HttpResponse::AlreadyReported().finish());
   }
 
-  let torrent_dto = sqlx::query_file_as!(
-    Torrent,
+  let inserted = sqlx::query_file_as!(
+    Torrent::<SHA1_LENGTH>,
     "queries/torrent_insert.sql",
     info_hash as _,
-    form.torrent.bytes, // torrent is already bencoded so we can just insert it
+    torrent_bytes,
     torrent_file.announce_url,
-    torrent_file.info.length,
+    torrent_file
+      .info
+      .total_length()
+      .ok_or(TorrentError::Metainfo(vec![MetainfoError::TotalLength]))?,
     torrent_file.info.name.clone(),
     torrent_file.nfo,
     None::<Genre> as _,
@@ -259,11 +548,20 @@ pub async fn torrent_put<const N: usize>(
     Utc::now(),
     user.id,
     None::<DateTime::<Utc>>,
-    None::<Uuid>
+    None::<Uuid>,
+    info_hash_v2 as _
   )
   .fetch_optional(pool.get_ref())
-  .await?
-  .map(TorrentDTO::from)
+  .await;
+  let torrent_dto = match inserted {
+    // Same torrent uploaded concurrently.
+    Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+      return Ok(HttpResponse::AlreadyReported().finish());
+    },
+    inserted => inserted?,
+  }
+  .map(TorrentDTO::try_from)
+  .transpose()?
   .ok_or(TorrentError::NotCreated)?;
 
   Ok(
@@ -276,24 +574,92 @@ pub async fn torrent_put<const N: usize>(
 #[utoipa::path(
   delete,
   path = "/api/torrent/{info_hash}",
+  request_body = TorrentTrashDTO,
   responses(
-    (status = 200, description = "Returns deleted torrent.", body = Torrent, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
-    (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 200, description = "Returns trashed torrent.", body = TorrentDTO<N>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Torrent not found or already trashed.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
   )
 )]
 pub async fn torrent_delete<const N: usize>(
   info_hash: web::Path<InfoHash<N>>,
+  torrent_trash: Json<TorrentTrashDTO>,
   pool: web::Data<PgPool>,
+  user: UserDTO,
 ) -> Result<HttpResponse, APIError> {
   let torrent_dto = sqlx::query_file_as!(
-    Torrent,
+    Torrent::<N>,
+    "queries/torrent_trash.sql",
+    info_hash.into_inner() as _,
+    Utc::now(),
+    user.id,
+    torrent_trash.into_inner().reason
+  )
+  .fetch_optional(pool.get_ref())
+  .await?
+  .map(TorrentDTO::try_from)
+  .transpose()?
+  .ok_or(TorrentError::NotUpdated)?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(torrent_dto),
+  )
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/torrent/{info_hash}/restore",
+  responses(
+    (status = 200, description = "Returns restored torrent.", body = TorrentDTO<N>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Torrent not found or not trashed.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  )
+)]
+pub async fn torrent_restore<const N: usize>(
+  info_hash: web::Path<InfoHash<N>>,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let torrent_dto = sqlx::query_file_as!(
+    Torrent::<N>,
+    "queries/torrent_restore.sql",
+    info_hash.into_inner() as _
+  )
+  .fetch_optional(pool.get_ref())
+  .await?
+  .map(TorrentDTO::try_from)
+  .transpose()?
+  .ok_or(TorrentError::NotUpdated)?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(torrent_dto),
+  )
+}
+
+/// Permanently deletes torrent, regardless of whether it was trashed.
+#[utoipa::path(
+  delete,
+  path = "/api/torrent/{info_hash}/purge",
+  responses(
+    (status = 200, description = "Returns purged torrent.", body = TorrentDTO<N>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  )
+)]
+pub async fn torrent_purge<const N: usize>(
+  info_hash: web::Path<InfoHash<N>>,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let torrent_dto = sqlx::query_file_as!(
+    Torrent::<N>,
     "queries/torrent_delete.sql",
     info_hash.into_inner() as _
   )
   .fetch_optional(pool.get_ref())
   .await?
-  .map(TorrentDTO::from)
+  .map(TorrentDTO::try_from)
+  .transpose()?
   .ok_or(TorrentError::NotFound)?;
   Ok(
     HttpResponse::Ok()
@@ -306,7 +672,7 @@ pub async fn torrent_delete<const N: usize>(
   get,
   path = "/api/torrent/{info_hash}/swarm",
   responses(
-    (status = 200, description = "Returns torrent swarm.", body = Vec<Peer>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 200, description = "Returns torrent swarm.", body = Vec<Peer<N>>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 400, description = "Torrent not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
   )
@@ -314,11 +680,13 @@ pub async fn torrent_delete<const N: usize>(
 pub async fn torrent_swarm<const N: usize>(
   info_hash: web::Path<InfoHash<N>>,
   pool: web::Data<PgPool>,
+  tracker_settings: web::Data<TrackerSettings>,
 ) -> Result<HttpResponse, APIError> {
   let swarm = sqlx::query_file_as!(
-    Peer,
+    Peer::<N>,
     "queries/torrent_swarm.sql",
-    info_hash.into_inner() as _
+    info_hash.into_inner() as _,
+    stale_peer_cutoff(tracker_settings.get_ref())
   )
   .fetch_all(pool.get_ref())
   .await?;
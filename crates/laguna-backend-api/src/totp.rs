@@ -0,0 +1,379 @@
+use actix_jwt_auth_middleware::TokenSigner;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_validator::Json;
+use chrono::{Duration, Utc};
+use digest::Digest;
+use jwt_compact::alg::{Hs256, Hs256Key};
+use laguna_backend_config::AuthSettings;
+use laguna_backend_dto::totp::{
+  LoginChallengeDTO, LoginTotpDTO, LoginTotpEnrollDTO, TotpCodeDTO, TotpEnrollDTO,
+};
+use laguna_backend_dto::user::UserDTO;
+use laguna_backend_middleware::mime::APPLICATION_LAGUNA_JSON_VERSIONED;
+use laguna_backend_model::behaviour::Behaviour;
+use laguna_backend_model::consts::{
+  TOTP_CODE_LEN, TOTP_RECOVERY_CODE_COUNT, TOTP_RECOVERY_CODE_LEN, TOTP_STEP_SECONDS,
+};
+use laguna_backend_model::role::Role;
+use laguna_backend_model::totp::Totp;
+use laguna_backend_model::user::{Passkey, User, UserSafe};
+use secrecy::ExposeSecret;
+use sha2::Sha256;
+use sqlx::types::ipnetwork::IpNetwork;
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::error::{user::UserError, APIError};
+use crate::helpers::request::request_ip;
+use crate::helpers::token::{sign_token, token_user_id, verify_token, TokenPurpose};
+use crate::login::login_complete;
+use crate::throttle::{forgive_login_attempt, record_login_attempt};
+
+const TOTP_ISSUER: &str = "Laguna";
+
+#[utoipa::path(
+  post,
+  path = "/api/user/me/totp",
+  responses(
+    (status = 200, description = "Returns secret and recovery codes of new pending enrollment, previous pending enrollment is replaced.", body = TotpEnrollDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "TOTP is already enabled.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn user_me_totp_enroll(
+  user: UserDTO,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::NotFound)?;
+  let enroll_dto = enroll(pool.get_ref(), &user).await?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(enroll_dto),
+  )
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/me/totp/confirm",
+  request_body = TotpCodeDTO,
+  responses(
+    (status = 200, description = "TOTP enabled, logins require code from now on."),
+    (status = 400, description = "TOTP is not being enrolled.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Wrong code or not logged in.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 429, description = "Too many wrong codes, retry after time in Retry-After header.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn user_me_totp_confirm(
+  req: HttpRequest,
+  totp_code_dto: Json<TotpCodeDTO>,
+  user: UserDTO,
+  pool: web::Data<PgPool>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let totp = sqlx::query_file_as!(Totp, "queries/totp_get.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(UserError::TotpNotEnabled)?;
+  if totp.confirmed_at.is_some() {
+    return Err(UserError::TotpAlreadyEnabled.into());
+  }
+  verify_code_throttled(
+    pool.get_ref(),
+    &auth_settings,
+    request_ip(&req, &auth_settings.trusted_proxies),
+    &totp,
+    &totp_code_dto.code,
+  )
+  .await?;
+  Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+  delete,
+  path = "/api/user/me/totp",
+  request_body = TotpCodeDTO,
+  responses(
+    (status = 200, description = "TOTP disabled and recovery codes deleted."),
+    (status = 400, description = "TOTP is not enabled.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Wrong code or not logged in.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 403, description = "Admins can't disable TOTP.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 429, description = "Too many wrong codes, retry after time in Retry-After header.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn user_me_totp_disable(
+  req: HttpRequest,
+  totp_code_dto: Json<TotpCodeDTO>,
+  user: UserDTO,
+  pool: web::Data<PgPool>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::NotFound)?;
+  if user.role == Role::Admin {
+    return Err(UserError::TotpRequired.into());
+  }
+  let totp = sqlx::query_file_as!(Totp, "queries/totp_get.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .filter(|totp| totp.confirmed_at.is_some())
+    .ok_or(UserError::TotpNotEnabled)?;
+  verify_code_throttled(
+    pool.get_ref(),
+    &auth_settings,
+    request_ip(&req, &auth_settings.trusted_proxies),
+    &totp,
+    &totp_code_dto.code,
+  )
+  .await?;
+  sqlx::query_file_scalar!("queries/totp_delete.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(UserError::TotpNotEnabled)?;
+  Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/login/totp",
+  request_body = LoginTotpDTO,
+  responses(
+    (status = 200, description = "User logged in successfully.", body = UserDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json", headers(
+      ("X-Access-Token" = String, description = "Access token."),
+      ("X-Refresh-Token" = String, description = "Refresh token.")
+    )),
+    (status = 400, description = "Invalid or expired login challenge.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Wrong or already used code.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 429, description = "Too many failed logins, retry after time in Retry-After header.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn login_totp(
+  req: HttpRequest,
+  login_totp_dto: Json<LoginTotpDTO>,
+  pool: web::Data<PgPool>,
+  signer: web::Data<TokenSigner<UserDTO, Hs256>>,
+  secret_key: web::Data<Hs256Key>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let user = challenged_user(pool.get_ref(), &auth_settings, &login_totp_dto.token).await?;
+  let totp = sqlx::query_file_as!(Totp, "queries/totp_get.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    // Pending enrollment can only be confirmed here when it is forced.
+    .filter(|totp| totp.confirmed_at.is_some() || user.role == Role::Admin)
+    .ok_or(UserError::TotpNotEnabled)?;
+  verify_code_throttled(
+    pool.get_ref(),
+    &auth_settings,
+    request_ip(&req, &auth_settings.trusted_proxies),
+    &totp,
+    &login_totp_dto.code,
+  )
+  .await?;
+  login_complete(
+    pool.get_ref(),
+    signer.get_ref(),
+    secret_key.get_ref(),
+    auth_settings.get_ref(),
+    &req,
+    user.id,
+  )
+  .await
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/login/totp/enroll",
+  request_body = LoginTotpEnrollDTO,
+  responses(
+    (status = 200, description = "Returns secret and recovery codes of enrollment required to log in, first code confirms it.", body = TotpEnrollDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "Invalid or expired login challenge or enrollment is not required.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn login_totp_enroll(
+  login_totp_enroll_dto: Json<LoginTotpEnrollDTO>,
+  pool: web::Data<PgPool>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let user = challenged_user(pool.get_ref(), &auth_settings, &login_totp_enroll_dto.token).await?;
+  if user.role != Role::Admin {
+    return Err(UserError::TotpNotEnabled.into());
+  }
+  let enroll_dto = enroll(pool.get_ref(), &user).await?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(enroll_dto),
+  )
+}
+
+/// Challenge for second login step, if `user` has TOTP enabled or has to enroll it.
+/// Admins can't log in without TOTP.
+pub(crate) async fn login_challenge(
+  pool: &PgPool,
+  auth_settings: &AuthSettings,
+  user: &UserSafe,
+) -> Result<Option<LoginChallengeDTO>, APIError> {
+  let is_enabled = sqlx::query_file_as!(Totp, "queries/totp_get.sql", user.id)
+    .fetch_optional(pool)
+    .await?
+    .is_some_and(|totp| totp.confirmed_at.is_some());
+  let enrollment_required = !is_enabled && user.role == Role::Admin;
+  if !is_enabled && !enrollment_required {
+    return Ok(None);
+  }
+  // Token is bound to current password hash, so password change invalidates it.
+  let token = sign_token(
+    auth_settings.secret_key.expose_secret(),
+    TokenPurpose::LoginChallenge,
+    user.id,
+    user.password.expose_secret(),
+    Utc::now() + Duration::seconds(auth_settings.login_challenge_token_lifetime_seconds),
+  );
+  Ok(Some(LoginChallengeDTO {
+    token,
+    enrollment_required,
+  }))
+}
+
+/// User who passed first login step and received `token`.
+async fn challenged_user(
+  pool: &PgPool,
+  auth_settings: &AuthSettings,
+  token: &str,
+) -> Result<UserSafe, APIError> {
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", token_user_id(token)?)
+    .fetch_optional(pool)
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::InvalidToken)?;
+  verify_token(
+    auth_settings.secret_key.expose_secret(),
+    TokenPurpose::LoginChallenge,
+    token,
+    user.password.expose_secret(),
+  )?;
+  // User could have been disabled since first step.
+  if !user.is_enabled {
+    return Err(UserError::Disabled.into());
+  }
+  Ok(user)
+}
+
+/// Starts new pending enrollment, replacing previous pending one and its recovery codes.
+async fn enroll(pool: &PgPool, user: &UserSafe) -> Result<TotpEnrollDTO, APIError> {
+  let Secret::Encoded(secret) = Secret::generate_secret().to_encoded() else {
+    unreachable!("Secret is encoded")
+  };
+  let recovery_codes = (0..TOTP_RECOVERY_CODE_COUNT)
+    .map(|_| hex::encode(rand::random::<[u8; TOTP_RECOVERY_CODE_LEN / 2]>()))
+    .collect::<Vec<_>>();
+  let recovery_code_hashes = recovery_codes
+    .iter()
+    .map(|recovery_code| recovery_code_hash(recovery_code))
+    .collect::<Vec<_>>();
+
+  let mut tx = pool.begin().await?;
+  sqlx::query_file_scalar!("queries/totp_upsert.sql", user.id, secret, Utc::now())
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(UserError::TotpAlreadyEnabled)?;
+  sqlx::query_file!("queries/totp_recovery_code_delete_all.sql", user.id)
+    .execute(&mut *tx)
+    .await?;
+  sqlx::query_file!(
+    "queries/totp_recovery_code_insert.sql",
+    user.id,
+    &recovery_code_hashes
+  )
+  .execute(&mut *tx)
+  .await?;
+  tx.commit().await?;
+
+  Ok(TotpEnrollDTO {
+    otpauth_uri: new_totp(&secret, user.username.clone()).get_url(),
+    secret,
+    recovery_codes,
+  })
+}
+
+/// Verifies code as login attempt of its user, so codes can't be brute-forced.
+async fn verify_code_throttled(
+  pool: &PgPool,
+  auth_settings: &AuthSettings,
+  ip: Option<IpNetwork>,
+  totp: &Totp,
+  code: &str,
+) -> Result<(), APIError> {
+  let attempted_at = record_login_attempt(pool, auth_settings, ip, Some(totp.user_id)).await?;
+  verify_code(pool, totp, code).await?;
+  forgive_login_attempt(pool, auth_settings, ip, Some(totp.user_id), attempted_at).await
+}
+
+/// Accepts code from authenticator, which also confirms pending enrollment,
+/// or unused recovery code of enabled TOTP.
+async fn verify_code(pool: &PgPool, totp: &Totp, code: &str) -> Result<(), APIError> {
+  let now = Utc::now();
+  if code.len() == TOTP_CODE_LEN {
+    let step = code_step(&totp_from(totp), code, now.timestamp() as u64)
+      .ok_or(UserError::InvalidTotpCode)?;
+    // SECURITY: Codes of already used steps are rejected, so observed code can't be replayed.
+    sqlx::query_file_scalar!("queries/totp_use_step.sql", totp.user_id, step, now)
+      .fetch_optional(pool)
+      .await?
+      .ok_or(UserError::InvalidTotpCode)?;
+    return Ok(());
+  }
+  if totp.confirmed_at.is_none() {
+    return Err(UserError::InvalidTotpCode.into());
+  }
+  sqlx::query_file_scalar!(
+    "queries/totp_recovery_code_use.sql",
+    totp.user_id,
+    recovery_code_hash(&code.to_lowercase()),
+    now
+  )
+  .fetch_optional(pool)
+  .await?
+  .map(drop)
+  .ok_or(UserError::InvalidTotpCode.into())
+}
+
+/// Time step `code` was generated for, one step of clock skew is allowed each way.
+fn code_step(totp: &TOTP, code: &str, now: u64) -> Option<i64> {
+  let current_step = now / TOTP_STEP_SECONDS;
+  (current_step.saturating_sub(1)..=current_step + 1)
+    .find(|step| totp.check(code, step * TOTP_STEP_SECONDS))
+    .map(|step| step as i64)
+}
+
+/// Account name doesn't matter when only checking codes.
+fn totp_from(totp: &Totp) -> TOTP {
+  new_totp(&totp.secret, totp.user_id.to_string())
+}
+
+fn new_totp(secret: &str, account_name: String) -> TOTP {
+  // Unchecked because usernames may contain ':', secrets are always generated by us.
+  TOTP::new_unchecked(
+    Algorithm::SHA1,
+    TOTP_CODE_LEN,
+    0,
+    TOTP_STEP_SECONDS,
+    Secret::Encoded(secret.to_string())
+      .to_bytes()
+      .expect("Stored TOTP secret is valid base32"),
+    Some(String::from(TOTP_ISSUER)),
+    account_name,
+  )
+}
+
+fn recovery_code_hash(recovery_code: &str) -> Vec<u8> {
+  Sha256::digest(recovery_code.as_bytes()).to_vec()
+}
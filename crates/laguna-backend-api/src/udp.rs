@@ -0,0 +1,230 @@
+//! UDP tracker server (BEP 15).
+//! Announces are handled by the same core as HTTP announces, see [`crate::peer::announce`].
+use std::net::{IpAddr, SocketAddr};
+use std::rc::Rc;
+
+use actix_web::dev::{Path, ResourceDef};
+use actix_web::rt::net::UdpSocket;
+use actix_web::web;
+
+use laguna_backend_model::download::DownloadHash;
+use laguna_backend_model::user::Passkey;
+use laguna_backend_tracker::http::announce::Announce;
+use laguna_backend_tracker::prelude::info_hash::SHA1_LENGTH;
+use laguna_backend_tracker::prelude::peer::PeerBin;
+use laguna_backend_tracker::udp::connection::ConnectionIdIssuer;
+use laguna_backend_tracker::udp::request::{
+  AnnounceRequest, ScrapeRequest, UdpRequest, UdpRequestError,
+};
+use laguna_backend_tracker::udp::response::{ScrapeStats, UdpResponse};
+use laguna_backend_tracker::udp::MAX_PACKET_SIZE;
+
+use laguna_backend_config::TrackerSettings;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::error::peer::PeerError;
+use crate::peer::{announce, announce_interval, scrape};
+
+/// Query part of legacy BEP 41 URLData, eg. `/peer/announce?down_hash=...`.
+#[derive(Debug, Deserialize)]
+struct UrlData {
+  down_hash: DownloadHash,
+}
+
+/// Extracts passkey from path part of BEP 41 URLData, eg. `/peer/{passkey}/announce`.
+/// Matched the same way as HTTP announce route.
+fn url_data_passkey(path: &str) -> Option<Passkey> {
+  let mut path = Path::new(path);
+  ResourceDef::new("/peer/{passkey}/announce")
+    .capture_match_info(&mut path)
+    .then(|| path.load().ok())
+    .flatten()
+}
+
+/// Serves UDP tracker requests on `socket` forever.
+/// Each datagram is handled in its own task, so slow DB queries don't block the socket.
+/// Must be spawned on actix (local) runtime.
+pub async fn udp_tracker(socket: UdpSocket, pool: PgPool, tracker_settings: TrackerSettings) {
+  let socket = Rc::new(socket);
+  let issuer = Rc::new(ConnectionIdIssuer::new());
+  let tracker_settings = Rc::new(tracker_settings);
+  let mut buf = [0; MAX_PACKET_SIZE];
+  loop {
+    let (len, addr) = match socket.recv_from(&mut buf).await {
+      Ok(received) => received,
+      Err(err) => {
+        log::warn!("Cannot receive UDP tracker datagram: {}", err);
+        continue;
+      },
+    };
+    let packet = buf[..len].to_vec();
+    actix_web::rt::spawn(handle_packet(
+      Rc::clone(&socket),
+      Rc::clone(&issuer),
+      Rc::clone(&tracker_settings),
+      pool.clone(),
+      packet,
+      addr,
+    ));
+  }
+}
+
+async fn handle_packet(
+  socket: Rc<UdpSocket>,
+  issuer: Rc<ConnectionIdIssuer>,
+  tracker_settings: Rc<TrackerSettings>,
+  pool: PgPool,
+  packet: Vec<u8>,
+  addr: SocketAddr,
+) {
+  let response = match UdpRequest::from_bytes(&packet) {
+    Ok(UdpRequest::Connect(request)) => UdpResponse::Connect {
+      transaction_id: request.transaction_id,
+      connection_id: issuer.issue(addr),
+    },
+    Ok(UdpRequest::Announce(request)) if !issuer.validate(request.connection_id, addr) => {
+      request_error(UdpRequestError::InvalidConnectionId {
+        transaction_id: request.transaction_id,
+      })
+    },
+    Ok(UdpRequest::Announce(request)) => {
+      handle_announce(&pool, &tracker_settings, request, addr).await
+    },
+    Ok(UdpRequest::Scrape(request)) if !issuer.validate(request.connection_id, addr) => {
+      request_error(UdpRequestError::InvalidConnectionId {
+        transaction_id: request.transaction_id,
+      })
+    },
+    Ok(UdpRequest::Scrape(request)) => handle_scrape(&pool, &tracker_settings, request).await,
+    Err(UdpRequestError::Malformed) => {
+      log::debug!("Ignoring malformed UDP tracker datagram from {}.", addr);
+      return;
+    },
+    Err(err) => request_error(err),
+  };
+  if let Err(err) = socket.send_to(&response.to_bytes(), addr).await {
+    log::warn!("Cannot send UDP tracker response to {}: {}", addr, err);
+  }
+}
+
+fn request_error(err: UdpRequestError) -> UdpResponse {
+  UdpResponse::Error {
+    transaction_id: err
+      .transaction_id()
+      .expect("Malformed requests have no transaction id"),
+    message: err.to_string(),
+  }
+}
+
+async fn handle_announce(
+  pool: &PgPool,
+  tracker_settings: &TrackerSettings,
+  request: AnnounceRequest,
+  addr: SocketAddr,
+) -> UdpResponse {
+  let transaction_id = request.transaction_id;
+  match udp_announce(pool, tracker_settings, request, addr).await {
+    Ok(response) => response,
+    Err(err) => UdpResponse::Error {
+      transaction_id,
+      message: err.to_string(),
+    },
+  }
+}
+
+async fn udp_announce(
+  pool: &PgPool,
+  tracker_settings: &TrackerSettings,
+  request: AnnounceRequest,
+  addr: SocketAddr,
+) -> Result<UdpResponse, PeerError<SHA1_LENGTH>> {
+  // Passkey is passed the same way as in HTTP announce URL, but in BEP 41 URLData.
+  let url_data = request.url_data.as_deref().unwrap_or_default();
+  let (path, query) = url_data.split_once('?').unwrap_or((url_data, ""));
+  let passkey = url_data_passkey(path);
+  let down_hash = web::Query::<UrlData>::from_query(query)
+    .ok()
+    .map(|url_data| url_data.into_inner().down_hash);
+
+  let ip = request.ip.map(IpAddr::V4);
+  let announce_data = Announce::<SHA1_LENGTH> {
+    down_hash,
+    info_hash: request.info_hash,
+    peer_id: request.peer_id,
+    ip,
+    port: request.port,
+    uploaded: request.uploaded,
+    downloaded: request.downloaded,
+    left: request.left,
+    event: Some(request.event),
+    numwant: request.num_want.map(i64::from),
+    compact: Some(true),
+    no_peer_id: Some(true),
+    key: Some(request.key.to_string()),
+    trackerid: None,
+    supportcrypto: None,
+    redundant: None,
+  };
+
+  let outcome = announce(
+    pool,
+    tracker_settings,
+    passkey.as_ref(),
+    announce_data,
+    ip.unwrap_or(addr.ip()),
+    None,
+  )
+  .await?;
+
+  Ok(UdpResponse::Announce {
+    transaction_id: request.transaction_id,
+    interval: i32::try_from(announce_interval(tracker_settings)).unwrap_or(i32::MAX),
+    leechers: i32::try_from(outcome.incomplete).unwrap_or(i32::MAX),
+    seeders: i32::try_from(outcome.complete).unwrap_or(i32::MAX),
+    // UDP announce reply can only carry IPv4 peers.
+    peers: outcome
+      .peers
+      .into_iter()
+      .filter_map(|peer| match peer.ip.ip().to_canonical() {
+        IpAddr::V4(ip) => Some(PeerBin::from_socket(ip, peer.port as u16)),
+        IpAddr::V6(_) => None,
+      })
+      .collect(),
+  })
+}
+
+async fn handle_scrape(
+  pool: &PgPool,
+  tracker_settings: &TrackerSettings,
+  request: ScrapeRequest,
+) -> UdpResponse {
+  let scrapes = match scrape(pool, tracker_settings, &request.info_hashes).await {
+    Ok(scrapes) => scrapes,
+    Err(err) => {
+      return UdpResponse::Error {
+        transaction_id: request.transaction_id,
+        message: err.to_string(),
+      }
+    },
+  };
+  UdpResponse::Scrape {
+    transaction_id: request.transaction_id,
+    // Torrents unknown to tracker are reported with zeros, in order of request.
+    torrents: request
+      .info_hashes
+      .iter()
+      .map(|info_hash| {
+        scrapes
+          .iter()
+          .find(|scrape| scrape.info_hash == *info_hash)
+          .map(|scrape| ScrapeStats {
+            seeders: i32::try_from(scrape.complete).unwrap_or(i32::MAX),
+            completed: i32::try_from(scrape.downloaded).unwrap_or(i32::MAX),
+            leechers: i32::try_from(scrape.incomplete).unwrap_or(i32::MAX),
+          })
+          .unwrap_or_default()
+      })
+      .collect(),
+  }
+}
@@ -1,10 +1,12 @@
 use actix_jwt_auth_middleware::TokenSigner;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 
-use jwt_compact::alg::Hs256;
+use jwt_compact::alg::{Hs256, Hs256Key};
+use laguna_backend_config::AuthSettings;
 use laguna_backend_dto::peer::PeerDTO;
 use laguna_backend_dto::role::RoleChangeDTO;
 use laguna_backend_dto::torrent::TorrentDTO;
+use laguna_backend_dto::user::PasskeyDTO;
 use laguna_backend_dto::user::UserDTO;
 use laguna_backend_dto::user::UserPatchDTO;
 use laguna_backend_middleware::consts::{ACCESS_TOKEN_HEADER_NAME, REFRESH_TOKEN_HEADER_NAME};
@@ -15,13 +17,16 @@ use laguna_backend_model::peer::Peer;
 use laguna_backend_model::role::Role;
 use laguna_backend_model::speedlevel::SpeedLevel;
 use laguna_backend_model::torrent::Torrent;
+use laguna_backend_model::user::Passkey;
 use laguna_backend_model::user::User;
 use laguna_backend_model::user::UserSafe;
+use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH, SHA256_LENGTH};
 
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::error::{user::UserError, APIError};
+use crate::session::{renew_session, revoke_sessions};
 
 #[utoipa::path(
     get,
@@ -86,6 +91,31 @@ pub async fn user_me_delete(
   Ok(HttpResponse::Ok().finish())
 }
 
+#[utoipa::path(
+  post,
+  path = "/api/user/me/passkey",
+  responses(
+    (status = 200, description = "Returns new passkey, previously downloaded torrents stop working.", body = PasskeyDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "User not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn user_me_passkey_reset(
+  user: UserDTO,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let passkey = sqlx::query_file_scalar!("queries/user_passkey_reset.sql", user.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(UserError::NotFound)?;
+  let passkey = Passkey::try_from(passkey).map_err(|err| sqlx::Error::Decode(err.into()))?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(PasskeyDTO { passkey }),
+  )
+}
+
 #[utoipa::path(
   patch,
   path = "/api/user/me",
@@ -100,10 +130,13 @@ pub async fn user_me_delete(
   request_body = UserPatchDTO
 )]
 pub async fn user_patch_me(
+  req: HttpRequest,
   user_patch_dto: web::Json<UserPatchDTO>,
   user: UserDTO,
   pool: web::Data<PgPool>,
   signer: web::Data<TokenSigner<UserDTO, Hs256>>,
+  secret_key: web::Data<Hs256Key>,
+  auth_settings: web::Data<AuthSettings>,
 ) -> Result<HttpResponse, APIError> {
   let user = sqlx::query_file_as!(
     User,
@@ -118,16 +151,19 @@ pub async fn user_patch_me(
   .map(UserSafe::from)
   .map(UserDTO::from)
   .ok_or(UserError::NotUpdated)?;
+  let (access_token, refresh_token) = renew_session(
+    pool.get_ref(),
+    signer.get_ref(),
+    secret_key.get_ref(),
+    auth_settings.get_ref(),
+    &req,
+    &user,
+  )
+  .await?;
   Ok(
     HttpResponse::Ok()
-      .append_header((
-        ACCESS_TOKEN_HEADER_NAME,
-        signer.create_access_header_value(&user)?,
-      ))
-      .append_header((
-        REFRESH_TOKEN_HEADER_NAME,
-        signer.create_refresh_header_value(&user)?,
-      ))
+      .append_header((ACCESS_TOKEN_HEADER_NAME, access_token))
+      .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token))
       .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
       .json(user),
   )
@@ -209,11 +245,95 @@ pub async fn user_role_change(
   }
 }
 
+#[utoipa::path(
+  post,
+  path = "/api/user/{id}/ban",
+  responses(
+    (status = 200, description = "User disabled and all its sessions revoked.", body = UserDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "User not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 403, description = "Not allowed to ban user.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+  params(
+    ("id", Path, description = "User's id.", format = Uuid)
+  )
+)]
+pub async fn user_ban(
+  user_id: web::Path<Uuid>,
+  current_user: UserDTO,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let user = user_enabled_update(user_id.into_inner(), false, &current_user, &pool).await?;
+  revoke_sessions(pool.get_ref(), user.id).await?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(user),
+  )
+}
+
+#[utoipa::path(
+  delete,
+  path = "/api/user/{id}/ban",
+  responses(
+    (status = 200, description = "User enabled, it has to log in again.", body = UserDTO, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 400, description = "User not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 403, description = "Not allowed to unban user.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+  params(
+    ("id", Path, description = "User's id.", format = Uuid)
+  )
+)]
+pub async fn user_unban(
+  user_id: web::Path<Uuid>,
+  current_user: UserDTO,
+  pool: web::Data<PgPool>,
+) -> Result<HttpResponse, APIError> {
+  let user = user_enabled_update(user_id.into_inner(), true, &current_user, &pool).await?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
+      .json(user),
+  )
+}
+
+/// Admin can (un)ban anyone but other admins, mod only verified users and normies.
+async fn user_enabled_update(
+  user_id: Uuid,
+  is_enabled: bool,
+  current_user: &UserDTO,
+  pool: &PgPool,
+) -> Result<UserDTO, APIError> {
+  let bannee = sqlx::query_file_as!(User, "queries/user_get.sql", user_id)
+    .fetch_optional(pool)
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::NotFound)?;
+  match (current_user.role, bannee.role) {
+    (Role::Admin, Role::Mod | Role::Verified | Role::Normie)
+    | (Role::Mod, Role::Verified | Role::Normie) => Ok(
+      sqlx::query_file_as!(
+        User,
+        "queries/user_enabled_update.sql",
+        is_enabled,
+        bannee.id
+      )
+      .fetch_optional(pool)
+      .await?
+      .map(UserSafe::from)
+      .map(UserDTO::from)
+      .ok_or(UserError::NotUpdated)?,
+    ),
+    (banner, bannee) => Err(UserError::BanNotAllowed { banner, bannee }.into()),
+  }
+}
+
 #[utoipa::path(
   get,
   path = "/api/user/{id}/peers",
   responses(
-    (status = 200, description = "Returns user's peers.", body = Vec<Peer>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 200, description = "Returns user's peers.", body = Vec<Peer<SHA1_LENGTH>>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 400, description = "User not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
   ),
@@ -225,11 +345,15 @@ pub async fn user_peers_get(
   id: web::Path<Uuid>,
   pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, APIError> {
-  let peers = sqlx::query_file_as!(Peer, "queries/user_peers.sql", id.into_inner())
-    .fetch_all(pool.get_ref())
-    .await?
-    .into_iter()
-    .collect::<Vec<PeerDTO>>();
+  let peers = sqlx::query_file_as!(
+    Peer::<SHA1_LENGTH>,
+    "queries/user_peers.sql",
+    id.into_inner()
+  )
+  .fetch_all(pool.get_ref())
+  .await?
+  .into_iter()
+  .collect::<Vec<PeerDTO<SHA1_LENGTH>>>();
   Ok(
     HttpResponse::Ok()
       .content_type(APPLICATION_LAGUNA_JSON_VERSIONED)
@@ -241,7 +365,7 @@ pub async fn user_peers_get(
   get,
   path = "/api/user/{id}/torrents",
   responses(
-    (status = 200, description = "Returns user's torrents.", body = Vec<Torrent>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 200, description = "Returns user's torrents.", body = Vec<TorrentDTO<SHA1_LENGTH>>, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 400, description = "User not found.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
     (status = 401, description = "Not logged in, hence unauthorized.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
   ),
@@ -253,12 +377,16 @@ pub async fn user_torrents_get(
   id: web::Path<Uuid>,
   pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, APIError> {
-  let torrents = sqlx::query_file_as!(Torrent, "queries/user_torrents.sql", id.into_inner())
-    .fetch_all(pool.get_ref())
-    .await?
-    .into_iter()
-    .map(TorrentDTO::from)
-    .collect::<Vec<TorrentDTO>>();
+  let torrents = sqlx::query_file_as!(
+    Torrent::<SHA1_LENGTH>,
+    "queries/user_torrents.sql",
+    id.into_inner()
+  )
+  .fetch_all(pool.get_ref())
+  .await?
+  .into_iter()
+  .map(TorrentDTO::try_from)
+  .collect::<Result<Vec<_>, _>>()?;
 
   Ok(
     HttpResponse::Ok()
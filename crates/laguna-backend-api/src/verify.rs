@@ -0,0 +1,137 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_validator::Json;
+use chrono::{Duration, Utc};
+use laguna_backend_config::{AuthSettings, FrontendSettings};
+use laguna_backend_dto::verify::{VerifyDTO, VerifyResendDTO};
+use laguna_backend_model::behaviour::Behaviour;
+use laguna_backend_model::role::Role;
+use laguna_backend_model::user::{Passkey, User, UserSafe};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::error::{user::UserError, APIError};
+use crate::helpers::request::request_ip;
+use crate::helpers::token::{sign_token, token_user_id, verify_token, TokenPurpose};
+use crate::mailer::{send_detached, Mail, Mailer};
+use crate::throttle::record_login_attempt;
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/verify",
+  request_body = VerifyDTO,
+  responses(
+    (status = 200, description = "Email verified successfully."),
+    (status = 400, description = "Invalid or expired token.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn verify_email(
+  verify_dto: Json<VerifyDTO>,
+  pool: web::Data<PgPool>,
+  auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, APIError> {
+  let user_id = token_user_id(&verify_dto.token)?;
+  let user = sqlx::query_file_as!(User, "queries/user_get.sql", user_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(UserSafe::from)
+    .ok_or(UserError::InvalidToken)?;
+  let email = user.email.expose_secret();
+  verify_token(
+    auth_settings.secret_key.expose_secret(),
+    TokenPurpose::EmailVerification,
+    &verify_dto.token,
+    email,
+  )?;
+
+  sqlx::query_file_as!(User, "queries/user_verify_email.sql", user_id, email)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .map(drop)
+    .ok_or(UserError::InvalidToken)?;
+
+  Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/user/auth/verify/resend",
+  request_body = VerifyResendDTO,
+  responses(
+    (status = 200, description = "Verification mail sent, if user with this email exists and is not verified yet."),
+    (status = 400, description = "Bad request.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+    (status = 429, description = "Too many requests, retry after time in Retry-After header.", body = String, content_type = "application/vnd.sloveniaengineering.laguna.0.1.0+json"),
+  ),
+)]
+pub async fn verify_email_resend(
+  req: HttpRequest,
+  verify_resend_dto: Json<VerifyResendDTO>,
+  pool: web::Data<PgPool>,
+  auth_settings: web::Data<AuthSettings>,
+  frontend_settings: web::Data<FrontendSettings>,
+  mailer: web::Data<dyn Mailer>,
+) -> Result<HttpResponse, APIError> {
+  let user = sqlx::query_file_as!(
+    User,
+    "queries/user_lookup.sql",
+    verify_resend_dto.email,
+    verify_resend_dto.email
+  )
+  .fetch_optional(pool.get_ref())
+  .await?
+  .map(UserSafe::from);
+
+  // Counted like failed login and never forgiven, so nobody can flood an address with mail.
+  record_login_attempt(
+    pool.get_ref(),
+    auth_settings.get_ref(),
+    request_ip(&req, &auth_settings.trusted_proxies),
+    user.as_ref().map(|user| user.id),
+  )
+  .await?;
+  // SECURITY: Same response whether user exists or not, so emails can't be enumerated.
+  if let Some(user) = user.filter(|user| !user.has_verified_email) {
+    send_detached(
+      mailer.into_inner(),
+      verification_mail(&user, &auth_settings, &frontend_settings),
+    );
+  }
+
+  Ok(HttpResponse::Ok().finish())
+}
+
+pub(crate) async fn send_verification_mail(
+  user: &UserSafe,
+  auth_settings: &AuthSettings,
+  frontend_settings: &FrontendSettings,
+  mailer: &dyn Mailer,
+) -> Result<(), APIError> {
+  mailer
+    .send(verification_mail(user, auth_settings, frontend_settings))
+    .await?;
+  Ok(())
+}
+
+fn verification_mail(
+  user: &UserSafe,
+  auth_settings: &AuthSettings,
+  frontend_settings: &FrontendSettings,
+) -> Mail {
+  let email = user.email.expose_secret();
+  let token = sign_token(
+    auth_settings.secret_key.expose_secret(),
+    TokenPurpose::EmailVerification,
+    user.id,
+    email,
+    Utc::now() + Duration::seconds(auth_settings.email_verification_token_lifetime_seconds),
+  );
+  Mail {
+    to: email.clone(),
+    subject: String::from("Potrditev elektronskega naslova"),
+    body: format!(
+      "Pozdravljeni {},\n\nelektronski naslov potrdite na {}/verify?token={}",
+      user.username,
+      frontend_settings.url(),
+      token
+    ),
+  }
+}
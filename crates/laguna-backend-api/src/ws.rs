@@ -0,0 +1,389 @@
+//! WebTorrent tracker server (WebSocket).
+//! Browser peers can't accept incoming connections, so instead of peer addresses
+//! tracker relays WebRTC offers and answers between peers connected to it.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use actix_web::dev::PeerAddr;
+use actix_web::http::header::USER_AGENT;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+
+use laguna_backend_model::download::DownloadHash;
+use laguna_backend_model::user::Passkey;
+use laguna_backend_tracker::http::announce::Announce;
+use laguna_backend_tracker::prelude::announce::AnnounceEvent;
+use laguna_backend_tracker::prelude::info_hash::{InfoHash, SHA1_LENGTH};
+use laguna_backend_tracker::prelude::peer::PeerId;
+use laguna_backend_tracker::ws::binary::to_binary_string;
+use laguna_backend_tracker::ws::message::{
+  WsAction, WsAnnounceRequest, WsAnnounceResponse, WsAnswerMessage, WsErrorResponse,
+  WsOfferMessage, WsRequest, WsResponse, WsScrapeFile, WsScrapeRequest, WsScrapeResponse,
+  MAX_OFFERS,
+};
+
+use laguna_backend_config::TrackerSettings;
+use rand::seq::IteratorRandom;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+use crate::error::peer::PeerError;
+use crate::peer::{announce, announce_interval, announcing_user, peer_ip, scrape};
+
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+  /// Legacy per-download hash, passkey is in path instead.
+  pub down_hash: Option<DownloadHash>,
+}
+
+/// Sessions of peers in swarm, each with id of connection that joined with it.
+type WsSwarm = HashMap<PeerId, (Uuid, Session)>;
+
+/// Live sessions of WebTorrent peers per torrent.
+/// Has to be shared by all workers, otherwise peers on different workers can't signal each other.
+#[derive(Default)]
+pub struct WsSwarms {
+  swarms: Mutex<HashMap<InfoHash<SHA1_LENGTH>, WsSwarm>>,
+}
+
+impl WsSwarms {
+  /// Joins swarm with session of connection, unless peer id is taken by another connection,
+  /// otherwise anyone could take over signaling of live peers.
+  fn join(
+    &self,
+    info_hash: InfoHash<SHA1_LENGTH>,
+    peer_id: PeerId,
+    connection_id: Uuid,
+    session: Session,
+  ) -> bool {
+    let mut swarms = self.swarms.lock().unwrap();
+    let (owner_id, _) = swarms
+      .entry(info_hash)
+      .or_default()
+      .entry(peer_id)
+      .or_insert((connection_id, session));
+    *owner_id == connection_id
+  }
+
+  /// Leaves swarm, only if peer id is owned by connection.
+  fn leave(&self, info_hash: &InfoHash<SHA1_LENGTH>, peer_id: &PeerId, connection_id: Uuid) {
+    let mut swarms = self.swarms.lock().unwrap();
+    if let Some(swarm) = swarms.get_mut(info_hash) {
+      if matches!(swarm.get(peer_id), Some((owner_id, _)) if *owner_id == connection_id) {
+        swarm.remove(peer_id);
+      }
+      if swarm.is_empty() {
+        swarms.remove(info_hash);
+      }
+    }
+  }
+
+  fn session(&self, info_hash: &InfoHash<SHA1_LENGTH>, peer_id: &PeerId) -> Option<Session> {
+    self
+      .swarms
+      .lock()
+      .unwrap()
+      .get(info_hash)
+      .and_then(|swarm| swarm.get(peer_id))
+      .map(|(_, session)| session.clone())
+  }
+
+  /// Picks at most `amount` random peers in swarm, other than `peer_id`.
+  fn others(
+    &self,
+    info_hash: &InfoHash<SHA1_LENGTH>,
+    peer_id: &PeerId,
+    amount: usize,
+  ) -> Vec<Session> {
+    self
+      .swarms
+      .lock()
+      .unwrap()
+      .get(info_hash)
+      .map(|swarm| {
+        swarm
+          .iter()
+          .filter(|(other_peer_id, _)| *other_peer_id != peer_id)
+          .map(|(_, (_, session))| session.clone())
+          .choose_multiple(&mut rand::thread_rng(), amount)
+      })
+      .unwrap_or_default()
+  }
+}
+
+/// State of a single WebSocket connection.
+struct WsConnection {
+  pool: PgPool,
+  tracker_settings: web::Data<TrackerSettings>,
+  swarms: web::Data<WsSwarms>,
+  /// Identifies sessions of this connection in [`WsSwarms`].
+  id: Uuid,
+  session: Session,
+  passkey: Option<Passkey>,
+  down_hash: Option<DownloadHash>,
+  ip: IpAddr,
+  user_agent: Option<String>,
+  /// Torrents this connection announced to and its peer id in each of them.
+  joined: HashMap<InfoHash<SHA1_LENGTH>, PeerId>,
+}
+
+#[utoipa::path(
+  get,
+  path = "/peer/{passkey}/ws",
+  params(("passkey" = String, Path, description = "Passkey from announce URL")),
+  responses((status = 101, description = "Switches to WebTorrent tracker protocol"))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn peer_ws(
+  peer_addr: PeerAddr,
+  req: HttpRequest,
+  body: web::Payload,
+  passkey: Option<web::Path<Passkey>>,
+  query: web::Query<WsQuery>,
+  pool: web::Data<PgPool>,
+  tracker_settings: web::Data<TrackerSettings>,
+  swarms: web::Data<WsSwarms>,
+) -> Result<HttpResponse, actix_web::Error> {
+  let passkey = passkey.map(web::Path::into_inner);
+  let down_hash = query.into_inner().down_hash;
+  // Reject unknown users before upgrading the connection.
+  announcing_user::<SHA1_LENGTH>(
+    pool.get_ref(),
+    tracker_settings.get_ref(),
+    passkey.as_ref(),
+    down_hash.as_ref(),
+  )
+  .await?;
+
+  let (response, session, stream) = actix_ws::handle(&req, body)?;
+
+  let connection = WsConnection {
+    pool: pool.get_ref().clone(),
+    tracker_settings,
+    swarms,
+    id: Uuid::new_v4(),
+    session,
+    passkey,
+    down_hash,
+    ip: peer_ip(&req, peer_addr.0, None),
+    user_agent: req
+      .headers()
+      .get(USER_AGENT)
+      .and_then(|hv| hv.to_str().ok())
+      .map(String::from),
+    joined: HashMap::new(),
+  };
+  actix_web::rt::spawn(connection.run(stream));
+
+  Ok(response)
+}
+
+impl WsConnection {
+  async fn run(mut self, mut stream: MessageStream) {
+    let mut close_reason = None;
+    while let Some(Ok(message)) = stream.recv().await {
+      let sent = match message {
+        Message::Text(text) => match self.handle_text(&text).await {
+          Some(response) => self.send(&response).await,
+          None => true,
+        },
+        Message::Ping(bytes) => self.session.pong(&bytes).await.is_ok(),
+        Message::Close(reason) => {
+          close_reason = reason;
+          break;
+        },
+        _ => true,
+      };
+      if !sent {
+        break;
+      }
+    }
+    for (info_hash, peer_id) in self.joined.drain() {
+      self.swarms.leave(&info_hash, &peer_id, self.id);
+    }
+    let _ = self.session.close(close_reason).await;
+  }
+
+  async fn send(&mut self, response: &WsResponse) -> bool {
+    let text = serde_json::to_string(response).expect("Cannot serialize WebTorrent message");
+    self.session.text(text).await.is_ok()
+  }
+
+  async fn handle_text(&mut self, text: &str) -> Option<WsResponse> {
+    match serde_json::from_str::<WsRequest>(text) {
+      Ok(WsRequest::Announce(request)) => {
+        let info_hash = request.info_hash.clone();
+        match self.handle_announce(request).await {
+          Ok(response) => response,
+          Err(err) => Some(WsResponse::Error(WsErrorResponse {
+            failure_reason: err.to_string(),
+            action: Some(WsAction::Announce),
+            info_hash: Some(info_hash),
+          })),
+        }
+      },
+      Ok(WsRequest::Scrape(request)) => Some(match self.handle_scrape(request).await {
+        Ok(response) => response,
+        Err(err) => WsResponse::Error(WsErrorResponse {
+          failure_reason: err.to_string(),
+          action: Some(WsAction::Scrape),
+          info_hash: None,
+        }),
+      }),
+      Err(err) => Some(WsResponse::Error(WsErrorResponse {
+        failure_reason: format!("Neveljavno sporočilo: {}.", err),
+        action: None,
+        info_hash: None,
+      })),
+    }
+  }
+
+  async fn handle_announce(
+    &mut self,
+    request: WsAnnounceRequest,
+  ) -> Result<Option<WsResponse>, PeerError<SHA1_LENGTH>> {
+    if let Some(answer) = request.answer {
+      // Answer is only relayed back to the offering peer, there is nothing to reply.
+      // Peers can only answer as peer id they announced with on this connection,
+      // otherwise anyone could inject answers into swarms they are not part of.
+      if self.joined.get(&request.info_hash) != Some(&request.peer_id) {
+        return Ok(None);
+      }
+      let to_peer_id = request
+        .to_peer_id
+        .ok_or(PeerError::MissingField("to_peer_id"))?;
+      let offer_id = request
+        .offer_id
+        .ok_or(PeerError::MissingField("offer_id"))?;
+      if let Some(mut session) = self.swarms.session(&request.info_hash, &to_peer_id) {
+        let text = serde_json::to_string(&WsResponse::Answer(WsAnswerMessage {
+          action: WsAction::Announce,
+          info_hash: request.info_hash,
+          peer_id: request.peer_id,
+          offer_id,
+          answer,
+        }))
+        .expect("Cannot serialize WebTorrent message");
+        let _ = session.text(text).await;
+      }
+      return Ok(None);
+    }
+
+    let announce_data = Announce::<SHA1_LENGTH> {
+      down_hash: self.down_hash.clone(),
+      info_hash: request.info_hash.clone(),
+      peer_id: request.peer_id,
+      ip: None,
+      // WebRTC peers don't listen on a port.
+      port: 0,
+      uploaded: request.uploaded.unwrap_or_default(),
+      downloaded: request.downloaded.unwrap_or_default(),
+      left: request.left.ok_or(PeerError::MissingField("left"))?,
+      event: request.event,
+      numwant: request.numwant.map(|numwant| numwant as i64),
+      compact: None,
+      no_peer_id: None,
+      key: None,
+      trackerid: None,
+      supportcrypto: None,
+      redundant: None,
+    };
+    let outcome = announce(
+      &self.pool,
+      &self.tracker_settings,
+      self.passkey.as_ref(),
+      announce_data,
+      self.ip,
+      self.user_agent.clone(),
+    )
+    .await?;
+
+    if request.event == Some(AnnounceEvent::Stopped) {
+      self
+        .swarms
+        .leave(&request.info_hash, &request.peer_id, self.id);
+      if self.joined.get(&request.info_hash) == Some(&request.peer_id) {
+        self.joined.remove(&request.info_hash);
+      }
+    } else {
+      if !self.swarms.join(
+        request.info_hash.clone(),
+        request.peer_id,
+        self.id,
+        self.session.clone(),
+      ) {
+        return Err(PeerError::PeerIdTaken(request.peer_id));
+      }
+      // Peer id changed, previous one is not used by this connection anymore.
+      if let Some(previous_peer_id) = self
+        .joined
+        .insert(request.info_hash.clone(), request.peer_id)
+        .filter(|previous_peer_id| *previous_peer_id != request.peer_id)
+      {
+        self
+          .swarms
+          .leave(&request.info_hash, &previous_peer_id, self.id);
+      }
+    }
+
+    if let Some(offers) = request.offers {
+      let amount = request
+        .numwant
+        .unwrap_or(offers.len())
+        .min(offers.len())
+        .min(MAX_OFFERS);
+      let others = self
+        .swarms
+        .others(&request.info_hash, &request.peer_id, amount);
+      for (mut session, offer) in others.into_iter().zip(offers) {
+        let text = serde_json::to_string(&WsResponse::Offer(WsOfferMessage {
+          action: WsAction::Announce,
+          info_hash: request.info_hash.clone(),
+          peer_id: request.peer_id,
+          offer_id: offer.offer_id,
+          offer: offer.offer,
+        }))
+        .expect("Cannot serialize WebTorrent message");
+        let _ = session.text(text).await;
+      }
+    }
+
+    Ok(Some(WsResponse::Announce(WsAnnounceResponse {
+      action: WsAction::Announce,
+      info_hash: request.info_hash,
+      interval: announce_interval(&self.tracker_settings),
+      complete: outcome.complete,
+      incomplete: outcome.incomplete,
+    })))
+  }
+
+  async fn handle_scrape(
+    &self,
+    request: WsScrapeRequest,
+  ) -> Result<WsResponse, PeerError<SHA1_LENGTH>> {
+    let info_hashes = request.info_hash.into_vec();
+    let scrapes = scrape(&self.pool, &self.tracker_settings, &info_hashes).await?;
+    Ok(WsResponse::Scrape(WsScrapeResponse {
+      action: WsAction::Scrape,
+      files: info_hashes
+        .iter()
+        .map(|info_hash| {
+          (
+            to_binary_string(&info_hash.0),
+            scrapes
+              .iter()
+              .find(|scrape| scrape.info_hash == *info_hash)
+              .map(|scrape| WsScrapeFile {
+                complete: scrape.complete,
+                incomplete: scrape.incomplete,
+                downloaded: scrape.downloaded,
+              })
+              .unwrap_or_default(),
+          )
+        })
+        .collect(),
+    }))
+  }
+}
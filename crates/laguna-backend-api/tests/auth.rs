@@ -4,22 +4,38 @@ mod common;
 
 use std::thread;
 
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
 use actix_web::http::StatusCode;
+use actix_web::test::{call_service, read_body_json, TestRequest};
 
 use fake::Fake;
 use fake::Faker;
+use futures::future::join_all;
 
 use laguna_backend_config::Settings;
+use laguna_backend_middleware::consts::{ACCESS_TOKEN_HEADER_NAME, REFRESH_TOKEN_HEADER_NAME};
 use laguna_backend_model::user::User;
 use laguna_backend_setup::get_settings;
 use sqlx::PgPool;
 use std::time::Duration as StdDuration;
+use uuid::Uuid;
 
-use laguna_backend_dto::{login::LoginDTO, register::RegisterDTO};
+use laguna_backend_dto::{
+  login::LoginDTO,
+  password::{PasswordChangeDTO, PasswordForgotDTO, PasswordResetDTO},
+  register::RegisterDTO,
+  session::SessionDTO,
+  totp::{LoginChallengeDTO, LoginTotpDTO, LoginTotpEnrollDTO, TotpCodeDTO, TotpEnrollDTO},
+  user::UserDTO,
+  verify::{VerifyDTO, VerifyResendDTO},
+};
 use laguna_backend_model::consts::EMAIL_MAX_LEN;
 use laguna_backend_model::consts::EMAIL_MIN_LEN;
 use laguna_backend_model::consts::PASSWORD_MAX_LEN;
 use laguna_backend_model::consts::PASSWORD_MIN_LEN;
+use laguna_backend_model::consts::TOTP_RECOVERY_CODE_COUNT;
 use laguna_backend_model::consts::USERNAME_MAX_LEN;
 use laguna_backend_model::consts::USERNAME_MIN_LEN;
 
@@ -59,6 +75,616 @@ async fn test_register_with_existing_email(pool: PgPool) -> sqlx::Result<()> {
   Ok(())
 }
 
+async fn verify(
+  token: String,
+  app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+) -> ServiceResponse {
+  call_service(
+    app,
+    TestRequest::post()
+      .uri("/api/user/auth/verify")
+      .set_json(VerifyDTO { token })
+      .to_request(),
+  )
+  .await
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_session_refresh_and_logout(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, _, refresh_token) = common::new_user(&app).await;
+  let refresh = |refresh_token: HeaderValue| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/refresh")
+        .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token))
+        .to_request(),
+    )
+  };
+
+  let refresh_res = refresh(refresh_token.clone()).await;
+  assert_eq!(refresh_res.status(), StatusCode::OK);
+  assert!(refresh_res.headers().contains_key(ACCESS_TOKEN_HEADER_NAME));
+  let new_refresh_token = refresh_res
+    .headers()
+    .get(REFRESH_TOKEN_HEADER_NAME)
+    .unwrap()
+    .to_owned();
+  assert_eq!(
+    read_body_json::<UserDTO, _>(refresh_res).await.id,
+    user_dto.id
+  );
+
+  let get_res = common::as_refreshing(
+    new_refresh_token.clone(),
+    TestRequest::get().uri("/api/user/me"),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+
+  let logout = || {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/logout")
+        .append_header((REFRESH_TOKEN_HEADER_NAME, new_refresh_token.clone()))
+        .to_request(),
+    )
+  };
+  assert_eq!(logout().await.status(), StatusCode::OK);
+  // Logging out twice is fine.
+  assert_eq!(logout().await.status(), StatusCode::OK);
+  let refresh_res = refresh(new_refresh_token.clone()).await;
+  assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+  let get_res = common::as_refreshing(
+    new_refresh_token,
+    TestRequest::get().uri("/api/user/me"),
+    &app,
+  )
+  .await;
+  assert!(get_res.is_err());
+
+  // Header that isn't visible ASCII is no token at all.
+  let refresh_res = refresh(HeaderValue::from_bytes(b"\xff").unwrap()).await;
+  assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_session_refresh_token_reuse(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, _, _, refresh_token) = common::new_user(&app).await;
+  let refresh = |refresh_token: HeaderValue| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/refresh")
+        .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token))
+        .to_request(),
+    )
+  };
+
+  let refresh_res = refresh(refresh_token.clone()).await;
+  assert_eq!(refresh_res.status(), StatusCode::OK);
+  let new_refresh_token = refresh_res
+    .headers()
+    .get(REFRESH_TOKEN_HEADER_NAME)
+    .unwrap()
+    .to_owned();
+  // Issued within the same second, still a different token.
+  assert_ne!(new_refresh_token, refresh_token);
+
+  // Reusing rotated token revokes whole session, including its newest token.
+  let refresh_res = refresh(refresh_token.clone()).await;
+  assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+  let refresh_res = refresh(new_refresh_token.clone()).await;
+  assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+
+  // Same through authority, when access token is expired.
+  let (_, _, _, refresh_token) = common::new_user(&app).await;
+  let new_refresh_token = refresh(refresh_token.clone())
+    .await
+    .headers()
+    .get(REFRESH_TOKEN_HEADER_NAME)
+    .unwrap()
+    .to_owned();
+  let get_res =
+    common::as_refreshing(refresh_token, TestRequest::get().uri("/api/user/me"), &app).await;
+  assert!(get_res.is_err());
+  let get_res = common::as_refreshing(
+    new_refresh_token,
+    TestRequest::get().uri("/api/user/me"),
+    &app,
+  )
+  .await;
+  assert!(get_res.is_err());
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_session_started_before_password_change(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, _, refresh_token) = common::new_user(&app).await;
+  // Session that somehow survived password change.
+  sqlx::query("UPDATE \"User\" SET password_changed_at = NOW() WHERE id = $1")
+    .bind(user_dto.id)
+    .execute(&pool)
+    .await?;
+
+  let refresh_res = call_service(
+    &app,
+    TestRequest::post()
+      .uri("/api/user/auth/refresh")
+      .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token.clone()))
+      .to_request(),
+  )
+  .await;
+  assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+  let get_res =
+    common::as_refreshing(refresh_token, TestRequest::get().uri("/api/user/me"), &app).await;
+  assert!(get_res.is_err());
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_user_me_sessions(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (register_dto, _, access_token, refresh_token) = common::new_user(&app).await;
+  let other_login_res = call_service(
+    &app,
+    TestRequest::post()
+      .uri("/api/user/auth/login")
+      .append_header((header::USER_AGENT, "laguna-test"))
+      .set_json(LoginDTO::from(register_dto))
+      .to_request(),
+  )
+  .await;
+  assert_eq!(other_login_res.status(), StatusCode::OK);
+  let other_refresh_token = other_login_res
+    .headers()
+    .get(REFRESH_TOKEN_HEADER_NAME)
+    .unwrap()
+    .to_owned();
+  let sessions = || async {
+    let sessions_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::get().uri("/api/user/me/sessions"),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(sessions_res.status(), StatusCode::OK);
+    read_body_json::<Vec<SessionDTO>, _>(sessions_res).await
+  };
+
+  let sessions_dto = sessions().await;
+  assert_eq!(sessions_dto.len(), 2);
+  // Most recently used first.
+  assert_eq!(
+    sessions_dto[0].user_agent,
+    Some(String::from("laguna-test"))
+  );
+  assert!(!sessions_dto[0].is_current);
+  assert!(sessions_dto[1].is_current);
+
+  let revoke = |id: Uuid| {
+    common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::delete().uri(&format!("/api/user/me/sessions/{}", id)),
+      &app,
+    )
+  };
+  let revoke_res = revoke(sessions_dto[0].id).await.unwrap();
+  assert_eq!(revoke_res.status(), StatusCode::OK);
+  let revoke_res = revoke(sessions_dto[0].id).await.unwrap();
+  assert_eq!(revoke_res.status(), StatusCode::BAD_REQUEST);
+
+  let sessions_dto = sessions().await;
+  assert_eq!(sessions_dto.len(), 1);
+  assert!(sessions_dto[0].is_current);
+  let get_res = common::as_refreshing(
+    other_refresh_token,
+    TestRequest::get().uri("/api/user/me"),
+    &app,
+  )
+  .await;
+  assert!(get_res.is_err());
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_totp(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (register_dto, _, access_token, refresh_token) = common::new_user(&app).await;
+  let as_user =
+    |req: TestRequest| common::as_logged_in(access_token.clone(), refresh_token.clone(), req, &app);
+
+  let enroll_res = as_user(TestRequest::post().uri("/api/user/me/totp"))
+    .await
+    .unwrap();
+  assert_eq!(enroll_res.status(), StatusCode::OK);
+  let totp_enroll_dto = read_body_json::<TotpEnrollDTO, _>(enroll_res).await;
+  assert!(totp_enroll_dto.otpauth_uri.starts_with("otpauth://totp/"));
+  assert_eq!(
+    totp_enroll_dto.recovery_codes.len(),
+    TOTP_RECOVERY_CODE_COUNT
+  );
+  // Pending enrollment doesn't change login.
+  common::login_user_safe(LoginDTO::from(register_dto.clone()), &app).await;
+
+  let confirm = |code: String| {
+    as_user(
+      TestRequest::post()
+        .uri("/api/user/me/totp/confirm")
+        .set_json(TotpCodeDTO { code }),
+    )
+  };
+  // Only one step of clock skew is allowed.
+  let confirm_res = confirm(common::totp_code(&totp_enroll_dto.secret, 5))
+    .await
+    .unwrap();
+  assert_eq!(confirm_res.status(), StatusCode::UNAUTHORIZED);
+  // Recovery codes don't confirm enrollment.
+  let confirm_res = confirm(totp_enroll_dto.recovery_codes[0].clone())
+    .await
+    .unwrap();
+  assert_eq!(confirm_res.status(), StatusCode::UNAUTHORIZED);
+  let confirm_code = common::totp_code(&totp_enroll_dto.secret, 0);
+  let confirm_res = confirm(confirm_code.clone()).await.unwrap();
+  assert_eq!(confirm_res.status(), StatusCode::OK);
+  let enroll_res = as_user(TestRequest::post().uri("/api/user/me/totp"))
+    .await
+    .unwrap();
+  assert_eq!(enroll_res.status(), StatusCode::BAD_REQUEST);
+
+  let login = || async {
+    let login_res = common::login_user(LoginDTO::from(register_dto.clone()), &app).await;
+    assert_eq!(login_res.status(), StatusCode::ACCEPTED);
+    assert!(!login_res.headers().contains_key(ACCESS_TOKEN_HEADER_NAME));
+    let login_challenge_dto = read_body_json::<LoginChallengeDTO, _>(login_res).await;
+    assert!(!login_challenge_dto.enrollment_required);
+    login_challenge_dto.token
+  };
+  let login_totp = |token: String, code: String| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/login/totp")
+        .set_json(LoginTotpDTO { token, code })
+        .to_request(),
+    )
+  };
+
+  let token = login().await;
+  let login_totp_res = login_totp(
+    different_string(token.clone()),
+    common::totp_code(&totp_enroll_dto.secret, 1),
+  )
+  .await;
+  assert_eq!(login_totp_res.status(), StatusCode::BAD_REQUEST);
+  let login_totp_res =
+    login_totp(token.clone(), common::totp_code(&totp_enroll_dto.secret, 5)).await;
+  assert_eq!(login_totp_res.status(), StatusCode::UNAUTHORIZED);
+  // Code of confirmation can't be used again.
+  let login_totp_res = login_totp(token.clone(), confirm_code).await;
+  assert_eq!(login_totp_res.status(), StatusCode::UNAUTHORIZED);
+  let code = common::totp_code(&totp_enroll_dto.secret, 1);
+  common::login_totp_safe(
+    LoginTotpDTO {
+      token: token.clone(),
+      code: code.clone(),
+    },
+    &app,
+  )
+  .await;
+  let login_totp_res = login_totp(token.clone(), code).await;
+  assert_eq!(login_totp_res.status(), StatusCode::UNAUTHORIZED);
+
+  // Recovery codes are single use.
+  let recovery_code = totp_enroll_dto.recovery_codes[1].clone();
+  common::login_totp_safe(
+    LoginTotpDTO {
+      token: token.clone(),
+      code: recovery_code.clone(),
+    },
+    &app,
+  )
+  .await;
+  let login_totp_res = login_totp(token, recovery_code).await;
+  assert_eq!(login_totp_res.status(), StatusCode::UNAUTHORIZED);
+
+  let disable_res = as_user(
+    TestRequest::delete()
+      .uri("/api/user/me/totp")
+      .set_json(TotpCodeDTO {
+        code: totp_enroll_dto.recovery_codes[2].clone(),
+      }),
+  )
+  .await
+  .unwrap();
+  assert_eq!(disable_res.status(), StatusCode::OK);
+  common::login_user_safe(LoginDTO::from(register_dto), &app).await;
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_totp_admin_required(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  // Admin has to enroll on first login.
+  let (register_dto, _, access_token, refresh_token) = common::new_admin_user(&app, &pool).await;
+  let disable_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::delete()
+      .uri("/api/user/me/totp")
+      .set_json(TotpCodeDTO {
+        code: String::from("000000"),
+      }),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(disable_res.status(), StatusCode::FORBIDDEN);
+
+  let login_res = common::login_user(LoginDTO::from(register_dto), &app).await;
+  assert_eq!(login_res.status(), StatusCode::ACCEPTED);
+  let login_challenge_dto = read_body_json::<LoginChallengeDTO, _>(login_res).await;
+  assert!(!login_challenge_dto.enrollment_required);
+  // Enrollment can't be restarted once it is confirmed.
+  let enroll_res = call_service(
+    &app,
+    TestRequest::post()
+      .uri("/api/user/auth/login/totp/enroll")
+      .set_json(LoginTotpEnrollDTO {
+        token: login_challenge_dto.token,
+      })
+      .to_request(),
+  )
+  .await;
+  assert_eq!(enroll_res.status(), StatusCode::BAD_REQUEST);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_verify_email(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (register_dto, user_dto, _, _) = common::new_user(&app).await;
+  assert!(!user_dto.has_verified_email);
+
+  let token = common::last_mail_token(&register_dto.email);
+  // Signature covers user id, so token can't be moved to another user.
+  let (_, other_user_dto, _, _) = common::new_user(&app).await;
+  let forged_token = token.replacen(&user_dto.id.to_string(), &other_user_dto.id.to_string(), 1);
+  let verify_res = verify(forged_token, &app).await;
+  assert_eq!(verify_res.status(), StatusCode::BAD_REQUEST);
+  let verify_res = verify(String::from("invalid"), &app).await;
+  assert_eq!(verify_res.status(), StatusCode::BAD_REQUEST);
+
+  let verify_res = verify(token, &app).await;
+  assert_eq!(verify_res.status(), StatusCode::OK);
+  let (user_dto, _, _) = common::login_user_safe(LoginDTO::from(register_dto), &app).await;
+  assert!(user_dto.has_verified_email);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_verify_email_link_scheme(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  Settings::override_field(&mut settings.application.frontend.scheme, "https").unwrap();
+  let frontend_url = settings.application.frontend.url();
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let token = common::last_mail_token(&register_dto.email);
+  assert!(frontend_url.starts_with("https://"));
+  assert!(common::last_mail(&register_dto.email)
+    .contains(&format!("{}/verify?token={}", frontend_url, token)));
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_verify_email_resend(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let resend = |email: String| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/verify/resend")
+        .set_json(VerifyResendDTO { email })
+        .to_request(),
+    )
+  };
+
+  // Unknown emails are not reported.
+  let resend_res = resend(Faker.fake::<RegisterDTO>().email).await;
+  assert_eq!(resend_res.status(), StatusCode::OK);
+
+  let resend_res = resend(register_dto.email.clone()).await;
+  assert_eq!(resend_res.status(), StatusCode::OK);
+  common::wait_for_mail(&register_dto.email, 2).await;
+  let verify_res = verify(common::last_mail_token(&register_dto.email), &app).await;
+  assert_eq!(verify_res.status(), StatusCode::OK);
+  let (user_dto, _, _) = common::login_user_safe(LoginDTO::from(register_dto), &app).await;
+  assert!(user_dto.has_verified_email);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_verify_email_resend_lockout(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  settings.application.auth.login_max_failed_attempts = 3;
+  Settings::override_field(&mut settings.application.mail.transport, "smtp").unwrap();
+  // Nothing listens there, so every mail fails.
+  settings.application.mail.smtp_port = 1;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let resend = || {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/verify/resend")
+        .set_json(VerifyResendDTO {
+          email: register_dto.email.clone(),
+        })
+        .to_request(),
+    )
+  };
+
+  // Failed mail is not reported either.
+  for _ in 0..3 {
+    let resend_res = resend().await;
+    assert_eq!(resend_res.status(), StatusCode::OK);
+  }
+  let resend_res = resend().await;
+  assert_eq!(resend_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_password_change(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (register_dto, _, access_token, refresh_token) = common::new_user(&app).await;
+  let new_password = Faker.fake::<RegisterDTO>().password;
+  let change = |password: String| {
+    common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::post()
+        .uri("/api/user/me/password")
+        .set_json(PasswordChangeDTO {
+          password,
+          new_password: new_password.clone(),
+        }),
+      &app,
+    )
+  };
+
+  let change_res = change(different_string(register_dto.password.clone()))
+    .await
+    .unwrap();
+  assert_eq!(change_res.status(), StatusCode::UNAUTHORIZED);
+
+  let change_res = change(register_dto.password.clone()).await.unwrap();
+  assert_eq!(change_res.status(), StatusCode::OK);
+  let new_refresh_token = change_res
+    .headers()
+    .get(REFRESH_TOKEN_HEADER_NAME)
+    .unwrap()
+    .to_owned();
+
+  // Other sessions can't refresh anymore.
+  let get_res = common::as_refreshing(
+    refresh_token.clone(),
+    TestRequest::get().uri("/api/user/me"),
+    &app,
+  )
+  .await;
+  assert!(get_res.is_err());
+  let get_res = common::as_refreshing(
+    new_refresh_token,
+    TestRequest::get().uri("/api/user/me"),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+
+  let login_res = common::login_user(LoginDTO::from(register_dto.clone()), &app).await;
+  assert_eq!(login_res.status(), StatusCode::UNAUTHORIZED);
+  common::login_user_safe(
+    LoginDTO {
+      username_or_email: register_dto.email,
+      password: new_password,
+    },
+    &app,
+  )
+  .await;
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_password_forgot_mail_fails(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  Settings::override_field(&mut settings.application.mail.transport, "smtp").unwrap();
+  // Nothing listens there, so every mail fails.
+  settings.application.mail.smtp_port = 1;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let forgot = |email: String| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/password/forgot")
+        .set_json(PasswordForgotDTO { email })
+        .to_request(),
+    )
+  };
+
+  // Same response as for unknown email, failure is only logged.
+  let forgot_res = forgot(register_dto.email).await;
+  assert_eq!(forgot_res.status(), StatusCode::OK);
+  let forgot_res = forgot(Faker.fake::<RegisterDTO>().email).await;
+  assert_eq!(forgot_res.status(), StatusCode::OK);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_password_reset(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (register_dto, _, _, refresh_token) = common::new_user(&app).await;
+  let forgot_res = call_service(
+    &app,
+    TestRequest::post()
+      .uri("/api/user/auth/password/forgot")
+      .set_json(PasswordForgotDTO {
+        email: register_dto.email.clone(),
+      })
+      .to_request(),
+  )
+  .await;
+  assert_eq!(forgot_res.status(), StatusCode::OK);
+  // Verification mail from registration and reset mail.
+  common::wait_for_mail(&register_dto.email, 2).await;
+  let token = common::last_mail_token(&register_dto.email);
+  let new_password = Faker.fake::<RegisterDTO>().password;
+  let reset = |token: String| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/password/reset")
+        .set_json(PasswordResetDTO {
+          token,
+          new_password: new_password.clone(),
+        })
+        .to_request(),
+    )
+  };
+
+  let reset_res = reset(token.clone()).await;
+  assert_eq!(reset_res.status(), StatusCode::OK);
+  // Tokens are single use.
+  let reset_res = reset(token).await;
+  assert_eq!(reset_res.status(), StatusCode::BAD_REQUEST);
+
+  let get_res =
+    common::as_refreshing(refresh_token, TestRequest::get().uri("/api/user/me"), &app).await;
+  assert!(get_res.is_err());
+  common::login_user_safe(
+    LoginDTO {
+      username_or_email: register_dto.email,
+      password: new_password,
+    },
+    &app,
+  )
+  .await;
+  Ok(())
+}
+
 #[sqlx::test(migrations = "../../migrations")]
 async fn test_login(pool: PgPool) -> sqlx::Result<()> {
   let app = common::setup_test(&pool).await;
@@ -210,6 +836,299 @@ async fn test_login_with_wrong_password(pool: PgPool) -> sqlx::Result<()> {
   Ok(())
 }
 
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_login_lockout(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let max_failed_attempts = get_settings().application.auth.login_max_failed_attempts;
+  let (register_dto, user_dto, normie_access_token, normie_refresh_token) =
+    common::new_user(&app).await;
+  let wrong_login_dto = || LoginDTO {
+    username_or_email: user_dto.username.clone(),
+    password: different_string(register_dto.password.clone()),
+  };
+
+  for _ in 0..max_failed_attempts {
+    let login_res = common::login_user(wrong_login_dto(), &app).await;
+    assert_eq!(login_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  // Locked even with correct password.
+  let login_res = common::login_user(LoginDTO::from(register_dto.clone()), &app).await;
+  assert_eq!(login_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  let retry_after = login_res
+    .headers()
+    .get(header::RETRY_AFTER)
+    .unwrap()
+    .to_str()
+    .unwrap()
+    .parse::<i64>()
+    .unwrap();
+  assert!(retry_after > 0);
+  assert!(retry_after <= get_settings().application.auth.login_lockout_seconds);
+
+  let unlock = |access_token: HeaderValue, refresh_token: HeaderValue| {
+    common::as_logged_in(
+      access_token,
+      refresh_token,
+      TestRequest::delete().uri(&format!("/api/user/{}/lock", user_dto.id)),
+      &app,
+    )
+  };
+  let unlock_res = unlock(normie_access_token, normie_refresh_token).await;
+  assert!(unlock_res.is_err());
+  let (_, _, admin_access_token, admin_refresh_token) = common::new_admin_user(&app, &pool).await;
+  let unlock_res = unlock(admin_access_token, admin_refresh_token)
+    .await
+    .unwrap();
+  assert_eq!(unlock_res.status(), StatusCode::OK);
+  common::login_user_safe(LoginDTO::from(register_dto), &app).await;
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_login_ip_lockout(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  settings.application.auth.login_ip_max_failed_attempts = 3;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let login_from = |ip: &str, login_dto: LoginDTO| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/login")
+        .peer_addr(format!("{}:6881", ip).parse().unwrap())
+        .set_json(login_dto)
+        .to_request(),
+    )
+  };
+
+  // Failures count for IP address even when accounts don't exist.
+  for _ in 0..3 {
+    let login_res = login_from("10.0.0.1", Faker.fake::<RegisterDTO>().into()).await;
+    assert_eq!(login_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  let login_res = login_from("10.0.0.1", LoginDTO::from(register_dto.clone())).await;
+  assert_eq!(login_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  assert!(login_res.headers().contains_key(header::RETRY_AFTER));
+  let login_res = login_from("10.0.0.2", LoginDTO::from(register_dto)).await;
+  assert_eq!(login_res.status(), StatusCode::OK);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_login_lockout_expiry(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  settings.application.auth.login_max_failed_attempts = 2;
+  settings.application.auth.login_ip_max_failed_attempts = 2;
+  settings.application.auth.login_lockout_seconds = 1;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let login = |login_dto: LoginDTO| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/login")
+        .peer_addr("10.0.0.1:6881".parse().unwrap())
+        .set_json(login_dto)
+        .to_request(),
+    )
+  };
+  let wrong_login_dto = || LoginDTO {
+    username_or_email: register_dto.username.clone(),
+    password: different_string(register_dto.password.clone()),
+  };
+
+  for _ in 0..2 {
+    let login_res = login(wrong_login_dto()).await;
+    assert_eq!(login_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  let login_res = login(LoginDTO::from(register_dto.clone())).await;
+  assert_eq!(login_res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+  thread::sleep(StdDuration::from_millis(1100));
+  // Correct logins after lock expired don't lock account or IP address again.
+  for _ in 0..2 {
+    let login_res = login(LoginDTO::from(register_dto.clone())).await;
+    assert_eq!(login_res.status(), StatusCode::OK);
+  }
+  // Failures still lock with doubled lockout.
+  let login_res = login(wrong_login_dto()).await;
+  assert_eq!(login_res.status(), StatusCode::UNAUTHORIZED);
+  let login_res = login(LoginDTO::from(register_dto)).await;
+  assert_eq!(login_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_login_ip_lockout_forwarded_for(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  settings.application.auth.login_ip_max_failed_attempts = 3;
+  Settings::override_field(&mut settings.application.auth.trusted_proxies, "10.0.0.100").unwrap();
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let login_from = |ip: &str, forwarded_for: String, login_dto: LoginDTO| {
+    call_service(
+      &app,
+      TestRequest::post()
+        .uri("/api/user/auth/login")
+        .peer_addr(format!("{}:6881", ip).parse().unwrap())
+        .insert_header((header::X_FORWARDED_FOR, forwarded_for))
+        .set_json(login_dto)
+        .to_request(),
+    )
+  };
+
+  // Header is ignored when connection doesn't come from trusted proxy.
+  for i in 0..3 {
+    let login_res = login_from(
+      "10.0.0.1",
+      format!("192.168.0.{}", i),
+      Faker.fake::<RegisterDTO>().into(),
+    )
+    .await;
+    assert_eq!(login_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  let login_res = login_from(
+    "10.0.0.1",
+    String::from("192.168.0.100"),
+    LoginDTO::from(register_dto.clone()),
+  )
+  .await;
+  assert_eq!(login_res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+  // Behind trusted proxy only address added by proxy counts, not ones sent by client.
+  for i in 0..3 {
+    let login_res = login_from(
+      "10.0.0.100",
+      format!("192.168.0.{}, 10.0.0.2", i),
+      Faker.fake::<RegisterDTO>().into(),
+    )
+    .await;
+    assert_eq!(login_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  let login_res = login_from(
+    "10.0.0.100",
+    String::from("10.0.0.2"),
+    LoginDTO::from(register_dto.clone()),
+  )
+  .await;
+  assert_eq!(login_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  let login_res = login_from(
+    "10.0.0.100",
+    String::from("10.0.0.3"),
+    LoginDTO::from(register_dto),
+  )
+  .await;
+  assert_eq!(login_res.status(), StatusCode::OK);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_login_lockout_concurrent(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  settings.application.auth.login_max_failed_attempts = 3;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, _, _) = common::new_user(&app).await;
+  let wrong_login_dto = || LoginDTO {
+    username_or_email: register_dto.username.clone(),
+    password: different_string(register_dto.password.clone()),
+  };
+
+  // Attempts are counted before password is verified, so concurrent ones can't exceed the limit.
+  let login_statuses =
+    join_all((0..8).map(|_| async { common::login_user(wrong_login_dto(), &app).await.status() }))
+      .await;
+  assert_eq!(
+    login_statuses
+      .iter()
+      .filter(|status| **status == StatusCode::UNAUTHORIZED)
+      .count(),
+    3
+  );
+  assert_eq!(
+    login_statuses
+      .iter()
+      .filter(|status| **status == StatusCode::TOO_MANY_REQUESTS)
+      .count(),
+    5
+  );
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_password_change_lockout(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  settings.application.auth.login_max_failed_attempts = 3;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (register_dto, _, access_token, refresh_token) = common::new_user(&app).await;
+  let change = |password: String| {
+    common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::post()
+        .uri("/api/user/me/password")
+        .set_json(PasswordChangeDTO {
+          password,
+          new_password: Faker.fake::<RegisterDTO>().password,
+        }),
+      &app,
+    )
+  };
+
+  // Correct password is forgiven, it doesn't count towards lockout.
+  for _ in 0..2 {
+    let change_res = change(different_string(register_dto.password.clone()))
+      .await
+      .unwrap();
+    assert_eq!(change_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  common::login_user_safe(LoginDTO::from(register_dto.clone()), &app).await;
+  for _ in 0..3 {
+    let change_res = change(different_string(register_dto.password.clone()))
+      .await
+      .unwrap();
+    assert_eq!(change_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  let change_res = change(register_dto.password.clone()).await.unwrap();
+  assert_eq!(change_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  // Shared with login.
+  let login_res = common::login_user(LoginDTO::from(register_dto), &app).await;
+  assert_eq!(login_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_totp_lockout(pool: PgPool) -> sqlx::Result<()> {
+  let mut settings = get_settings();
+  settings.application.auth.login_max_failed_attempts = 3;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let (_, _, access_token, refresh_token) = common::new_user(&app).await;
+  let as_user =
+    |req: TestRequest| common::as_logged_in(access_token.clone(), refresh_token.clone(), req, &app);
+  let enroll_res = as_user(TestRequest::post().uri("/api/user/me/totp"))
+    .await
+    .unwrap();
+  let totp_enroll_dto = read_body_json::<TotpEnrollDTO, _>(enroll_res).await;
+  let confirm = |code: String| {
+    as_user(
+      TestRequest::post()
+        .uri("/api/user/me/totp/confirm")
+        .set_json(TotpCodeDTO { code }),
+    )
+  };
+
+  for _ in 0..3 {
+    let confirm_res = confirm(common::totp_code(&totp_enroll_dto.secret, 5))
+      .await
+      .unwrap();
+    assert_eq!(confirm_res.status(), StatusCode::UNAUTHORIZED);
+  }
+  let confirm_res = confirm(common::totp_code(&totp_enroll_dto.secret, 0))
+    .await
+    .unwrap();
+  assert_eq!(confirm_res.status(), StatusCode::TOO_MANY_REQUESTS);
+  Ok(())
+}
+
 #[sqlx::test(migrations = "../../migrations")]
 async fn test_register_password_too_long(pool: PgPool) -> sqlx::Result<()> {
   let app = common::setup_test(&pool).await;
@@ -10,17 +10,25 @@ use actix_web::{
   test::TestRequest,
 };
 
+use chrono::Utc;
 use fake::{Fake, Faker};
 
 use actix_web::test::init_service;
 use laguna_backend_config::Settings;
+use laguna_backend_dto::totp::{
+  LoginChallengeDTO, LoginTotpDTO, LoginTotpEnrollDTO, TotpEnrollDTO,
+};
 use laguna_backend_dto::user::UserDTO;
 use laguna_backend_dto::{login::LoginDTO, register::RegisterDTO};
-use laguna_backend_setup::{setup, setup_with_settings};
+use laguna_backend_model::consts::{TOTP_CODE_LEN, TOTP_STEP_SECONDS};
+use laguna_backend_model::user::Passkey;
+use laguna_backend_setup::{get_settings, setup, setup_with_settings};
 
 use laguna_backend_middleware::consts::{ACCESS_TOKEN_HEADER_NAME, REFRESH_TOKEN_HEADER_NAME};
 
 use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
 
 pub async fn setup_test(
   pool: &PgPool,
@@ -37,6 +45,7 @@ pub async fn setup_test_with_settings(
 }
 
 /// Registers and logs in a default user (Normie) with fake data.
+#[allow(dead_code)]
 pub async fn new_user(
   app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
 ) -> (RegisterDTO, UserDTO, HeaderValue, HeaderValue) {
@@ -61,6 +70,16 @@ pub async fn new_verified_user(
   (register_dto, user_dto, access_token, refresh_token)
 }
 
+/// Looks up user's passkey, it isn't exposed through UserDTO.
+#[allow(dead_code)]
+pub async fn passkey(pool: &PgPool, user_id: Uuid) -> Passkey {
+  sqlx::query_scalar::<_, Passkey>("SELECT passkey FROM \"User\" WHERE id = $1")
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .expect("Unable to get user's passkey")
+}
+
 /// Registers and logs in a Moderator user with fake data.
 #[allow(dead_code)]
 pub async fn new_mod_user(
@@ -78,7 +97,7 @@ pub async fn new_mod_user(
   (register_dto, user_dto, access_token, refresh_token)
 }
 
-/// Registers and logs in an Admin user with fake data.
+/// Registers and logs in an Admin user with fake data, enrolling TOTP required to log in.
 #[allow(dead_code)]
 pub async fn new_admin_user(
   app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
@@ -90,11 +109,78 @@ pub async fn new_admin_user(
     .execute(pool)
     .await
     .expect("Unable to set user to 'Admin'");
-  let (user_dto, access_token, refresh_token) =
-    login_user_safe(LoginDTO::from(register_dto.clone()), &app).await;
+  let login_res = login_user(LoginDTO::from(register_dto.clone()), &app).await;
+  assert_eq!(login_res.status(), StatusCode::ACCEPTED);
+  let login_challenge_dto = read_body_json::<LoginChallengeDTO, _>(login_res).await;
+  assert!(login_challenge_dto.enrollment_required);
+  let enroll_res = app
+    .call(
+      TestRequest::post()
+        .uri("/api/user/auth/login/totp/enroll")
+        .set_json(LoginTotpEnrollDTO {
+          token: login_challenge_dto.token.clone(),
+        })
+        .to_request(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(enroll_res.status(), StatusCode::OK);
+  let totp_enroll_dto = read_body_json::<TotpEnrollDTO, _>(enroll_res).await;
+  let (user_dto, access_token, refresh_token) = login_totp_safe(
+    LoginTotpDTO {
+      token: login_challenge_dto.token,
+      code: totp_code(&totp_enroll_dto.secret, 0),
+    },
+    &app,
+  )
+  .await;
   (register_dto, user_dto, access_token, refresh_token)
 }
 
+/// TOTP code of `secret` for current time step moved by `step_offset` steps.
+#[allow(dead_code)]
+pub fn totp_code(secret: &str, step_offset: i64) -> String {
+  TOTP::new_unchecked(
+    Algorithm::SHA1,
+    TOTP_CODE_LEN,
+    0,
+    TOTP_STEP_SECONDS,
+    Secret::Encoded(secret.to_string()).to_bytes().unwrap(),
+    None,
+    String::new(),
+  )
+  .generate((Utc::now().timestamp() + step_offset * TOTP_STEP_SECONDS as i64) as u64)
+}
+
+#[allow(dead_code)]
+pub async fn login_totp_safe(
+  login_totp_dto: LoginTotpDTO,
+  app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+) -> (UserDTO, HeaderValue, HeaderValue) {
+  let res = app
+    .call(
+      TestRequest::post()
+        .uri("/api/user/auth/login/totp")
+        .set_json(login_totp_dto)
+        .to_request(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(res.status(), StatusCode::OK);
+  let access_token = res
+    .headers()
+    .get(ACCESS_TOKEN_HEADER_NAME)
+    .unwrap()
+    .to_owned();
+  let refresh_token = res
+    .headers()
+    .get(REFRESH_TOKEN_HEADER_NAME)
+    .unwrap()
+    .to_owned();
+  let user_dto = read_body_json::<UserDTO, _>(res).await;
+  (user_dto, access_token, refresh_token)
+}
+
 /// Registers and logs in a Normie user given a RegisterDTO.
 pub async fn new_user_with(
   register_dto: RegisterDTO,
@@ -179,6 +265,63 @@ pub async fn as_logged_in(
   app.call(req.to_request()).await
 }
 
+/// Mail sent to `email` so far, mail transport has to be "file".
+#[allow(dead_code)]
+pub fn last_mail(email: &str) -> String {
+  let mail_settings = get_settings().application.mail;
+  std::fs::read_to_string(mail_settings.file_dir().join(format!("{}.eml", email)))
+    .expect("No mail sent to user")
+}
+
+/// Waits until `count` mails were sent to `email`, for mail sent in background.
+#[allow(dead_code)]
+pub async fn wait_for_mail(email: &str, count: usize) {
+  let mail_settings = get_settings().application.mail;
+  let path = mail_settings.file_dir().join(format!("{}.eml", email));
+  for _ in 0..50 {
+    let sent = std::fs::read_to_string(&path)
+      .map(|mails| {
+        mails
+          .lines()
+          .filter(|line| line.starts_with("To: "))
+          .count()
+      })
+      .unwrap_or(0);
+    if sent >= count {
+      return;
+    }
+    actix_web::rt::time::sleep(std::time::Duration::from_millis(100)).await;
+  }
+  panic!("Mail to {} not sent", email);
+}
+
+/// Token from last verification mail sent to `email`, mail transport has to be "file".
+#[allow(dead_code)]
+pub fn last_mail_token(email: &str) -> String {
+  last_mail(email)
+    .rsplit("token=")
+    .next()
+    .and_then(|token| token.split_whitespace().next())
+    .expect("No token in mail")
+    .to_string()
+}
+
+/// Sends request with refresh token only, so access token has to be renewed.
+#[allow(dead_code)]
+pub async fn as_refreshing(
+  refresh_token: HeaderValue,
+  req: TestRequest,
+  app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+) -> Result<ServiceResponse, actix_web::Error> {
+  app
+    .call(
+      req
+        .append_header((REFRESH_TOKEN_HEADER_NAME, refresh_token))
+        .to_request(),
+    )
+    .await
+}
+
 // Replaces the last character of the string with its successor, guaranteeing that the new string is different from the original.
 // We need this because we have tests (using fake data) that require different strings than original which Faker cannot guarantee.
 // TODO: Find a better way
@@ -200,6 +343,28 @@ pub struct MultipartField<'a> {
   pub content_type: &'a str,
 }
 
+/// Makes BitTorrent v2 (BEP 52) torrent with a single 5 byte file.
+/// Hybrid torrent also has v1 metadata.
+/// Returns bencoded torrent and its bencoded info dictionary.
+#[allow(dead_code)]
+pub fn make_v2_torrent(hybrid: bool) -> (Vec<u8>, Vec<u8>) {
+  let mut info = Vec::new();
+  info.extend_from_slice(b"d9:file treed9:hello.txtd0:d6:lengthi5e11:pieces root32:");
+  info.extend_from_slice(&[7; 32]);
+  info.extend_from_slice(b"eee");
+  if hybrid {
+    info.extend_from_slice(b"6:lengthi5e");
+  }
+  info.extend_from_slice(b"12:meta versioni2e4:name9:hello.txt12:piece lengthi16384e");
+  if hybrid {
+    info.extend_from_slice(b"6:pieces20:");
+    info.extend_from_slice(&[9; 20]);
+  }
+  info.extend_from_slice(b"e");
+  let torrent = [&b"d13:creation datei1690000000e4:info"[..], &info, b"e"].concat();
+  (torrent, info)
+}
+
 /// Actix doesn't have a way to create Test multipart requests, so we have to do it manually.
 /// This function utilizes [`MultipartField`] to create a multipart request.
 #[allow(dead_code)]
@@ -1,4 +1,19 @@
-use actix_web::test::TestRequest;
+use std::net::Ipv6Addr;
+
+use actix_http::{Request, StatusCode};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::test::{call_service, read_body, read_body_json, TestRequest};
+use actix_web::web::Bytes;
+
+use bendy::decoding::FromBencode;
+use laguna_backend_api::reaper::expire_stale_peers;
+use laguna_backend_dto::torrent::{TorrentDTO, TorrentFile, TorrentTrashDTO};
+use laguna_backend_middleware::mime::APPLICATION_XBITTORRENT;
+use laguna_backend_model::behaviour::Behaviour;
+use laguna_backend_model::consts::SCRAPE_INFO_HASH_COUNT_MAX;
+use laguna_backend_model::user::Passkey;
+use laguna_backend_setup::get_settings;
+use laguna_backend_tracker_common::info_hash::SHA1_LENGTH;
 
 use sqlx::PgPool;
 
@@ -31,3 +46,794 @@ async fn test_announce_peer_stopped(_pool: PgPool) -> sqlx::Result<()> {
 async fn test_patch_peer(_pool: PgPool) -> sqlx::Result<()> {
   Ok(())
 }
+
+fn percent_encode(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("%{:02X}", b)).collect()
+}
+
+/// Uploads bunny.torrent and downloads it as a new verified user.
+async fn upload_and_download(
+  app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+  pool: &PgPool,
+) -> (TorrentDTO<SHA1_LENGTH>, Passkey) {
+  upload_and_download_torrent(
+    app,
+    pool,
+    include_bytes!("fixtures/webtorrent-fixtures/fixtures/bunny.torrent"),
+  )
+  .await
+}
+
+/// Uploads `torrent` and downloads it as a new verified user.
+async fn upload_and_download_torrent(
+  app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+  pool: &PgPool,
+  torrent: &[u8],
+) -> (TorrentDTO<SHA1_LENGTH>, Passkey) {
+  let (_, user_dto, access_token, refresh_token) = common::new_verified_user(app, pool).await;
+  let put_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    common::make_multipart(
+      TestRequest::put().uri("/api/torrent/"),
+      vec![common::MultipartField {
+        name: b"torrent",
+        filename: b"upload.torrent",
+        content: torrent,
+        content_type: APPLICATION_XBITTORRENT,
+        boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+      }],
+    ),
+    app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(put_res.status(), StatusCode::OK);
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+
+  let get_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}/raw", torrent_dto.info_hash)),
+    app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+
+  // Downloaded torrent announces with user's passkey.
+  let passkey = common::passkey(pool, user_dto.id).await;
+  assert!(TorrentFile::from_bencode(&read_body(get_res).await)
+    .unwrap()
+    .announce_url
+    .unwrap()
+    .ends_with(&format!("/peer/{}/announce", passkey)));
+  (torrent_dto, passkey)
+}
+
+/// Announces peer from 127.0.0.1:`port` and returns bencoded reply.
+async fn announce(
+  app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+  torrent_dto: &TorrentDTO<SHA1_LENGTH>,
+  passkey: &Passkey,
+  peer_id: &[u8; 20],
+  port: u16,
+  left: i64,
+  extra_query: &str,
+) -> Bytes {
+  let announce_res = call_service(
+    app,
+    TestRequest::get()
+      .uri(&format!(
+        "/peer/{}/announce?info_hash={}&peer_id={}&port={}&uploaded=0&downloaded=0&left={}{}",
+        passkey,
+        percent_encode(&torrent_dto.info_hash.0),
+        percent_encode(peer_id),
+        port,
+        left,
+        extra_query,
+      ))
+      .peer_addr(format!("127.0.0.1:{}", port).parse().unwrap())
+      .to_request(),
+  )
+  .await;
+  assert_eq!(announce_res.status(), StatusCode::OK);
+  read_body(announce_res).await
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_numwant(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-seedseedseed",
+    7001,
+    0,
+    "",
+  )
+  .await;
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech01",
+    7002,
+    1000,
+    "",
+  )
+  .await;
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech02",
+    7003,
+    1000,
+    "",
+  )
+  .await;
+
+  // Leechers get seeds first.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech03",
+    7004,
+    1000,
+    "&numwant=1",
+  )
+  .await;
+  let peers = [&b"5:peers6:"[..], &[127, 0, 0, 1], &7001_u16.to_be_bytes()].concat();
+  assert!(reply.windows(peers.len()).any(|window| window == peers));
+
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech04",
+    7005,
+    1000,
+    "&numwant=0",
+  )
+  .await;
+  assert!(reply.windows(9).any(|window| window == b"5:peers0:"));
+
+  // Seeds only get leechers, at most numwant-max of them.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-seedseedsee2",
+    7006,
+    0,
+    "&numwant=100000",
+  )
+  .await;
+  assert!(reply.windows(10).any(|window| window == b"5:peers24:"));
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_trashed_torrent(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+  let (_, _, access_token, refresh_token) = common::new_mod_user(&app, &pool).await;
+  let trash_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::delete()
+      .uri(&format!("/api/torrent/{}", torrent_dto.info_hash))
+      .set_json(TorrentTrashDTO {
+        reason: String::from("Dvojnik"),
+      }),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(trash_res.status(), StatusCode::OK);
+
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    6881,
+    1000,
+    "&event=started",
+  )
+  .await;
+  let failure_reason = "Torrent je bil odstranjen: Dvojnik.";
+  assert!(reply.starts_with(
+    format!(
+      "d14:failure reason{}:{}",
+      failure_reason.len(),
+      failure_reason
+    )
+    .as_bytes()
+  ));
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_min_interval(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    6881,
+    1000,
+    "&event=started",
+  )
+  .await;
+  let intervals = b"8:intervali1800e12:min intervali900e";
+  assert!(reply
+    .windows(intervals.len())
+    .any(|window| window == intervals));
+
+  // Regular announce right after start is too frequent.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    6881,
+    1000,
+    "",
+  )
+  .await;
+  assert!(reply.starts_with(b"d14:failure reason"));
+
+  // Events are accepted regardless of interval.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    6881,
+    1000,
+    "&event=stopped",
+  )
+  .await;
+  assert!(!reply.starts_with(b"d14:failure reason"));
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_same_peer_id_on_two_torrents(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (bunny_dto, bunny_passkey) = upload_and_download(&app, &pool).await;
+  let (leaves_dto, leaves_passkey) = upload_and_download_torrent(
+    &app,
+    &pool,
+    include_bytes!("fixtures/webtorrent-fixtures/fixtures/leaves.torrent"),
+  )
+  .await;
+  // Most clients use single peer id for all torrents.
+  let peer_id = b"-qB4550-abcdefghijkl";
+
+  announce(
+    &app,
+    &bunny_dto,
+    &bunny_passkey,
+    peer_id,
+    6881,
+    1000,
+    "&event=started",
+  )
+  .await;
+  announce(
+    &app,
+    &leaves_dto,
+    &leaves_passkey,
+    peer_id,
+    6881,
+    1000,
+    "&event=started",
+  )
+  .await;
+  // Last announces were longer than min interval ago.
+  sqlx::query("UPDATE \"Peer\" SET updated_at = NOW() - INTERVAL '1000 seconds'")
+    .execute(&pool)
+    .await?;
+
+  // Announce on one torrent doesn't count as announce on the other.
+  let reply = announce(&app, &leaves_dto, &leaves_passkey, peer_id, 6881, 0, "").await;
+  assert!(!reply.starts_with(b"d14:failure reason"));
+  let reply = announce(&app, &bunny_dto, &bunny_passkey, peer_id, 6881, 1000, "").await;
+  assert!(!reply.starts_with(b"d14:failure reason"));
+  assert_eq!(torrent_counts(&pool, &bunny_dto).await, (0, 1, 0));
+  assert_eq!(torrent_counts(&pool, &leaves_dto).await, (1, 0, 0));
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_peers6(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-seedseedseed",
+    7001,
+    0,
+    "&event=started",
+  )
+  .await;
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-seedseedsee6",
+    7002,
+    0,
+    "&event=started&ip=2001:db8::1",
+  )
+  .await;
+
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    7003,
+    1000,
+    "&event=started&compact=1",
+  )
+  .await;
+  let peers = [&b"5:peers6:"[..], &[127, 0, 0, 1], &7001_u16.to_be_bytes()].concat();
+  assert!(reply.windows(peers.len()).any(|window| window == peers));
+  let peers6 = [
+    &b"6:peers618:"[..],
+    &"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets(),
+    &7002_u16.to_be_bytes(),
+  ]
+  .concat();
+  assert!(reply.windows(peers6.len()).any(|window| window == peers6));
+
+  // Non-compact reply has all peers in dictionaries.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-mnopqrstuvwx",
+    7004,
+    1000,
+    "&event=started&compact=0",
+  )
+  .await;
+  assert!(reply.windows(15).any(|window| window == b"2:ip11:2001:db8"));
+  assert!(!reply.windows(6).any(|window| window == b"peers6"));
+
+  Ok(())
+}
+
+async fn torrent_counts(pool: &PgPool, torrent_dto: &TorrentDTO<SHA1_LENGTH>) -> (i32, i32, i32) {
+  sqlx::query_as::<_, (i32, i32, i32)>(
+    "SELECT seed_count, leech_count, completed_count FROM \"Torrent\" WHERE info_hash = $1",
+  )
+  .bind(torrent_dto.info_hash.clone())
+  .fetch_one(pool)
+  .await
+  .unwrap()
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_torrent_counts(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (0, 0, 0));
+
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-seedseedseed",
+    7001,
+    0,
+    "&event=started",
+  )
+  .await;
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech01",
+    7002,
+    1000,
+    "&event=started",
+  )
+  .await;
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (1, 1, 0));
+
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech01",
+    7002,
+    0,
+    "&event=completed",
+  )
+  .await;
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (2, 0, 1));
+
+  // Repeated completion is not counted again.
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech01",
+    7002,
+    0,
+    "&event=completed",
+  )
+  .await;
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (2, 0, 1));
+
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-seedseedseed",
+    7001,
+    0,
+    "&event=stopped",
+  )
+  .await;
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (1, 0, 1));
+
+  sqlx::query("UPDATE \"Peer\" SET updated_at = NOW() - INTERVAL '1 day'")
+    .execute(&pool)
+    .await?;
+  // Stale peers are not counted even before reaper stops them, same as in scrape.
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-leechleech02",
+    7003,
+    1000,
+    "&event=started",
+  )
+  .await;
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (0, 1, 1));
+  expire_stale_peers(&pool, &get_settings().application.tracker).await?;
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (0, 1, 1));
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_stale_peers(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-stalestale01",
+    7001,
+    0,
+    "&event=started",
+  )
+  .await;
+  // Peer crashed a day ago, without sending stopped.
+  sqlx::query("UPDATE \"Peer\" SET updated_at = NOW() - INTERVAL '1 day'")
+    .execute(&pool)
+    .await?;
+
+  // Stale peer is excluded from swarm before reaper runs.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    7002,
+    1000,
+    "&event=started",
+  )
+  .await;
+  assert!(reply.windows(9).any(|window| window == b"5:peers0:"));
+
+  let tracker_settings = get_settings().application.tracker;
+  assert_eq!(expire_stale_peers(&pool, &tracker_settings).await?, 1);
+  assert_eq!(expire_stale_peers(&pool, &tracker_settings).await?, 0);
+  let behaviours =
+    sqlx::query_scalar::<_, Behaviour>("SELECT behaviour FROM \"Peer\" ORDER BY port")
+      .fetch_all(&pool)
+      .await?;
+  assert_eq!(behaviours, vec![Behaviour::Stopped, Behaviour::Downloader]);
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_scrape(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    6881,
+    1000,
+    "&event=started",
+  )
+  .await;
+
+  // Unknown torrents are omitted.
+  let scrape_res = call_service(
+    &app,
+    TestRequest::get()
+      .uri(&format!(
+        "/peer/{}/scrape?info_hash={}&info_hash={}",
+        passkey,
+        percent_encode(&torrent_dto.info_hash.0),
+        percent_encode(&[0xAB; SHA1_LENGTH]),
+      ))
+      .to_request(),
+  )
+  .await;
+  assert_eq!(scrape_res.status(), StatusCode::OK);
+  assert_eq!(
+    read_body(scrape_res).await,
+    [
+      &b"d5:filesd20:"[..],
+      &torrent_dto.info_hash.0,
+      b"d8:completei0e10:downloadedi0e10:incompletei1eeee",
+    ]
+    .concat()
+  );
+
+  // Scrape without info hashes is rejected with failure reason.
+  let scrape_res = call_service(
+    &app,
+    TestRequest::get()
+      .uri(&format!("/peer/{}/scrape", passkey))
+      .to_request(),
+  )
+  .await;
+  assert_eq!(scrape_res.status(), StatusCode::OK);
+  assert!(read_body(scrape_res)
+    .await
+    .starts_with(b"d14:failure reason"));
+
+  // So is scrape of too many torrents at once.
+  let query = vec![
+    format!("info_hash={}", percent_encode(&torrent_dto.info_hash.0));
+    SCRAPE_INFO_HASH_COUNT_MAX + 1
+  ]
+  .join("&");
+  let scrape_res = call_service(
+    &app,
+    TestRequest::get()
+      .uri(&format!("/peer/{}/scrape?{}", passkey, query))
+      .to_request(),
+  )
+  .await;
+  assert_eq!(scrape_res.status(), StatusCode::OK);
+  assert!(read_body(scrape_res)
+    .await
+    .starts_with(b"d14:failure reason"));
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_scrape_v2(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let mut info_hashes_v2 = Vec::new();
+  let mut passkey = None;
+  for (hybrid, peer_id) in [
+    (true, b"-qB4550-hybridhybrid"),
+    (false, b"-qB4550-v2onlyv2only"),
+  ] {
+    let (torrent, _) = common::make_v2_torrent(hybrid);
+    let (torrent_dto, torrent_passkey) = upload_and_download_torrent(&app, &pool, &torrent).await;
+    let info_hash_v2 = torrent_dto.info_hash_v2.unwrap();
+    let announce_res = call_service(
+      &app,
+      TestRequest::get()
+        .uri(&format!(
+          "/peer/{}/v2/announce?info_hash={}&peer_id={}&port=7001&uploaded=0&downloaded=0&left=5",
+          torrent_passkey,
+          percent_encode(&info_hash_v2.0),
+          percent_encode(peer_id),
+        ))
+        .peer_addr("127.0.0.1:7001".parse().unwrap())
+        .to_request(),
+    )
+    .await;
+    assert_eq!(announce_res.status(), StatusCode::OK);
+    info_hashes_v2.push(info_hash_v2);
+    passkey = Some(torrent_passkey);
+  }
+  let passkey = passkey.unwrap();
+
+  // Torrents are reported under full v2 info hash they were scraped with.
+  let scrape_res = call_service(
+    &app,
+    TestRequest::get()
+      .uri(&format!(
+        "/peer/{}/v2/scrape?info_hash={}&info_hash={}",
+        passkey,
+        percent_encode(&info_hashes_v2[0].0),
+        percent_encode(&info_hashes_v2[1].0),
+      ))
+      .to_request(),
+  )
+  .await;
+  assert_eq!(scrape_res.status(), StatusCode::OK);
+  let reply = read_body(scrape_res).await;
+  for info_hash_v2 in info_hashes_v2 {
+    let file = [
+      &b"32:"[..],
+      &info_hash_v2.0,
+      b"d8:completei0e10:downloadedi0e10:incompletei1ee",
+    ]
+    .concat();
+    assert!(reply.windows(file.len()).any(|w| w == file));
+  }
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_hybrid_v2(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent, _) = common::make_v2_torrent(true);
+  let (torrent_dto, passkey) = upload_and_download_torrent(&app, &pool, &torrent).await;
+  let info_hash_v2 = torrent_dto.info_hash_v2.clone().unwrap();
+
+  // v2 clients announce truncated v2 info hash on v1 tracker protocol
+  // and full v2 info hash on v2 route, both match torrent stored under v1 info hash.
+  for (route, info_hash, peer_id) in [
+    (
+      "announce",
+      &info_hash_v2.0[..SHA1_LENGTH],
+      b"-qB4550-truncatedv2h",
+    ),
+    ("v2/announce", &info_hash_v2.0[..], b"-qB4550-fullv2hashhh"),
+  ] {
+    let announce_res = call_service(
+      &app,
+      TestRequest::get()
+        .uri(&format!(
+          "/peer/{}/{}?info_hash={}&peer_id={}&port=7001&uploaded=0&downloaded=0&left=5",
+          passkey,
+          route,
+          percent_encode(info_hash),
+          percent_encode(peer_id),
+        ))
+        .peer_addr("127.0.0.1:7001".parse().unwrap())
+        .to_request(),
+    )
+    .await;
+    assert_eq!(announce_res.status(), StatusCode::OK);
+    let reply = read_body(announce_res).await;
+    assert!(!reply.windows(14).any(|w| w == b"failure reason"));
+  }
+
+  assert_eq!(torrent_counts(&pool, &torrent_dto).await, (0, 2, 0));
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_passkey_reset(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  sqlx::query("UPDATE \"User\" SET passkey = DEFAULT WHERE passkey = $1")
+    .bind(passkey.clone())
+    .execute(&pool)
+    .await?;
+
+  // Torrents downloaded with old passkey stop working.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    6881,
+    1000,
+    "",
+  )
+  .await;
+  assert!(reply.starts_with(b"d14:failure reason"));
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_disabled_user(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  sqlx::query("UPDATE \"User\" SET is_enabled = FALSE WHERE passkey = $1")
+    .bind(passkey.clone())
+    .execute(&pool)
+    .await?;
+
+  // Banned users keep their passkey, but can't announce with it.
+  let reply = announce(
+    &app,
+    &torrent_dto,
+    &passkey,
+    b"-qB4550-abcdefghijkl",
+    6881,
+    1000,
+    "&event=started",
+  )
+  .await;
+  let failure_reason = "Uporabniški račun je onemogočen.";
+  assert!(reply.starts_with(
+    format!(
+      "d14:failure reason{}:{}",
+      failure_reason.len(),
+      failure_reason
+    )
+    .as_bytes()
+  ));
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_announce_legacy_down_hash(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (torrent_dto, passkey) = upload_and_download(&app, &pool).await;
+
+  // Torrents downloaded before passkeys were introduced announce with down_hash.
+  let down_hash = "ab".repeat(32);
+  sqlx::query(
+    "INSERT INTO \"Download\" (info_hash, user_id, ts, down_hash)
+     SELECT $1, id, NOW(), decode($2, 'hex') FROM \"User\" WHERE passkey = $3",
+  )
+  .bind(torrent_dto.info_hash.clone())
+  .bind(&down_hash)
+  .bind(passkey)
+  .execute(&pool)
+  .await?;
+  let legacy_announce = || {
+    TestRequest::get()
+      .uri(&format!(
+        "/peer/announce?down_hash={}&info_hash={}&peer_id={}&port=6881&uploaded=0&downloaded=0&left=1000",
+        down_hash,
+        percent_encode(&torrent_dto.info_hash.0),
+        percent_encode(b"-qB4550-abcdefghijkl"),
+      ))
+      .peer_addr("127.0.0.1:6881".parse().unwrap())
+      .to_request()
+  };
+
+  let announce_res = call_service(&app, legacy_announce()).await;
+  assert_eq!(announce_res.status(), StatusCode::OK);
+  assert!(!read_body(announce_res)
+    .await
+    .starts_with(b"d14:failure reason"));
+
+  // After migration window, down_hash is rejected.
+  let mut settings = get_settings();
+  settings.application.tracker.accept_down_hash = false;
+  let app = common::setup_test_with_settings(settings, &pool).await;
+  let announce_res = call_service(&app, legacy_announce()).await;
+  assert_eq!(announce_res.status(), StatusCode::OK);
+  assert!(read_body(announce_res)
+    .await
+    .starts_with(b"d14:failure reason"));
+  Ok(())
+}
@@ -8,20 +8,24 @@ use bendy::decoding::FromBencode;
 use chrono::{DateTime, Utc};
 
 use digest::Digest;
+use laguna_backend_api::error::torrent::MetainfoError;
 use laguna_backend_dto::{
   peer::PeerDTO,
-  torrent::{TorrentDTO, TorrentFile, TorrentPatchDTO},
+  torrent::{
+    FileNodeDTO, MagnetDTO, TorrentDTO, TorrentFile, TorrentFileDTO, TorrentFilesDTO,
+    TorrentPageDTO, TorrentPatchDTO, TorrentTrashDTO,
+  },
+  torrent_revision::TorrentRevisionDTO,
 };
 use laguna_backend_middleware::mime::APPLICATION_XBITTORRENT;
-use laguna_backend_model::{
-  download::{Download, DownloadHash},
-  genre::Genre,
-  speedlevel::SpeedLevel,
-};
+use laguna_backend_model::{genre::Genre, speedlevel::SpeedLevel};
 
-use laguna_backend_tracker_common::info_hash::SHA1_LENGTH;
+use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH, SHA256_LENGTH};
+use serde_json::json;
+use sha1::Sha1;
 use sha2::Sha256;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 mod common;
 
@@ -48,11 +52,11 @@ async fn test_get_torrent_bunny(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
 
   assert_eq!(put_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
-  let expected_torrent_dto = TorrentDTO {
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  let expected_torrent_dto = TorrentDTO::<SHA1_LENGTH> {
     info_hash: torrent_dto.info_hash.clone(),
-    raw: include_bytes!("fixtures/webtorrent-fixtures/fixtures/bunny.torrent").to_vec(),
-    announce_url: None,
+    info_hash_v2: None,
+    announce_url: Some(String::from("http://127.0.0.1:6969/peer/announce")),
     length: 434839491,
     file_name: String::from("bbb_sunflower_1080p_30fps_stereo_abl.mp4"),
     nfo: None,
@@ -68,6 +72,17 @@ async fn test_get_torrent_bunny(pool: PgPool) -> sqlx::Result<()> {
     uploaded_by: user_dto.id,
     modded_at: None,
     modded_by: None,
+    deleted_at: None,
+    deleted_by: None,
+    delete_reason: None,
+    comment: None,
+    piece_length: 262144,
+    piece_count: 1659,
+    files: vec![TorrentFileDTO {
+      path: vec![String::from("bbb_sunflower_1080p_30fps_stereo_abl.mp4")],
+      length: 434839491,
+    }],
+    url_list: None,
   };
   assert_eq!(torrent_dto, expected_torrent_dto);
 
@@ -81,7 +96,7 @@ async fn test_get_torrent_bunny(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
 
   assert_eq!(get_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(get_res).await;
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(get_res).await;
   assert_eq!(torrent_dto, expected_torrent_dto,);
 
   Ok(())
@@ -111,7 +126,7 @@ async fn test_get_torrent_bunny_raw(pool: PgPool) -> sqlx::Result<()> {
 
   assert_eq!(put_res.status(), StatusCode::OK);
 
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
 
   let get_res = common::as_logged_in(
     access_token,
@@ -128,40 +143,18 @@ async fn test_get_torrent_bunny_raw(pool: PgPool) -> sqlx::Result<()> {
 
   let torrent = TorrentFile::from_bencode(torrent_bytes).unwrap();
 
-  // check if Download was inserted
-  // aka.   queries/download_lookup.sql
-  let download = sqlx::query_as::<_, Download<SHA1_LENGTH>>(
-    "SELECT * FROM \"Download\" WHERE info_hash = $1 AND user_id = $2",
-  )
-  .bind(torrent_dto.info_hash.clone())
-  .bind(user_dto.id)
-  .fetch_one(&pool)
-  .await?;
-
+  // Announce URL carries user's passkey instead of a per-download hash.
+  let passkey = common::passkey(&pool, user_dto.id).await;
   assert_eq!(
     torrent.announce_url,
-    Some(format!(
-      "http://127.0.0.1:6969/peer/announce?down_hash={}",
-      download.down_hash
-    ))
+    Some(format!("http://127.0.0.1:6969/peer/{}/announce", passkey))
   );
 
-  assert_eq!(download.info_hash, torrent_dto.info_hash);
-  assert_eq!(download.user_id, user_dto.id);
-  assert_eq!(
-    download.down_hash,
-    DownloadHash::from(
-      Sha256::digest(
-        [
-          &torrent_dto.info_hash.0,
-          &user_dto.id.to_bytes_le()[..],
-          &download.ts.timestamp().to_le_bytes()
-        ]
-        .concat()
-      )
-      .to_vec()
-    )
-  );
+  // Passkey is stable across downloads, no Download rows are written anymore.
+  let downloads = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM \"Download\"")
+    .fetch_one(&pool)
+    .await?;
+  assert_eq!(downloads, 0);
 
   Ok(())
 }
@@ -189,11 +182,11 @@ async fn test_put_torrent(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
 
   assert_eq!(put_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
-  let expected_torrent_dto = TorrentDTO {
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  let expected_torrent_dto = TorrentDTO::<SHA1_LENGTH> {
     info_hash: torrent_dto.info_hash.clone(),
-    raw: include_bytes!("fixtures/webtorrent-fixtures/fixtures/leaves.torrent").to_vec(),
-    announce_url: None,
+    info_hash_v2: None,
+    announce_url: Some(String::from("http://127.0.0.1:6969/peer/announce")),
     length: 362017,
     file_name: String::from("Leaves of Grass by Walt Whitman.epub"),
     nfo: None,
@@ -209,6 +202,17 @@ async fn test_put_torrent(pool: PgPool) -> sqlx::Result<()> {
     uploaded_by: user_dto.id,
     modded_at: None,
     modded_by: None,
+    deleted_at: None,
+    deleted_by: None,
+    delete_reason: None,
+    comment: None,
+    piece_length: 16384,
+    piece_count: 23,
+    files: vec![TorrentFileDTO {
+      path: vec![String::from("Leaves of Grass by Walt Whitman.epub")],
+      length: 362017,
+    }],
+    url_list: None,
   };
 
   assert_eq!(torrent_dto, expected_torrent_dto);
@@ -242,6 +246,439 @@ async fn test_put_torrent_unverified(pool: PgPool) -> sqlx::Result<()> {
   Ok(())
 }
 
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_put_torrent_v2(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, _, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  for hybrid in [true, false] {
+    let (torrent, info) = common::make_v2_torrent(hybrid);
+    // Canonical torrent is private, info hashes are computed with private flag set.
+    let info = [&info[..info.len() - 1], b"7:privatei1ee"].concat();
+    let put_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      common::make_multipart(
+        TestRequest::put().uri("/api/torrent/"),
+        vec![common::MultipartField {
+          name: b"torrent",
+          filename: b"hello.torrent",
+          content: &torrent,
+          content_type: APPLICATION_XBITTORRENT,
+          boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+        }],
+      ),
+      &app,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(put_res.status(), StatusCode::OK);
+    let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+    let info_hash_v2 = Sha256::digest(&info);
+    // Hybrid torrents are stored under v1 info hash, v2 only under truncated v2 info hash.
+    let info_hash = if hybrid {
+      InfoHash::<SHA1_LENGTH>(Sha1::digest(&info).into())
+    } else {
+      InfoHash::<SHA1_LENGTH>::from(info_hash_v2[..SHA1_LENGTH].to_vec())
+    };
+    assert_eq!(torrent_dto.info_hash, info_hash);
+    assert_eq!(
+      torrent_dto.info_hash_v2,
+      Some(InfoHash::<SHA256_LENGTH>(info_hash_v2.into()))
+    );
+    assert_eq!(torrent_dto.length, 5);
+    assert_eq!(torrent_dto.file_name, "hello.txt");
+    // File list and piece count come from v2 file tree for v2 only torrents.
+    assert_eq!(
+      torrent_dto.files,
+      vec![TorrentFileDTO {
+        path: vec![String::from("hello.txt")],
+        length: 5,
+      }]
+    );
+    assert_eq!(torrent_dto.piece_count, 1);
+  }
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_put_torrent_v2_duplicate(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, _, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let (torrent, _) = common::make_v2_torrent(true);
+  let put = || {
+    common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      common::make_multipart(
+        TestRequest::put().uri("/api/torrent/"),
+        vec![common::MultipartField {
+          name: b"torrent",
+          filename: b"hello.torrent",
+          content: &torrent,
+          content_type: APPLICATION_XBITTORRENT,
+          boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+        }],
+      ),
+      &app,
+    )
+  };
+  assert_eq!(put().await.unwrap().status(), StatusCode::OK);
+  assert_eq!(put().await.unwrap().status(), StatusCode::ALREADY_REPORTED);
+
+  // Torrent stored under different v1 info hash, but with same v2 info hash, is a duplicate as well.
+  sqlx::query("UPDATE \"Torrent\" SET info_hash = $1")
+    .bind(vec![0xAB_u8; SHA1_LENGTH])
+    .execute(&pool)
+    .await?;
+  assert_eq!(put().await.unwrap().status(), StatusCode::ALREADY_REPORTED);
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_put_torrent_invalid_metainfo(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, _, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let torrent = |extra: &[u8], info: &[u8]| {
+    [
+      &b"d13:creation datei1690000000e"[..],
+      extra,
+      b"4:infod",
+      info,
+      b"ee",
+    ]
+    .concat()
+  };
+  let single = |piece_length: &[u8], pieces: &[u8]| {
+    [
+      &b"6:lengthi40000e4:name4:file12:piece lengthi"[..],
+      piece_length,
+      b"e6:pieces",
+      format!("{}:", pieces.len()).as_bytes(),
+      pieces,
+    ]
+    .concat()
+  };
+  let cases = [
+    (
+      torrent(b"", &single(b"16384", &[1; 50])),
+      vec![MetainfoError::PiecesLength(50)],
+    ),
+    (
+      torrent(b"", &single(b"10000", &[1; 80])),
+      vec![MetainfoError::PieceLength(10000)],
+    ),
+    (
+      torrent(b"", &single(b"16384", &[1; 40])),
+      vec![MetainfoError::PieceCount {
+        expected: 3,
+        actual: 2,
+      }],
+    ),
+    (
+      torrent(
+        b"",
+        &[
+          &b"5:filesld6:lengthi5e4:pathl2:..6:passwdeee4:name3:dir12:piece lengthi16384e6:pieces20:"[..],
+          &[1; 20],
+        ]
+        .concat(),
+      ),
+      vec![MetainfoError::FilePath(vec![
+        String::from("dir"),
+        String::from(".."),
+        String::from("passwd"),
+      ])],
+    ),
+    (
+      torrent(
+        b"",
+        &[
+          &b"6:lengthi9223372036854775807e4:name4:file12:piece lengthi16384e6:pieces20:"[..],
+          &[1; 20],
+        ]
+        .concat(),
+      ),
+      vec![MetainfoError::PieceCount {
+        expected: i64::MAX / 16384 + 1,
+        actual: 1,
+      }],
+    ),
+    (
+      torrent(
+        b"",
+        b"9:file treed1:ad0:d6:lengthi9223372036854775807eee1:bd0:d6:lengthi9223372036854775807eeee12:meta versioni2e4:name3:dir12:piece lengthi16384e",
+      ),
+      vec![MetainfoError::TotalLength],
+    ),
+    (
+      torrent(b"8:encoding10:ISO-8859-2", &single(b"16384", &[1; 60])),
+      vec![MetainfoError::Encoding(String::from("ISO-8859-2"))],
+    ),
+    (
+      torrent(b"8:encoding10:ISO-8859-2", &single(b"16384", &[1; 50])),
+      vec![
+        MetainfoError::Encoding(String::from("ISO-8859-2")),
+        MetainfoError::PiecesLength(50),
+      ],
+    ),
+  ];
+
+  for (torrent, metainfo_errors) in cases {
+    let put_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      common::make_multipart(
+        TestRequest::put().uri("/api/torrent/"),
+        vec![common::MultipartField {
+          name: b"torrent",
+          filename: b"invalid.torrent",
+          content: &torrent,
+          content_type: APPLICATION_XBITTORRENT,
+          boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+        }],
+      ),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(put_res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+      read_body_json::<Vec<MetainfoError>, _>(put_res).await,
+      metainfo_errors
+    );
+  }
+  // Rule ids and details are part of API.
+  assert_eq!(
+    serde_json::to_value(MetainfoError::PieceCount {
+      expected: 3,
+      actual: 2
+    })
+    .unwrap(),
+    json!({"rule": "piece_count", "details": {"expected": 3, "actual": 2}})
+  );
+  assert_eq!(
+    serde_json::to_value(MetainfoError::TotalLength).unwrap(),
+    json!({"rule": "total_length"})
+  );
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_put_torrent_canonical(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let info = [
+    &b"d6:lengthi5e4:name9:hello.txt12:piece lengthi16384e6:pieces20:"[..],
+    &[9; 20],
+    b"7:privatei0ee",
+  ]
+  .concat();
+  let torrent = [
+    &b"d8:announce27:http://foreign.org/announce13:announce-listll27:http://foreign.org/announceee"
+      [..],
+    b"13:creation datei1690000000e4:info",
+    &info,
+    b"5:nodesld4:node9:127.0.0.14:porti6881eeee",
+  ]
+  .concat();
+  let put_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    common::make_multipart(
+      TestRequest::put().uri("/api/torrent/"),
+      vec![common::MultipartField {
+        name: b"torrent",
+        filename: b"hello.torrent",
+        content: &torrent,
+        content_type: APPLICATION_XBITTORRENT,
+        boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+      }],
+    ),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(put_res.status(), StatusCode::OK);
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  assert_eq!(
+    torrent_dto.announce_url,
+    Some(String::from("http://127.0.0.1:6969/peer/announce"))
+  );
+
+  // Private flag is forced, so info hash differs from info hash of uploaded torrent.
+  let canonical_info = [&info[..info.len() - 3], b"1ee"].concat();
+  assert_ne!(
+    torrent_dto.info_hash,
+    InfoHash::<SHA1_LENGTH>(Sha1::digest(&info).into())
+  );
+  assert_eq!(
+    torrent_dto.info_hash,
+    InfoHash::<SHA1_LENGTH>(Sha1::digest(&canonical_info).into())
+  );
+
+  // Uploader can re-download tracker-ready torrent by new info hash.
+  let get_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}/raw", torrent_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+  let raw = read_body(get_res).await;
+  assert_eq!(TorrentFile::raw_info(&raw).unwrap(), &canonical_info[..]);
+  let torrent = TorrentFile::from_bencode(&raw).unwrap();
+  assert_eq!(
+    torrent.announce_url,
+    Some(format!(
+      "http://127.0.0.1:6969/peer/{}/announce",
+      common::passkey(&pool, user_dto.id).await
+    ))
+  );
+  assert_eq!(torrent.announce_list, None);
+  assert_eq!(torrent.nodes, None);
+  assert_eq!(torrent.info.private, Some(1));
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_put_torrent_unknown_keys(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, _, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  // Already canonical, with unknown keys in torrent, info and file dictionaries.
+  let info = [
+    &b"d5:filesld4:attr1:x6:lengthi5e4:pathl9:hello.txteee4:name5:hello"[..],
+    b"12:piece lengthi16384e6:pieces20:",
+    &[9; 20],
+    b"7:privatei1e9:publisher6:laguna6:source3:LGNe",
+  ]
+  .concat();
+  let torrent = [
+    &b"d8:announce35:http://127.0.0.1:6969/peer/announce13:creation datei1690000000e4:info"[..],
+    &info,
+    b"12:x_cross_seed10:mb-abcdef0e",
+  ]
+  .concat();
+  let put_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    common::make_multipart(
+      TestRequest::put().uri("/api/torrent/"),
+      vec![common::MultipartField {
+        name: b"torrent",
+        filename: b"hello.torrent",
+        content: &torrent,
+        content_type: APPLICATION_XBITTORRENT,
+        boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+      }],
+    ),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(put_res.status(), StatusCode::OK);
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+
+  // Unknown keys survive decode/encode, so info hash is same as of uploaded torrent.
+  assert_eq!(
+    torrent_dto.info_hash,
+    InfoHash::<SHA1_LENGTH>(Sha1::digest(&info).into())
+  );
+  let raw = sqlx::query_scalar::<_, Vec<u8>>("SELECT raw FROM \"Torrent\" WHERE info_hash = $1")
+    .bind(&torrent_dto.info_hash.0[..])
+    .fetch_one(&pool)
+    .await?;
+  assert_eq!(raw, torrent);
+
+  let get_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}/raw", torrent_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+  let raw = read_body(get_res).await;
+  assert_eq!(TorrentFile::raw_info(&raw).unwrap(), &info[..]);
+  let torrent = TorrentFile::from_bencode(&raw).unwrap();
+  assert_eq!(
+    torrent.extensions.keys().collect::<Vec<_>>(),
+    vec![&b"x_cross_seed".to_vec()]
+  );
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_get_torrent_v2(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let (torrent, _) = common::make_v2_torrent(true);
+  let put_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    common::make_multipart(
+      TestRequest::put().uri("/api/torrent/"),
+      vec![common::MultipartField {
+        name: b"torrent",
+        filename: b"hello.torrent",
+        content: &torrent,
+        content_type: APPLICATION_XBITTORRENT,
+        boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+      }],
+    ),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(put_res.status(), StatusCode::OK);
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  let info_hash_v2 = torrent_dto.info_hash_v2.clone().unwrap();
+
+  // v2 routes address torrent by its v2 info hash.
+  let get_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::get().uri(&format!("/api/torrent/v2/{}", info_hash_v2)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+  let torrent_dto_v2 = read_body_json::<TorrentDTO<SHA256_LENGTH>, _>(get_res).await;
+  assert_eq!(torrent_dto_v2.info_hash, info_hash_v2);
+  assert_eq!(torrent_dto_v2.files, torrent_dto.files);
+
+  let get_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/v2/{}/raw", info_hash_v2)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+
+  // Same announce URL, whichever info hash torrent was requested with.
+  let torrent = TorrentFile::from_bencode(&read_body(get_res).await).unwrap();
+  assert_eq!(
+    torrent.announce_url,
+    Some(format!(
+      "http://127.0.0.1:6969/peer/{}/announce",
+      common::passkey(&pool, user_dto.id).await
+    ))
+  );
+
+  Ok(())
+}
+
 #[sqlx::test(migrations = "../../migrations")]
 async fn test_patch_torrent(pool: PgPool) -> sqlx::Result<()> {
   let app = common::setup_test(&pool).await;
@@ -265,11 +702,11 @@ async fn test_patch_torrent(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
 
   assert_eq!(put_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
-  let expected_torrent_dto = TorrentDTO {
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  let expected_torrent_dto = TorrentDTO::<SHA1_LENGTH> {
     info_hash: torrent_dto.info_hash.clone(),
-    raw: include_bytes!("fixtures/webtorrent-fixtures/fixtures/leaves.torrent").to_vec(),
-    announce_url: None,
+    info_hash_v2: None,
+    announce_url: Some(String::from("http://127.0.0.1:6969/peer/announce")),
     length: 362017,
     file_name: String::from("Leaves of Grass by Walt Whitman.epub"),
     nfo: None,
@@ -285,6 +722,17 @@ async fn test_patch_torrent(pool: PgPool) -> sqlx::Result<()> {
     uploaded_by: user_dto.id,
     modded_at: None,
     modded_by: None,
+    deleted_at: None,
+    deleted_by: None,
+    delete_reason: None,
+    comment: None,
+    piece_length: 16384,
+    piece_count: 23,
+    files: vec![TorrentFileDTO {
+      path: vec![String::from("Leaves of Grass by Walt Whitman.epub")],
+      length: 362017,
+    }],
+    url_list: None,
   };
 
   assert_eq!(torrent_dto, expected_torrent_dto);
@@ -304,10 +752,16 @@ async fn test_patch_torrent(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
 
   assert_eq!(patch_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(patch_res).await;
-  let expected_torrent_dto = TorrentDTO {
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(patch_res).await;
+  assert!(torrent_dto.modded_at.is_some());
+  let expected_torrent_dto = TorrentDTO::<SHA1_LENGTH> {
     nfo: Some(String::from("New NFO")),
     genre: Some(Genre::Action),
+    modded_at: torrent_dto.modded_at,
+    modded_by: Some(user_dto.id),
+    deleted_at: None,
+    deleted_by: None,
+    delete_reason: None,
     ..expected_torrent_dto
   };
 
@@ -316,10 +770,162 @@ async fn test_patch_torrent(pool: PgPool) -> sqlx::Result<()> {
   Ok(())
 }
 
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_torrent_revisions(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_mod_user(&app, &pool).await;
+  let (_, normie_dto, normie_access_token, normie_refresh_token) = common::new_user(&app).await;
+  let (torrent, _) = common::make_v2_torrent(true);
+  let put_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    common::make_multipart(
+      TestRequest::put().uri("/api/torrent/"),
+      vec![common::MultipartField {
+        name: b"torrent",
+        filename: b"hello.torrent",
+        content: &torrent,
+        content_type: APPLICATION_XBITTORRENT,
+        boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+      }],
+    ),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(put_res.status(), StatusCode::OK);
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  let info_hash_v2 = torrent_dto.info_hash_v2.clone().unwrap();
+
+  for (nfo, genre) in [("First NFO", Genre::Action), ("Second NFO", Genre::Drama)] {
+    let patch_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::patch()
+        .uri(&format!("/api/torrent/{}", torrent_dto.info_hash))
+        .set_json(TorrentPatchDTO {
+          nfo: Some(String::from(nfo)),
+          genre: Some(genre),
+        }),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(patch_res.status(), StatusCode::OK);
+  }
+
+  // Revisions are newest first and carry values before and after each change.
+  let revisions_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::get().uri(&format!("/api/torrent/{}/revisions", torrent_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(revisions_res.status(), StatusCode::OK);
+  let revisions = read_body_json::<Vec<TorrentRevisionDTO<SHA1_LENGTH>>, _>(revisions_res).await;
+  assert_eq!(revisions.len(), 2);
+  assert_eq!(
+    revisions[0],
+    TorrentRevisionDTO::<SHA1_LENGTH> {
+      id: revisions[0].id,
+      info_hash: torrent_dto.info_hash.clone(),
+      old_nfo: Some(String::from("First NFO")),
+      new_nfo: Some(String::from("Second NFO")),
+      old_genre: Some(Genre::Action),
+      new_genre: Some(Genre::Drama),
+      modded_at: revisions[0].modded_at,
+      modded_by: Some(user_dto.id),
+    }
+  );
+  assert_eq!(revisions[1].old_nfo, None);
+  assert_eq!(revisions[1].new_nfo, Some(String::from("First NFO")));
+
+  // Only mods can see revisions.
+  let revisions_res = common::as_logged_in(
+    normie_access_token,
+    normie_refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}/revisions", torrent_dto.info_hash)),
+    &app,
+  )
+  .await;
+  assert!(revisions_res.is_err());
+
+  // Reverting first revision restores torrent as it was uploaded.
+  let revert_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::post().uri(&format!(
+      "/api/torrent/v2/{}/revisions/{}/revert",
+      info_hash_v2, revisions[1].id
+    )),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(revert_res.status(), StatusCode::OK);
+  let reverted_dto = read_body_json::<TorrentDTO<SHA256_LENGTH>, _>(revert_res).await;
+  assert_eq!(reverted_dto.info_hash, info_hash_v2);
+  assert_eq!(reverted_dto.nfo, None);
+  assert_eq!(reverted_dto.genre, None);
+  assert_eq!(reverted_dto.modded_by, Some(user_dto.id));
+
+  // Revert is recorded as well.
+  let revisions_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::get().uri(&format!("/api/torrent/v2/{}/revisions", info_hash_v2)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(revisions_res.status(), StatusCode::OK);
+  let revisions = read_body_json::<Vec<TorrentRevisionDTO<SHA256_LENGTH>>, _>(revisions_res).await;
+  assert_eq!(revisions.len(), 3);
+  assert_eq!(revisions[0].info_hash, info_hash_v2);
+  assert_eq!(revisions[0].old_nfo, Some(String::from("Second NFO")));
+  assert_eq!(revisions[0].new_nfo, None);
+
+  let revert_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::post().uri(&format!(
+      "/api/torrent/{}/revisions/{}/revert",
+      torrent_dto.info_hash,
+      Uuid::new_v4()
+    )),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(revert_res.status(), StatusCode::BAD_REQUEST);
+
+  // Revisions survive deletion of moderator's account, without moderator.
+  sqlx::query("UPDATE \"Torrent\" SET uploaded_by = $1 WHERE info_hash = $2")
+    .bind(normie_dto.id)
+    .bind(&torrent_dto.info_hash.0[..])
+    .execute(&pool)
+    .await?;
+  sqlx::query("DELETE FROM \"User\" WHERE id = $1")
+    .bind(user_dto.id)
+    .execute(&pool)
+    .await?;
+  let modded_by = sqlx::query_scalar::<_, Option<Uuid>>(
+    "SELECT modded_by FROM \"TorrentRevision\" WHERE info_hash = $1",
+  )
+  .bind(&torrent_dto.info_hash.0[..])
+  .fetch_all(&pool)
+  .await?;
+  assert_eq!(modded_by, vec![None; 3]);
+
+  Ok(())
+}
+
 #[sqlx::test(migrations = "../../migrations")]
 async fn test_delete_torrent(pool: PgPool) -> sqlx::Result<()> {
   let app = common::setup_test(&pool).await;
-  let (_, _, access_token, refresh_token) = common::new_mod_user(&app, &pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_mod_user(&app, &pool).await;
   let put_res = common::as_logged_in(
     access_token.clone(),
     refresh_token.clone(),
@@ -338,16 +944,124 @@ async fn test_delete_torrent(pool: PgPool) -> sqlx::Result<()> {
   .await
   .unwrap();
   assert_eq!(put_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
-  let delete_res = common::as_logged_in(
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+
+  let trash = |reason: &str| {
+    common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::delete()
+        .uri(&format!("/api/torrent/{}", torrent_dto.info_hash))
+        .set_json(TorrentTrashDTO {
+          reason: String::from(reason),
+        }),
+      &app,
+    )
+  };
+  let search = || {
+    common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::get().uri("/api/torrent/"),
+      &app,
+    )
+  };
+
+  // Reason is required.
+  let trash_res = trash("").await.unwrap();
+  assert_eq!(trash_res.status(), StatusCode::BAD_REQUEST);
+
+  let trash_res = trash("Kršitev avtorskih pravic").await.unwrap();
+  assert_eq!(trash_res.status(), StatusCode::OK);
+  let trashed_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(trash_res).await;
+  assert!(trashed_dto.deleted_at.is_some());
+  assert_eq!(trashed_dto.deleted_by, Some(user_dto.id));
+  assert_eq!(
+    trashed_dto.delete_reason.as_deref(),
+    Some("Kršitev avtorskih pravic")
+  );
+
+  // Trashed torrents are hidden from listings and can't be trashed again.
+  let page = read_body_json::<TorrentPageDTO, _>(search().await.unwrap()).await;
+  assert!(page.torrents.is_empty());
+  let trash_res = trash("Dvojnik").await.unwrap();
+  assert_eq!(trash_res.status(), StatusCode::BAD_REQUEST);
+
+  // Trashed torrents can't be fetched, downloaded or edited either.
+  for uri in [
+    format!("/api/torrent/{}", torrent_dto.info_hash),
+    format!("/api/torrent/{}/raw", torrent_dto.info_hash),
+    format!("/api/torrent/{}/files", torrent_dto.info_hash),
+    format!("/api/torrent/{}/magnet", torrent_dto.info_hash),
+  ] {
+    let get_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::get().uri(&uri),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(get_res.status(), StatusCode::BAD_REQUEST);
+  }
+  let patch_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::patch()
+      .uri(&format!("/api/torrent/{}", torrent_dto.info_hash))
+      .set_json(TorrentPatchDTO {
+        nfo: Some(String::from("NFO")),
+        genre: None,
+      }),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(patch_res.status(), StatusCode::BAD_REQUEST);
+
+  let restore_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::post().uri(&format!("/api/torrent/{}/restore", torrent_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(restore_res.status(), StatusCode::OK);
+  let restored_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(restore_res).await;
+  assert_eq!(restored_dto, torrent_dto);
+  let page = read_body_json::<TorrentPageDTO, _>(search().await.unwrap()).await;
+  assert_eq!(page.torrents.len(), 1);
+
+  // Only Admins can purge.
+  let purge_res = common::as_logged_in(
     access_token,
     refresh_token,
-    TestRequest::delete().uri(&format!("/api/torrent/{}", torrent_dto.info_hash)),
+    TestRequest::delete().uri(&format!("/api/torrent/{}/purge", torrent_dto.info_hash)),
+    &app,
+  )
+  .await;
+  assert!(purge_res.is_err());
+
+  let (_, _, access_token, refresh_token) = common::new_admin_user(&app, &pool).await;
+  let purge_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::delete().uri(&format!("/api/torrent/{}/purge", torrent_dto.info_hash)),
     &app,
   )
   .await
   .unwrap();
-  assert_eq!(delete_res.status(), StatusCode::OK);
+  assert_eq!(purge_res.status(), StatusCode::OK);
+  let get_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}", torrent_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::BAD_REQUEST);
   Ok(())
 }
 
@@ -374,7 +1088,7 @@ async fn test_get_torrent_swarm(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
   assert_eq!(put_res.status(), StatusCode::OK);
 
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
 
   let get_res = common::as_logged_in(
     access_token,
@@ -385,7 +1099,296 @@ async fn test_get_torrent_swarm(pool: PgPool) -> sqlx::Result<()> {
   .await
   .unwrap();
   assert_eq!(get_res.status(), StatusCode::OK);
-  assert_eq!(read_body_json::<Vec<PeerDTO>, _>(get_res).await, vec![]);
+  assert_eq!(
+    read_body_json::<Vec<PeerDTO<SHA1_LENGTH>>, _>(get_res).await,
+    vec![]
+  );
+
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_search_torrents(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let (hello, _) = common::make_v2_torrent(true);
+  let mut info_hashes = Vec::new();
+  for (seed_count, torrent) in [
+    (
+      5,
+      &include_bytes!("fixtures/webtorrent-fixtures/fixtures/bunny.torrent")[..],
+    ),
+    (
+      10,
+      &include_bytes!("fixtures/webtorrent-fixtures/fixtures/leaves.torrent")[..],
+    ),
+    (1, &hello[..]),
+  ] {
+    let put_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      common::make_multipart(
+        TestRequest::put().uri("/api/torrent/"),
+        vec![common::MultipartField {
+          name: b"torrent",
+          filename: b"upload.torrent",
+          content: torrent,
+          content_type: APPLICATION_XBITTORRENT,
+          boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+        }],
+      ),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(put_res.status(), StatusCode::OK);
+    let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+    sqlx::query("UPDATE \"Torrent\" SET seed_count = $1 WHERE info_hash = $2")
+      .bind(seed_count)
+      .bind(torrent_dto.info_hash.clone())
+      .execute(&pool)
+      .await?;
+    info_hashes.push(torrent_dto.info_hash);
+  }
+  sqlx::query("UPDATE \"Torrent\" SET genre = 'Drama' WHERE info_hash = $1")
+    .bind(info_hashes[1].clone())
+    .execute(&pool)
+    .await?;
+
+  let search = |query: &str| {
+    common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::get().uri(&format!("/api/torrent/?{}", query)),
+      &app,
+    )
+  };
+
+  // Words in file names are separated by dots.
+  let search_res = search("q=whitman").await.unwrap();
+  assert_eq!(search_res.status(), StatusCode::OK);
+  let page = read_body_json::<TorrentPageDTO, _>(search_res).await;
+  assert_eq!(page.torrents.len(), 1);
+  assert_eq!(page.torrents[0].info_hash, info_hashes[1]);
+  assert_eq!(page.torrents[0].uploaded_by, user_dto.id);
+  assert_eq!(page.next_cursor, None);
+
+  let page = read_body_json::<TorrentPageDTO, _>(search("genre=Drama").await.unwrap()).await;
+  assert_eq!(page.torrents.len(), 1);
+  assert_eq!(page.torrents[0].info_hash, info_hashes[1]);
+
+  // Most seeded first, paged by cursor.
+  let page =
+    read_body_json::<TorrentPageDTO, _>(search("sort=seeders&limit=2").await.unwrap()).await;
+  assert_eq!(
+    page
+      .torrents
+      .iter()
+      .map(|torrent| torrent.seed_count)
+      .collect::<Vec<_>>(),
+    vec![10, 5]
+  );
+  let cursor = page.next_cursor.unwrap();
+  let page = read_body_json::<TorrentPageDTO, _>(
+    search(&format!("sort=seeders&limit=2&cursor={}", cursor))
+      .await
+      .unwrap(),
+  )
+  .await;
+  assert_eq!(page.torrents.len(), 1);
+  assert_eq!(page.torrents[0].info_hash, info_hashes[2]);
+  assert_eq!(page.next_cursor, None);
+
+  let page =
+    read_body_json::<TorrentPageDTO, _>(search("sort=seeders&order=asc&limit=1").await.unwrap())
+      .await;
+  assert_eq!(page.torrents[0].info_hash, info_hashes[2]);
+
+  let search_res = search("limit=0").await.unwrap();
+  assert_eq!(search_res.status(), StatusCode::BAD_REQUEST);
+  let search_res = search("cursor=nonsense").await.unwrap();
+  assert_eq!(search_res.status(), StatusCode::BAD_REQUEST);
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_torrent_files(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, _, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  // album/{cd1/01.flac, cover.jpg}
+  let album = [
+    &b"d13:creation datei1690000000e4:infod5:filesl"[..],
+    b"d6:lengthi30000e4:pathl3:cd17:01.flaceed6:lengthi12e4:pathl9:cover.jpgeee",
+    b"4:name5:album12:piece lengthi16384e6:pieces40:",
+    &[3; 40],
+    b"ee",
+  ]
+  .concat();
+  let (hello, _) = common::make_v2_torrent(false);
+
+  let mut files = Vec::new();
+  for torrent in [album, hello] {
+    let put_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      common::make_multipart(
+        TestRequest::put().uri("/api/torrent/"),
+        vec![common::MultipartField {
+          name: b"torrent",
+          filename: b"upload.torrent",
+          content: &torrent,
+          content_type: APPLICATION_XBITTORRENT,
+          boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+        }],
+      ),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(put_res.status(), StatusCode::OK);
+    let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+    let files_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::get().uri(&format!("/api/torrent/{}/files", torrent_dto.info_hash)),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(files_res.status(), StatusCode::OK);
+    files.push(read_body_json::<TorrentFilesDTO, _>(files_res).await);
+  }
+
+  let file = |name: &str, length, pieces_root| FileNodeDTO {
+    name: String::from(name),
+    length,
+    pieces_root,
+    children: None,
+  };
+  assert_eq!(
+    files[0],
+    TorrentFilesDTO {
+      length: 30012,
+      file_count: 2,
+      root: FileNodeDTO {
+        name: String::from("album"),
+        length: 30012,
+        pieces_root: None,
+        children: Some(vec![
+          FileNodeDTO {
+            name: String::from("cd1"),
+            length: 30000,
+            pieces_root: None,
+            children: Some(vec![file("01.flac", 30000, None)]),
+          },
+          file("cover.jpg", 12, None),
+        ]),
+      },
+    }
+  );
+  // v2 files carry their merkle root from BEP 52 file tree.
+  assert_eq!(
+    files[1],
+    TorrentFilesDTO {
+      length: 5,
+      file_count: 1,
+      root: file("hello.txt", 5, Some(vec![7; 32])),
+    }
+  );
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_torrent_magnet(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let (hybrid, _) = common::make_v2_torrent(true);
+  let bunny = include_bytes!("fixtures/webtorrent-fixtures/fixtures/bunny.torrent").to_vec();
+  let tr = format!(
+    "http%3A%2F%2F127.0.0.1%3A6969%2Fpeer%2F{}%2Fannounce",
+    common::passkey(&pool, user_dto.id).await
+  );
+
+  let mut torrent_dtos = Vec::new();
+  for torrent in [hybrid, bunny] {
+    let put_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      common::make_multipart(
+        TestRequest::put().uri("/api/torrent/"),
+        vec![common::MultipartField {
+          name: b"torrent",
+          filename: b"upload.torrent",
+          content: &torrent,
+          content_type: APPLICATION_XBITTORRENT,
+          boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+        }],
+      ),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(put_res.status(), StatusCode::OK);
+    torrent_dtos.push(read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await);
+  }
+  let hybrid_dto = &torrent_dtos[0];
+  let bunny_dto = &torrent_dtos[1];
+  let info_hash_v2 = hybrid_dto.info_hash_v2.clone().unwrap();
+
+  // Hybrid torrent has both exact topics, whichever info hash it was requested with.
+  let hybrid_magnet = format!(
+    "magnet:?xt=urn:btih:{}&xt=urn:btmh:1220{}&dn=hello.txt&xl=5&tr={}",
+    hybrid_dto.info_hash, info_hash_v2, tr
+  );
+  for uri in [
+    format!("/api/torrent/{}/magnet", hybrid_dto.info_hash),
+    format!("/api/torrent/v2/{}/magnet", info_hash_v2),
+  ] {
+    let magnet_res = common::as_logged_in(
+      access_token.clone(),
+      refresh_token.clone(),
+      TestRequest::get().uri(&uri),
+      &app,
+    )
+    .await
+    .unwrap();
+    assert_eq!(magnet_res.status(), StatusCode::OK);
+    assert_eq!(
+      read_body_json::<MagnetDTO, _>(magnet_res).await,
+      MagnetDTO {
+        magnet: hybrid_magnet.clone()
+      }
+    );
+  }
+
+  let magnet_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    TestRequest::get().uri(&format!("/api/torrent/{}/magnet", bunny_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(magnet_res.status(), StatusCode::OK);
+  assert_eq!(
+    read_body_json::<MagnetDTO, _>(magnet_res).await,
+    MagnetDTO {
+      magnet: format!(
+        "magnet:?xt=urn:btih:{}&dn=bbb_sunflower_1080p_30fps_stereo_abl.mp4&xl=434839491&tr={}",
+        bunny_dto.info_hash, tr
+      )
+    }
+  );
+
+  let magnet_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}/magnet", "00".repeat(SHA1_LENGTH))),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(magnet_res.status(), StatusCode::BAD_REQUEST);
 
   Ok(())
 }
@@ -0,0 +1,224 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use actix_http::StatusCode;
+use actix_web::rt::net::UdpSocket;
+use actix_web::rt::time::timeout;
+use actix_web::test::{read_body_json, TestRequest};
+
+use laguna_backend_api::udp::udp_tracker;
+use laguna_backend_dto::torrent::TorrentDTO;
+use laguna_backend_middleware::mime::APPLICATION_XBITTORRENT;
+use laguna_backend_setup::get_settings;
+use laguna_backend_tracker_common::info_hash::SHA1_LENGTH;
+
+use sqlx::PgPool;
+use tokio::task::LocalSet;
+
+mod common;
+
+const PROTOCOL_ID: i64 = 0x41727101980;
+
+async fn start_udp_tracker(pool: &PgPool) -> SocketAddr {
+  let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+  let addr = socket.local_addr().unwrap();
+  actix_web::rt::spawn(udp_tracker(
+    socket,
+    pool.clone(),
+    get_settings().application.tracker,
+  ));
+  addr
+}
+
+async fn udp_client(tracker_addr: SocketAddr) -> UdpSocket {
+  let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+  client.connect(tracker_addr).await.unwrap();
+  client
+}
+
+async fn exchange(client: &UdpSocket, packet: &[u8]) -> Vec<u8> {
+  client.send(packet).await.unwrap();
+  let mut buf = [0; 1496];
+  let len = timeout(Duration::from_secs(5), client.recv(&mut buf))
+    .await
+    .expect("UDP tracker did not respond")
+    .unwrap();
+  buf[..len].to_vec()
+}
+
+async fn connect(client: &UdpSocket) -> i64 {
+  let response = exchange(
+    client,
+    &[
+      &PROTOCOL_ID.to_be_bytes()[..],
+      &0_i32.to_be_bytes(),
+      &7_i32.to_be_bytes(),
+    ]
+    .concat(),
+  )
+  .await;
+  assert_eq!(response.len(), 16);
+  assert_eq!(&response[..4], &0_i32.to_be_bytes());
+  assert_eq!(&response[4..8], &7_i32.to_be_bytes());
+  i64::from_be_bytes(response[8..16].try_into().unwrap())
+}
+
+fn announce_packet(
+  connection_id: i64,
+  info_hash: &[u8; SHA1_LENGTH],
+  event: i32,
+  url_data: &[u8],
+) -> Vec<u8> {
+  [
+    &connection_id.to_be_bytes()[..],
+    &1_i32.to_be_bytes(),
+    &8_i32.to_be_bytes(),
+    info_hash,
+    b"-qB4550-abcdefghijkl",
+    &0_i64.to_be_bytes(),       // downloaded
+    &1000_i64.to_be_bytes(),    // left
+    &0_i64.to_be_bytes(),       // uploaded
+    &event.to_be_bytes(),       // event
+    &0_u32.to_be_bytes(),       // ip
+    &42_u32.to_be_bytes(),      // key
+    &(-1_i32).to_be_bytes(),    // num_want
+    &6881_u16.to_be_bytes(),    // port
+    &[2, url_data.len() as u8], // BEP 41 URLData
+    url_data,
+  ]
+  .concat()
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_udp_connect(pool: PgPool) -> sqlx::Result<()> {
+  LocalSet::new()
+    .run_until(async {
+      let tracker_addr = start_udp_tracker(&pool).await;
+      let client = udp_client(tracker_addr).await;
+      let connection_id = connect(&client).await;
+
+      // Connection id issued to another address is rejected.
+      let other_client = udp_client(tracker_addr).await;
+      let response = exchange(
+        &other_client,
+        &announce_packet(connection_id, &[0; SHA1_LENGTH], 2, b"/peer/announce"),
+      )
+      .await;
+      assert_eq!(&response[..4], &3_i32.to_be_bytes());
+      assert_eq!(&response[4..8], &8_i32.to_be_bytes());
+    })
+    .await;
+  Ok(())
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_udp_announce_and_scrape(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let put_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    common::make_multipart(
+      TestRequest::put().uri("/api/torrent/"),
+      vec![common::MultipartField {
+        name: b"torrent",
+        filename: b"bunny.torrent",
+        content: include_bytes!("fixtures/webtorrent-fixtures/fixtures/bunny.torrent"),
+        content_type: APPLICATION_XBITTORRENT,
+        boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+      }],
+    ),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(put_res.status(), StatusCode::OK);
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+
+  let get_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}/raw", torrent_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+
+  let passkey = common::passkey(&pool, user_dto.id).await;
+
+  LocalSet::new()
+    .run_until(async {
+      let tracker_addr = start_udp_tracker(&pool).await;
+      let client = udp_client(tracker_addr).await;
+      let connection_id = connect(&client).await;
+
+      // Announce without passkey is an error.
+      let response = exchange(
+        &client,
+        &announce_packet(
+          connection_id,
+          &torrent_dto.info_hash.0,
+          2,
+          b"/peer/announce",
+        ),
+      )
+      .await;
+      assert_eq!(&response[..4], &3_i32.to_be_bytes());
+
+      let url_data = format!("/peer/{}/announce", passkey);
+      let response = exchange(
+        &client,
+        &announce_packet(
+          connection_id,
+          &torrent_dto.info_hash.0,
+          2,
+          url_data.as_bytes(),
+        ),
+      )
+      .await;
+      // action, transaction_id, interval, leechers, seeders and no peers
+      assert_eq!(response.len(), 20);
+      assert_eq!(&response[..4], &1_i32.to_be_bytes());
+      assert_eq!(&response[4..8], &8_i32.to_be_bytes());
+
+      let response = exchange(
+        &client,
+        &[
+          &connection_id.to_be_bytes()[..],
+          &2_i32.to_be_bytes(),
+          &9_i32.to_be_bytes(),
+          &torrent_dto.info_hash.0,
+          &[0; SHA1_LENGTH],
+        ]
+        .concat(),
+      )
+      .await;
+      assert_eq!(
+        response,
+        [
+          &2_i32.to_be_bytes()[..],
+          &9_i32.to_be_bytes(),
+          // seeders, completed, leechers
+          &0_i32.to_be_bytes(),
+          &0_i32.to_be_bytes(),
+          &1_i32.to_be_bytes(),
+          // unknown torrent
+          &[0; 12],
+        ]
+        .concat()
+      );
+    })
+    .await;
+
+  let peer_count = sqlx::query_scalar::<_, i64>(
+    "SELECT COUNT(*) FROM \"Peer\" WHERE info_hash = $1 AND created_by = $2",
+  )
+  .bind(torrent_dto.info_hash.clone())
+  .bind(user_dto.id)
+  .fetch_one(&pool)
+  .await?;
+  assert_eq!(peer_count, 1);
+
+  Ok(())
+}
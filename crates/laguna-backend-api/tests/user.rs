@@ -7,11 +7,13 @@ use chrono::{DateTime, Utc};
 
 use laguna_backend_dto::{
   role::RoleChangeDTO,
-  torrent::TorrentDTO,
-  user::{UserDTO, UserPatchDTO},
+  torrent::{TorrentDTO, TorrentFileDTO},
+  totp::{LoginChallengeDTO, LoginTotpDTO},
+  user::{PasskeyDTO, UserDTO, UserPatchDTO},
 };
 use laguna_backend_middleware::mime::APPLICATION_XBITTORRENT;
 use laguna_backend_model::{role::Role, speedlevel::SpeedLevel};
+use laguna_backend_tracker_common::info_hash::SHA1_LENGTH;
 use sqlx::PgPool;
 
 mod common;
@@ -66,6 +68,29 @@ async fn test_delete_me(pool: PgPool) -> sqlx::Result<()> {
   Ok(())
 }
 
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_user_me_passkey_reset(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_user(&app).await;
+  let old_passkey = common::passkey(&pool, user_dto.id).await;
+  let post_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::post().uri("/api/user/me/passkey"),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(post_res.status(), StatusCode::OK);
+  let passkey_dto = read_body_json::<PasskeyDTO, _>(post_res).await;
+  assert_ne!(passkey_dto.passkey, old_passkey);
+  assert_eq!(
+    passkey_dto.passkey,
+    common::passkey(&pool, user_dto.id).await
+  );
+  Ok(())
+}
+
 #[sqlx::test(migrations = "../../migrations")]
 async fn test_user_patch(pool: PgPool) -> sqlx::Result<()> {
   let app = common::setup_test(&pool).await;
@@ -204,11 +229,11 @@ async fn test_user_torrents_get(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
 
   assert_eq!(put_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
-  let expected_torrent_dto_1 = TorrentDTO {
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  let expected_torrent_dto_1 = TorrentDTO::<SHA1_LENGTH> {
     info_hash: torrent_dto.info_hash.clone(),
-    raw: include_bytes!("fixtures/webtorrent-fixtures/fixtures/leaves.torrent").to_vec(),
-    announce_url: None,
+    info_hash_v2: None,
+    announce_url: Some(String::from("http://127.0.0.1:6969/peer/announce")),
     length: 362017,
     file_name: String::from("Leaves of Grass by Walt Whitman.epub"),
     nfo: None,
@@ -224,6 +249,17 @@ async fn test_user_torrents_get(pool: PgPool) -> sqlx::Result<()> {
     uploaded_by: user_dto.id,
     modded_at: None,
     modded_by: None,
+    deleted_at: None,
+    deleted_by: None,
+    delete_reason: None,
+    comment: None,
+    piece_length: 16384,
+    piece_count: 23,
+    files: vec![TorrentFileDTO {
+      path: vec![String::from("Leaves of Grass by Walt Whitman.epub")],
+      length: 362017,
+    }],
+    url_list: None,
   };
 
   assert_eq!(torrent_dto, expected_torrent_dto_1);
@@ -247,11 +283,11 @@ async fn test_user_torrents_get(pool: PgPool) -> sqlx::Result<()> {
   .unwrap();
 
   assert_eq!(put_res.status(), StatusCode::OK);
-  let torrent_dto = read_body_json::<TorrentDTO, _>(put_res).await;
-  let expected_torrent_dto_2 = TorrentDTO {
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+  let expected_torrent_dto_2 = TorrentDTO::<SHA1_LENGTH> {
     info_hash: torrent_dto.info_hash.clone(),
-    raw: include_bytes!("fixtures/webtorrent-fixtures/fixtures/bunny.torrent").to_vec(),
-    announce_url: None,
+    info_hash_v2: None,
+    announce_url: Some(String::from("http://127.0.0.1:6969/peer/announce")),
     length: 434839491,
     file_name: String::from("bbb_sunflower_1080p_30fps_stereo_abl.mp4"),
     nfo: None,
@@ -267,6 +303,17 @@ async fn test_user_torrents_get(pool: PgPool) -> sqlx::Result<()> {
     uploaded_by: user_dto.id,
     modded_at: None,
     modded_by: None,
+    deleted_at: None,
+    deleted_by: None,
+    delete_reason: None,
+    comment: None,
+    piece_length: 262144,
+    piece_count: 1659,
+    files: vec![TorrentFileDTO {
+      path: vec![String::from("bbb_sunflower_1080p_30fps_stereo_abl.mp4")],
+      length: 434839491,
+    }],
+    url_list: None,
   };
   assert_eq!(torrent_dto, expected_torrent_dto_2);
 
@@ -281,7 +328,7 @@ async fn test_user_torrents_get(pool: PgPool) -> sqlx::Result<()> {
 
   assert_eq!(get_torrents_res.status(), StatusCode::OK);
 
-  let torrents = read_body_json::<Vec<TorrentDTO>, _>(get_torrents_res).await;
+  let torrents = read_body_json::<Vec<TorrentDTO<SHA1_LENGTH>>, _>(get_torrents_res).await;
   assert_eq!(torrents.len(), 2);
 
   assert_eq!(
@@ -490,9 +537,23 @@ async fn test_user_role_change_admin_to_mod_by_admin(pool: PgPool) -> sqlx::Resu
 
   assert_eq!(role_change_res.status(), StatusCode::OK);
 
-  // Changed user logs in and looks at their role
-  let (mod_dto, mod_access_token, mod_refresh_token) =
-    common::login_user_safe(admin_register_dto.into(), &app).await;
+  // Changed user logs in and looks at their role, TOTP enrolled as admin stays enabled.
+  let login_res = common::login_user(admin_register_dto.into(), &app).await;
+  assert_eq!(login_res.status(), StatusCode::ACCEPTED);
+  let login_challenge_dto = read_body_json::<LoginChallengeDTO, _>(login_res).await;
+  let secret = sqlx::query_scalar::<_, String>("SELECT secret FROM \"Totp\" WHERE user_id = $1")
+    .bind(admin_dto.id)
+    .fetch_one(&pool)
+    .await?;
+  let (mod_dto, mod_access_token, mod_refresh_token) = common::login_totp_safe(
+    LoginTotpDTO {
+      token: login_challenge_dto.token,
+      // Code of current step was used for enrollment.
+      code: common::totp_code(&secret, 1),
+    },
+    &app,
+  )
+  .await;
 
   assert_eq!(mod_dto.role, Role::Mod);
   assert_ne!(mod_access_token, admin_2_access_token);
@@ -500,3 +561,60 @@ async fn test_user_role_change_admin_to_mod_by_admin(pool: PgPool) -> sqlx::Resu
 
   Ok(())
 }
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_user_ban(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, _, mod_access_token, mod_refresh_token) = common::new_mod_user(&app, &pool).await;
+  let (_, admin_dto, _, _) = common::new_admin_user(&app, &pool).await;
+  let (register_dto, normie_dto, normie_access_token, normie_refresh_token) =
+    common::new_user(&app).await;
+  let ban = |req: TestRequest| {
+    common::as_logged_in(
+      mod_access_token.clone(),
+      mod_refresh_token.clone(),
+      req,
+      &app,
+    )
+  };
+
+  // Normies can't ban.
+  let ban_res = common::as_logged_in(
+    normie_access_token,
+    normie_refresh_token.clone(),
+    TestRequest::post().uri(&format!("/api/user/{}/ban", admin_dto.id)),
+    &app,
+  )
+  .await;
+  assert!(ban_res.is_err());
+  // Mods can't ban admins.
+  let ban_res = ban(TestRequest::post().uri(&format!("/api/user/{}/ban", admin_dto.id)))
+    .await
+    .unwrap();
+  assert_eq!(ban_res.status(), StatusCode::FORBIDDEN);
+
+  let ban_res = ban(TestRequest::post().uri(&format!("/api/user/{}/ban", normie_dto.id)))
+    .await
+    .unwrap();
+  assert_eq!(ban_res.status(), StatusCode::OK);
+  assert!(!read_body_json::<UserDTO, _>(ban_res).await.is_enabled);
+
+  // Banned user's sessions are revoked and it can't log in.
+  let get_res = common::as_refreshing(
+    normie_refresh_token,
+    TestRequest::get().uri("/api/user/me"),
+    &app,
+  )
+  .await;
+  assert!(get_res.is_err());
+  let login_res = common::login_user(register_dto.clone().into(), &app).await;
+  assert_eq!(login_res.status(), StatusCode::FORBIDDEN);
+
+  let unban_res = ban(TestRequest::delete().uri(&format!("/api/user/{}/ban", normie_dto.id)))
+    .await
+    .unwrap();
+  assert_eq!(unban_res.status(), StatusCode::OK);
+  assert!(read_body_json::<UserDTO, _>(unban_res).await.is_enabled);
+  common::login_user_safe(register_dto.into(), &app).await;
+  Ok(())
+}
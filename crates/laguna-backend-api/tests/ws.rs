@@ -0,0 +1,298 @@
+use std::time::Duration;
+
+use actix_http::ws::{Frame, Message, ProtocolError};
+use actix_http::StatusCode;
+use actix_web::rt::time::timeout;
+use actix_web::test::{read_body_json, TestRequest};
+use actix_web::web;
+
+use futures::{SinkExt, Stream, StreamExt};
+
+use laguna_backend_api::ws::WsSwarms;
+use laguna_backend_dto::torrent::TorrentDTO;
+use laguna_backend_middleware::mime::APPLICATION_XBITTORRENT;
+use laguna_backend_model::consts::SCRAPE_INFO_HASH_COUNT_MAX;
+use laguna_backend_setup::setup;
+use laguna_backend_tracker::ws::binary::to_binary_string;
+use laguna_backend_tracker_common::info_hash::SHA1_LENGTH;
+
+use serde_json::{json, Value};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use tokio::task::LocalSet;
+
+mod common;
+
+async fn recv_json<S>(client: &mut S) -> Value
+where
+  S: Stream<Item = Result<Frame, ProtocolError>> + Unpin,
+{
+  loop {
+    let frame = timeout(Duration::from_secs(5), client.next())
+      .await
+      .expect("WebSocket tracker did not respond")
+      .expect("WebSocket closed")
+      .unwrap();
+    if let Frame::Text(text) = frame {
+      return serde_json::from_slice(&text).unwrap();
+    }
+  }
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn test_ws_announce_offer_answer_scrape(pool: PgPool) -> sqlx::Result<()> {
+  let app = common::setup_test(&pool).await;
+  let (_, user_dto, access_token, refresh_token) = common::new_verified_user(&app, &pool).await;
+  let put_res = common::as_logged_in(
+    access_token.clone(),
+    refresh_token.clone(),
+    common::make_multipart(
+      TestRequest::put().uri("/api/torrent/"),
+      vec![common::MultipartField {
+        name: b"torrent",
+        filename: b"bunny.torrent",
+        content: include_bytes!("fixtures/webtorrent-fixtures/fixtures/bunny.torrent"),
+        content_type: APPLICATION_XBITTORRENT,
+        boundary: b"abbc761f78ff4d7cb7573b5a23f96ef0",
+      }],
+    ),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(put_res.status(), StatusCode::OK);
+  let torrent_dto = read_body_json::<TorrentDTO<SHA1_LENGTH>, _>(put_res).await;
+
+  let get_res = common::as_logged_in(
+    access_token,
+    refresh_token,
+    TestRequest::get().uri(&format!("/api/torrent/{}/raw", torrent_dto.info_hash)),
+    &app,
+  )
+  .await
+  .unwrap();
+  assert_eq!(get_res.status(), StatusCode::OK);
+
+  let passkey = common::passkey(&pool, user_dto.id).await;
+
+  LocalSet::new()
+    .run_until(async {
+      // Test server runs on its own runtime, so it needs its own pool.
+      let connect_options = (*pool.connect_options()).clone();
+      let swarms = web::Data::new(WsSwarms::default());
+      let mut srv = actix_test::start(move || {
+        setup()
+          .app_data(web::Data::new(
+            PgPoolOptions::new().connect_lazy_with(connect_options.clone()),
+          ))
+          .app_data(swarms.clone())
+      });
+
+      // Unknown passkey is rejected before upgrade.
+      assert!(srv
+        .ws_at(&format!("/peer/{}/ws", "00".repeat(16)))
+        .await
+        .is_err());
+
+      let info_hash = to_binary_string(&torrent_dto.info_hash.0);
+      let peer_a = "-WW0105-aaaaaaaaaaaa";
+      let peer_b = "-WW0105-bbbbbbbbbbbb";
+      let offer_id = "cccccccccccccccccccc";
+
+      let mut client_a = srv.ws_at(&format!("/peer/{}/ws", passkey)).await.unwrap();
+      let mut client_b = srv.ws_at(&format!("/peer/{}/ws", passkey)).await.unwrap();
+
+      client_a
+        .send(Message::Text(
+          json!({
+            "action": "announce",
+            "info_hash": info_hash,
+            "peer_id": peer_a,
+            "uploaded": 0,
+            "downloaded": 0,
+            "left": 0,
+            "event": "started",
+            "numwant": 5,
+            "offers": [],
+          })
+          .to_string()
+          .into(),
+        ))
+        .await
+        .unwrap();
+      let response = recv_json(&mut client_a).await;
+      assert_eq!(response["action"], "announce");
+      assert_eq!(response["info_hash"], info_hash);
+      assert_eq!(response["complete"], 0);
+      assert_eq!(response["incomplete"], 0);
+
+      client_b
+        .send(Message::Text(
+          json!({
+            "action": "announce",
+            "info_hash": info_hash,
+            "peer_id": peer_b,
+            "uploaded": 0,
+            "downloaded": 0,
+            "left": 1000,
+            "event": "started",
+            "numwant": 5,
+            "offers": [{ "offer": { "type": "offer", "sdp": "offer-sdp" }, "offer_id": offer_id }],
+          })
+          .to_string()
+          .into(),
+        ))
+        .await
+        .unwrap();
+      let response = recv_json(&mut client_b).await;
+      assert_eq!(response["action"], "announce");
+      assert_eq!(response["complete"], 1);
+      assert_eq!(response["incomplete"], 0);
+
+      // Offer of B is relayed to A.
+      let offer = recv_json(&mut client_a).await;
+      assert_eq!(offer["peer_id"], peer_b);
+      assert_eq!(offer["offer_id"], offer_id);
+      assert_eq!(offer["offer"]["sdp"], "offer-sdp");
+
+      // Answer of A is relayed back to B.
+      client_a
+        .send(Message::Text(
+          json!({
+            "action": "announce",
+            "info_hash": info_hash,
+            "peer_id": peer_a,
+            "to_peer_id": peer_b,
+            "offer_id": offer_id,
+            "answer": { "type": "answer", "sdp": "answer-sdp" },
+          })
+          .to_string()
+          .into(),
+        ))
+        .await
+        .unwrap();
+      let answer = recv_json(&mut client_b).await;
+      assert_eq!(answer["peer_id"], peer_a);
+      assert_eq!(answer["offer_id"], offer_id);
+      assert_eq!(answer["answer"]["sdp"], "answer-sdp");
+
+      // Answer from connection that didn't announce with claimed peer id is ignored.
+      let mut client_c = srv.ws_at(&format!("/peer/{}/ws", passkey)).await.unwrap();
+      client_c
+        .send(Message::Text(
+          json!({
+            "action": "announce",
+            "info_hash": info_hash,
+            "peer_id": peer_a,
+            "to_peer_id": peer_b,
+            "offer_id": offer_id,
+            "answer": { "type": "answer", "sdp": "spoofed-sdp" },
+          })
+          .to_string()
+          .into(),
+        ))
+        .await
+        .unwrap();
+      // Messages of connection are handled in order, so once scrape is answered, answer was handled too.
+      client_c
+        .send(Message::Text(
+          json!({ "action": "scrape", "info_hash": [info_hash] })
+            .to_string()
+            .into(),
+        ))
+        .await
+        .unwrap();
+      assert_eq!(recv_json(&mut client_c).await["action"], "scrape");
+
+      client_b
+        .send(Message::Text(
+          json!({ "action": "scrape", "info_hash": [info_hash] })
+            .to_string()
+            .into(),
+        ))
+        .await
+        .unwrap();
+      let scrape = recv_json(&mut client_b).await;
+      assert_eq!(scrape["action"], "scrape");
+      assert_eq!(scrape["files"][&info_hash]["complete"], 1);
+      assert_eq!(scrape["files"][&info_hash]["incomplete"], 1);
+
+      // Announce without `left` is rejected with failure reason.
+      client_b
+        .send(Message::Text(
+          json!({ "action": "announce", "info_hash": info_hash, "peer_id": peer_b })
+            .to_string()
+            .into(),
+        ))
+        .await
+        .unwrap();
+      let response = recv_json(&mut client_b).await;
+      assert!(response["failure reason"].is_string());
+
+      // Peer id of live connection can't be taken over by another one.
+      let announce_as_a = |event: &str| {
+        Message::Text(
+          json!({
+            "action": "announce",
+            "info_hash": info_hash,
+            "peer_id": peer_a,
+            "uploaded": 0,
+            "downloaded": 0,
+            "left": 0,
+            "event": event,
+            "numwant": 5,
+            "offers": [],
+          })
+          .to_string()
+          .into(),
+        )
+      };
+      client_c.send(announce_as_a("started")).await.unwrap();
+      let response = recv_json(&mut client_c).await;
+      assert!(response["failure reason"].is_string());
+      // Nor can another connection make it leave swarm.
+      client_c.send(announce_as_a("stopped")).await.unwrap();
+      assert_eq!(recv_json(&mut client_c).await["action"], "announce");
+      // So offers still reach A.
+      client_b
+        .send(Message::Text(
+          json!({
+            "action": "announce",
+            "info_hash": info_hash,
+            "peer_id": peer_b,
+            "uploaded": 0,
+            "downloaded": 0,
+            "left": 1000,
+            "event": "started",
+            "numwant": 5,
+            "offers": [{ "offer": { "type": "offer", "sdp": "offer-sdp-2" }, "offer_id": offer_id }],
+          })
+          .to_string()
+          .into(),
+        ))
+        .await
+        .unwrap();
+      assert_eq!(recv_json(&mut client_b).await["action"], "announce");
+      let offer = recv_json(&mut client_a).await;
+      assert_eq!(offer["peer_id"], peer_b);
+      assert_eq!(offer["offer"]["sdp"], "offer-sdp-2");
+
+      // Scrape is capped like over HTTP.
+      client_b
+        .send(Message::Text(
+          json!({
+            "action": "scrape",
+            "info_hash": vec![info_hash.clone(); SCRAPE_INFO_HASH_COUNT_MAX + 1],
+          })
+          .to_string()
+          .into(),
+        ))
+        .await
+        .unwrap();
+      let response = recv_json(&mut client_b).await;
+      assert!(response["failure reason"].is_string());
+    })
+    .await;
+
+  Ok(())
+}
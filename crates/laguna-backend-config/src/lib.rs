@@ -1,9 +1,14 @@
 #![doc(html_logo_url = "https://sloveniaengineering.github.io/laguna-backend/logo.png")]
 #![doc(html_favicon_url = "https://sloveniaengineering.github.io/laguna-backend/favicon.ico")]
 #![doc(issue_tracker_base_url = "https://github.com/SloveniaEngineering/laguna-backend")]
-use std::{env, net::SocketAddr};
+use std::{
+  env,
+  net::{IpAddr, SocketAddr},
+  path::PathBuf,
+  str::FromStr,
+};
 
-use actix_settings::BasicSettings;
+use actix_settings::{AtError, BasicSettings, Parse};
 use const_format::formatcp;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
@@ -21,6 +26,7 @@ pub struct ApplicationSettings {
   pub auth: AuthSettings,
   pub frontend: FrontendSettings,
   pub tracker: TrackerSettings,
+  pub mail: MailSettings,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -30,6 +36,50 @@ pub struct AuthSettings {
   pub password_pepper: Secret<String>,
   pub access_token_lifetime_seconds: i64,
   pub refresh_token_lifetime_seconds: i64,
+  pub email_verification_token_lifetime_seconds: i64,
+  pub password_reset_token_lifetime_seconds: i64,
+  pub login_challenge_token_lifetime_seconds: i64,
+  /// Failed logins of account before it is locked.
+  pub login_max_failed_attempts: i32,
+  /// Failed logins from IP address before it is locked.
+  pub login_ip_max_failed_attempts: i32,
+  /// First lockout, each further failed login doubles it.
+  pub login_lockout_seconds: i64,
+  /// Longest lockout, failures older than this are forgotten.
+  pub login_lockout_max_seconds: i64,
+  /// Reverse proxies whose `X-Forwarded-For` header is believed.
+  pub trusted_proxies: TrustedProxies,
+}
+
+/// IP addresses of trusted reverse proxies, comma separated in environment variable.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+impl TrustedProxies {
+  pub fn contains(&self, ip: &IpAddr) -> bool {
+    self.0.contains(ip)
+  }
+}
+
+impl Parse for TrustedProxies {
+  fn parse(string: &str) -> Result<Self, AtError> {
+    string
+      .split(',')
+      .map(str::trim)
+      .filter(|ip| !ip.is_empty())
+      .map(|ip| {
+        IpAddr::from_str(ip).map_err(|_| AtError::InvalidValue {
+          expected: "comma separated IP addresses",
+          got: string.to_string(),
+          file: file!(),
+          line: line!(),
+          column: column!(),
+        })
+      })
+      .collect::<Result<_, _>>()
+      .map(Self)
+  }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -82,11 +132,18 @@ impl DatabaseSettings {
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct FrontendSettings {
+  /// `https` in production, links in mail carry tokens.
+  pub scheme: String,
   pub host: String,
   pub port: u16,
 }
 
 impl FrontendSettings {
+  /// Base URL of frontend, links to it are appended to this.
+  pub fn url(&self) -> String {
+    format!("{}://{}", self.scheme, self.address())
+  }
+
   pub fn address(&self) -> SocketAddr {
     SocketAddr::new(
       self
@@ -102,6 +159,103 @@ impl FrontendSettings {
 #[serde(rename_all = "kebab-case")]
 pub struct TrackerSettings {
   pub announce_url: String,
+  /// Seconds clients should wait between regular announces.
+  pub announce_interval: u64,
+  /// Clients announcing more often than this (in seconds) are rejected.
+  pub announce_min_interval: u64,
+  /// Random amount of seconds (up to this value) added to interval, so clients don't announce all at once.
+  /// Zero disables jitter.
+  pub announce_jitter: u64,
+  /// Peers that didn't announce for this many (maximum) announce intervals are considered stale.
+  pub stale_peer_intervals: u64,
+  /// Number of peers returned if client doesn't specify `numwant`.
+  pub numwant_default: u32,
+  /// Maximum number of peers returned in single announce reply.
+  pub numwant_max: u32,
+  /// Accept old announce URLs with `down_hash` query, instead of passkey in path.
+  /// Meant for migration only, disable once clients re-downloaded their torrents.
+  pub accept_down_hash: bool,
+  pub udp_enabled: bool,
+  pub udp_host: String,
+  pub udp_port: u16,
+}
+
+impl TrackerSettings {
+  /// Seconds after last announce, after which peer is considered stale.
+  pub fn stale_peer_timeout(&self) -> u64 {
+    (self.announce_interval + self.announce_jitter) * self.stale_peer_intervals
+  }
+
+  /// Number of peers to return for `numwant` requested by client.
+  /// Negative `numwant` (UDP uses -1) means default.
+  pub fn numwant(&self, numwant: Option<i64>) -> usize {
+    match numwant {
+      Some(numwant) if numwant >= 0 => (numwant as usize).min(self.numwant_max as usize),
+      _ => self.numwant_default.min(self.numwant_max) as usize,
+    }
+  }
+
+  pub fn udp_address(&self) -> SocketAddr {
+    SocketAddr::new(
+      self
+        .udp_host
+        .parse()
+        .expect("Cannot parse UDP tracker host address"),
+      self.udp_port,
+    )
+  }
+}
+
+/// How outgoing mail is delivered.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum MailTransport {
+  Smtp,
+  /// Appends mail to file per recipient in `file-dir`, meant for development and tests.
+  File,
+  /// Prints mail to stdout, meant for development.
+  Stdout,
+}
+
+impl Parse for MailTransport {
+  fn parse(string: &str) -> Result<Self, AtError> {
+    match string {
+      "smtp" => Ok(Self::Smtp),
+      "file" => Ok(Self::File),
+      "stdout" => Ok(Self::Stdout),
+      _ => Err(AtError::InvalidValue {
+        expected: "smtp, file or stdout",
+        got: string.to_string(),
+        file: file!(),
+        line: line!(),
+        column: column!(),
+      }),
+    }
+  }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct MailSettings {
+  pub transport: MailTransport,
+  /// Sender mailbox, for example `Laguna <noreply@example.com>`.
+  pub from: String,
+  pub smtp_host: String,
+  pub smtp_port: u16,
+  pub smtp_username: String,
+  pub smtp_password: Secret<String>,
+  /// Relative paths are relative to workspace root.
+  pub file_dir: PathBuf,
+}
+
+impl MailSettings {
+  pub fn file_dir(&self) -> PathBuf {
+    if self.file_dir.is_relative() {
+      PathBuf::from(WORKSPACE_ROOT).join(&self.file_dir)
+    } else {
+      self.file_dir.clone()
+    }
+  }
 }
 
 pub fn make_overridable_with_env_vars(settings: &mut Settings) {
@@ -167,6 +321,55 @@ pub fn make_overridable_with_env_vars(settings: &mut Settings) {
     "APPLICATION_AUTH_REFRESH_TOKEN_LIFETIME_SECONDS",
   )
   .expect("APPLICATION_REFRESH_AUTH_TOKEN_LIFETIME_SECONDS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings
+      .application
+      .auth
+      .email_verification_token_lifetime_seconds,
+    "APPLICATION_AUTH_EMAIL_VERIFICATION_TOKEN_LIFETIME_SECONDS",
+  )
+  .expect("APPLICATION_AUTH_EMAIL_VERIFICATION_TOKEN_LIFETIME_SECONDS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings
+      .application
+      .auth
+      .password_reset_token_lifetime_seconds,
+    "APPLICATION_AUTH_PASSWORD_RESET_TOKEN_LIFETIME_SECONDS",
+  )
+  .expect("APPLICATION_AUTH_PASSWORD_RESET_TOKEN_LIFETIME_SECONDS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings
+      .application
+      .auth
+      .login_challenge_token_lifetime_seconds,
+    "APPLICATION_AUTH_LOGIN_CHALLENGE_TOKEN_LIFETIME_SECONDS",
+  )
+  .expect("APPLICATION_AUTH_LOGIN_CHALLENGE_TOKEN_LIFETIME_SECONDS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.auth.login_max_failed_attempts,
+    "APPLICATION_AUTH_LOGIN_MAX_FAILED_ATTEMPTS",
+  )
+  .expect("APPLICATION_AUTH_LOGIN_MAX_FAILED_ATTEMPTS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.auth.login_ip_max_failed_attempts,
+    "APPLICATION_AUTH_LOGIN_IP_MAX_FAILED_ATTEMPTS",
+  )
+  .expect("APPLICATION_AUTH_LOGIN_IP_MAX_FAILED_ATTEMPTS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.auth.login_lockout_seconds,
+    "APPLICATION_AUTH_LOGIN_LOCKOUT_SECONDS",
+  )
+  .expect("APPLICATION_AUTH_LOGIN_LOCKOUT_SECONDS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.auth.login_lockout_max_seconds,
+    "APPLICATION_AUTH_LOGIN_LOCKOUT_MAX_SECONDS",
+  )
+  .expect("APPLICATION_AUTH_LOGIN_LOCKOUT_MAX_SECONDS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.auth.trusted_proxies,
+    "APPLICATION_AUTH_TRUSTED_PROXIES",
+  )
+  .expect("APPLICATION_AUTH_TRUSTED_PROXIES not specified");
   Settings::override_field_with_env_var(
     &mut settings.application.database.proto,
     "APPLICATION_DATABASE_PROTO",
@@ -193,6 +396,11 @@ pub fn make_overridable_with_env_vars(settings: &mut Settings) {
     "APPLICATION_DATABASE_NAME",
   )
   .expect("APPLICATION_DATABASE_NAME not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.frontend.scheme,
+    "APPLICATION_FRONTEND_SCHEME",
+  )
+  .expect("APPLICATION_FRONTEND_SCHEME not specified");
   Settings::override_field_with_env_var(
     &mut settings.application.frontend.host,
     "APPLICATION_FRONTEND_HOST",
@@ -211,6 +419,89 @@ pub fn make_overridable_with_env_vars(settings: &mut Settings) {
     "APPLICATION_TRACKER_ANNOUNCE_URL",
   )
   .expect("APPLICATION_TRACKER_ANNOUNCE_URL not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.announce_interval,
+    "APPLICATION_TRACKER_ANNOUNCE_INTERVAL",
+  )
+  .expect("APPLICATION_TRACKER_ANNOUNCE_INTERVAL not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.announce_min_interval,
+    "APPLICATION_TRACKER_ANNOUNCE_MIN_INTERVAL",
+  )
+  .expect("APPLICATION_TRACKER_ANNOUNCE_MIN_INTERVAL not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.announce_jitter,
+    "APPLICATION_TRACKER_ANNOUNCE_JITTER",
+  )
+  .expect("APPLICATION_TRACKER_ANNOUNCE_JITTER not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.stale_peer_intervals,
+    "APPLICATION_TRACKER_STALE_PEER_INTERVALS",
+  )
+  .expect("APPLICATION_TRACKER_STALE_PEER_INTERVALS not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.numwant_default,
+    "APPLICATION_TRACKER_NUMWANT_DEFAULT",
+  )
+  .expect("APPLICATION_TRACKER_NUMWANT_DEFAULT not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.numwant_max,
+    "APPLICATION_TRACKER_NUMWANT_MAX",
+  )
+  .expect("APPLICATION_TRACKER_NUMWANT_MAX not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.accept_down_hash,
+    "APPLICATION_TRACKER_ACCEPT_DOWN_HASH",
+  )
+  .expect("APPLICATION_TRACKER_ACCEPT_DOWN_HASH not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.udp_enabled,
+    "APPLICATION_TRACKER_UDP_ENABLED",
+  )
+  .expect("APPLICATION_TRACKER_UDP_ENABLED not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.udp_host,
+    "APPLICATION_TRACKER_UDP_HOST",
+  )
+  .expect("APPLICATION_TRACKER_UDP_HOST not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.tracker.udp_port,
+    "APPLICATION_TRACKER_UDP_PORT",
+  )
+  .expect("APPLICATION_TRACKER_UDP_PORT not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.mail.transport,
+    "APPLICATION_MAIL_TRANSPORT",
+  )
+  .expect("APPLICATION_MAIL_TRANSPORT not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.mail.from,
+    "APPLICATION_MAIL_FROM",
+  )
+  .expect("APPLICATION_MAIL_FROM not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.mail.smtp_host,
+    "APPLICATION_MAIL_SMTP_HOST",
+  )
+  .expect("APPLICATION_MAIL_SMTP_HOST not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.mail.smtp_port,
+    "APPLICATION_MAIL_SMTP_PORT",
+  )
+  .expect("APPLICATION_MAIL_SMTP_PORT not specified");
+  Settings::override_field_with_env_var(
+    &mut settings.application.mail.smtp_username,
+    "APPLICATION_MAIL_SMTP_USERNAME",
+  )
+  .expect("APPLICATION_MAIL_SMTP_USERNAME not specified");
+  if let Ok(application_mail_smtp_password) = env::var("APPLICATION_MAIL_SMTP_PASSWORD") {
+    settings.application.mail.smtp_password = Secret::new(application_mail_smtp_password);
+  }
+  Settings::override_field_with_env_var(
+    &mut settings.application.mail.file_dir,
+    "APPLICATION_MAIL_FILE_DIR",
+  )
+  .expect("APPLICATION_MAIL_FILE_DIR not specified");
 }
 
 #[cfg(test)]
@@ -4,11 +4,16 @@
 pub mod already_exists;
 pub mod login;
 pub mod meta;
+pub mod password;
 pub mod peer;
 pub mod rating;
 pub mod register;
 pub mod role;
+pub mod session;
 pub mod torrent;
 pub mod torrent_rating;
+pub mod torrent_revision;
+pub mod totp;
 pub mod user;
 pub mod validators;
+pub mod verify;
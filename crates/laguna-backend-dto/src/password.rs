@@ -0,0 +1,42 @@
+use laguna_backend_model::consts::{EMAIL_MAX_LEN, EMAIL_MIN_LEN};
+use laguna_backend_model::consts::{PASSWORD_MAX_LEN, PASSWORD_MIN_LEN};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct PasswordChangeDTO {
+  /// Current plaintext password.
+  #[validate(
+    non_control_character,
+    length(min = "PASSWORD_MIN_LEN", max = "PASSWORD_MAX_LEN")
+  )]
+  pub password: String,
+  #[validate(
+    non_control_character,
+    length(min = "PASSWORD_MIN_LEN", max = "PASSWORD_MAX_LEN")
+  )]
+  pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct PasswordForgotDTO {
+  #[validate(
+    non_control_character,
+    email,
+    length(min = "EMAIL_MIN_LEN", max = "EMAIL_MAX_LEN")
+  )]
+  pub email: String,
+}
+
+/// Token from password reset mail and new password.
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct PasswordResetDTO {
+  #[validate(non_control_character)]
+  pub token: String,
+  #[validate(
+    non_control_character,
+    length(min = "PASSWORD_MIN_LEN", max = "PASSWORD_MAX_LEN")
+  )]
+  pub new_password: String,
+}
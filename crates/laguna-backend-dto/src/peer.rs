@@ -1,3 +1,3 @@
 use laguna_backend_model::peer::Peer;
 
-pub type PeerDTO = Peer;
+pub type PeerDTO<const N: usize> = Peer<N>;
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use laguna_backend_model::session::Session;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Active session of user, refresh token is never exposed.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct SessionDTO {
+  pub id: Uuid,
+  pub created_at: DateTime<Utc>,
+  pub last_used_at: DateTime<Utc>,
+  pub expires_at: DateTime<Utc>,
+  pub user_agent: Option<String>,
+  pub ip: Option<String>,
+  /// Session of request's refresh token.
+  pub is_current: bool,
+}
+
+impl SessionDTO {
+  pub fn new(session: Session, current_token_hash: Option<&[u8]>) -> Self {
+    Self {
+      is_current: current_token_hash == Some(session.token_hash.as_slice()),
+      id: session.id,
+      created_at: session.created_at,
+      last_used_at: session.last_used_at,
+      expires_at: session.expires_at,
+      user_agent: session.user_agent,
+      ip: session.ip.map(|ip| ip.ip().to_string()),
+    }
+  }
+}
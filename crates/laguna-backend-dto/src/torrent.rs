@@ -1,23 +1,115 @@
-use bendy::decoding::{self, FromBencode, ResultExt};
+use std::collections::BTreeMap;
+
+use bendy::decoding::{self, Decoder, FromBencode, Object, ResultExt};
 use bendy::encoding::{self, AsString, ToBencode};
+use bendy::value::Value;
 use chrono::serde::ts_seconds;
 use chrono::{DateTime, NaiveDateTime, Utc};
 #[cfg(feature = "testx")]
 use fake::Dummy;
+use laguna_backend_model::consts::{
+  TORRENT_DELETE_REASON_MAX_LEN, TORRENT_DELETE_REASON_MIN_LEN, TORRENT_PAGE_SIZE_MAX,
+  TORRENT_TITLE_MAX_LEN, TORRENT_TITLE_MIN_LEN,
+};
 use laguna_backend_model::consts::{TORRENT_FILENAME_MAX_LEN, TORRENT_FILENAME_MIN_LEN};
-use laguna_backend_model::consts::{TORRENT_TITLE_MAX_LEN, TORRENT_TITLE_MIN_LEN};
 use laguna_backend_model::genre::Genre;
+use laguna_backend_model::speedlevel::SpeedLevel;
+use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH, SHA256_LENGTH};
 
 use laguna_backend_model::torrent::Torrent;
 
 use actix_multipart_extract::File as ActixFile;
 use actix_multipart_extract::MultipartForm;
 use serde::{Deserialize, Serialize};
+use serde_with::hex::Hex;
+use serde_with::{serde_as, DisplayFromStr};
+use std::fmt;
+use std::str::FromStr;
 
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 use validator::Validate;
 
-pub type TorrentDTO = Torrent;
+/// Torrent with metadata parsed from its metainfo, without raw bencoded metainfo.
+/// Use `/api/torrent/{info_hash}/raw` for .torrent file.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct TorrentDTO<const N: usize> {
+  pub info_hash: InfoHash<N>,
+  pub info_hash_v2: Option<InfoHash<SHA256_LENGTH>>,
+  pub announce_url: Option<String>,
+  pub length: i64,
+  pub file_name: String,
+  pub nfo: Option<String>,
+  pub is_freeleech: bool,
+  pub genre: Option<Genre>,
+  pub leech_count: i32,
+  pub seed_count: i32,
+  pub completed_count: i32,
+  pub speedlevel: SpeedLevel,
+  pub creation_date: DateTime<Utc>,
+  pub created_by: Option<String>,
+  pub uploaded_at: DateTime<Utc>,
+  pub uploaded_by: Uuid,
+  pub modded_at: Option<DateTime<Utc>>,
+  pub modded_by: Option<Uuid>,
+  pub deleted_at: Option<DateTime<Utc>>,
+  pub deleted_by: Option<Uuid>,
+  pub delete_reason: Option<String>,
+  pub comment: Option<String>,
+  pub piece_length: i64,
+  pub piece_count: i64,
+  pub files: Vec<TorrentFileDTO>,
+  pub url_list: Option<Vec<String>>,
+}
+
+/// Single file in torrent.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct TorrentFileDTO {
+  /// Path elements, starting with torrent's name.
+  pub path: Vec<String>,
+  pub length: i64,
+}
+
+impl<const N: usize> TryFrom<Torrent<N>> for TorrentDTO<N> {
+  type Error = decoding::Error;
+
+  fn try_from(torrent: Torrent<N>) -> Result<Self, Self::Error> {
+    let torrent_file = TorrentFile::from_bencode(&torrent.raw)?;
+    Ok(Self {
+      info_hash: torrent.info_hash,
+      info_hash_v2: torrent.info_hash_v2,
+      announce_url: torrent.announce_url,
+      length: torrent.length,
+      file_name: torrent.file_name,
+      nfo: torrent.nfo,
+      is_freeleech: torrent.is_freeleech,
+      genre: torrent.genre,
+      leech_count: torrent.leech_count,
+      seed_count: torrent.seed_count,
+      completed_count: torrent.completed_count,
+      speedlevel: torrent.speedlevel,
+      creation_date: torrent.creation_date,
+      created_by: torrent.created_by,
+      uploaded_at: torrent.uploaded_at,
+      uploaded_by: torrent.uploaded_by,
+      modded_at: torrent.modded_at,
+      modded_by: torrent.modded_by,
+      deleted_at: torrent.deleted_at,
+      deleted_by: torrent.deleted_by,
+      delete_reason: torrent.delete_reason,
+      comment: torrent_file.comment,
+      piece_length: torrent_file.info.piece_length,
+      piece_count: torrent_file.info.piece_count(),
+      files: torrent_file
+        .info
+        .file_list()
+        .into_iter()
+        .map(|(path, length)| TorrentFileDTO { path, length })
+        .collect(),
+      url_list: torrent_file.url_list,
+    })
+  }
+}
 
 #[derive(Debug, Deserialize, MultipartForm, ToSchema)]
 pub struct TorrentPutDTO {
@@ -25,6 +117,10 @@ pub struct TorrentPutDTO {
   pub torrent: ActixFile,
 }
 
+/// Dictionary entries with keys unknown to us (ex. `publisher`, `source`, `x_cross_seed`).
+/// They are kept sorted by key, so re-encoded dictionary is byte-for-byte same as decoded one.
+pub type Extensions = BTreeMap<Vec<u8>, Value<'static>>;
+
 /// Torrent file (raw).
 /// Some specifications:
 /// <http://bittorrent.org/beps/bep_0052.html>
@@ -66,6 +162,10 @@ pub struct TorrentFile {
   pub created_by: Option<String>,
   // info is set by torrent client
   pub info: TorrentInfo,
+  // piece layers is set by torrent client (BitTorrent v2 only)
+  #[serde(rename = "piece layers")]
+  #[serde(default)]
+  pub piece_layers: Option<PieceLayers>,
   // url-list is set by torrent client (this is webtorrent specific)
   #[serde(rename = "url-list")]
   #[serde(default)]
@@ -79,6 +179,9 @@ pub struct TorrentFile {
   // httpseeds is set by torrent client
   #[serde(default)]
   pub httpseeds: Option<Vec<String>>,
+  // keys unknown to us, re-emitted as they were
+  #[serde(skip)]
+  pub extensions: Extensions,
 }
 
 impl FromBencode for TorrentFile {
@@ -87,6 +190,7 @@ impl FromBencode for TorrentFile {
     Self: Sized,
   {
     let mut dict = object.try_into_dictionary()?;
+    let mut extensions = Extensions::new();
     let mut announce_url = None;
     let mut announce_list = None;
     let mut title = None;
@@ -96,6 +200,7 @@ impl FromBencode for TorrentFile {
     let mut cration_date = None;
     let mut created_by = None;
     let mut info = None;
+    let mut piece_layers = None;
     let mut url_list = None;
     let mut website = None;
     let mut nodes = None;
@@ -153,6 +258,11 @@ impl FromBencode for TorrentFile {
             .context("info")
             .map(Some)?;
         },
+        (b"piece layers", value) => {
+          piece_layers = PieceLayers::decode_bencode_object(value)
+            .context("piece layers")
+            .map(Some)?;
+        },
         (b"url-list", value) => {
           url_list = Vec::<String>::decode_bencode_object(value)
             .context("url-list")
@@ -173,10 +283,11 @@ impl FromBencode for TorrentFile {
             .context("httpseeds")
             .map(Some)?;
         },
-        (unknown_field, _) => {
-          return Err(decoding::Error::unexpected_field(String::from_utf8_lossy(
-            unknown_field,
-          )));
+        (unknown_field, value) => {
+          extensions.insert(
+            unknown_field.to_vec(),
+            Value::decode_bencode_object(value).context(String::from_utf8_lossy(unknown_field))?,
+          );
         },
       }
     }
@@ -190,16 +301,36 @@ impl FromBencode for TorrentFile {
       creation_date: cration_date.ok_or_else(|| decoding::Error::missing_field("creation date"))?,
       created_by,
       info: info.ok_or_else(|| decoding::Error::missing_field("info"))?,
+      piece_layers,
       url_list,
       website,
       nodes,
       httpseeds,
+      extensions,
     })
   }
 }
 
+impl TorrentFile {
+  /// Raw bencoded `info` dictionary, exactly as it appears in torrent `bytes`.
+  /// Info hashes have to be computed from it, re-encoded [`TorrentInfo`] might differ.
+  pub fn raw_info(bytes: &[u8]) -> Result<&[u8], decoding::Error> {
+    let mut decoder = Decoder::new(bytes);
+    let mut dict = decoder
+      .next_object()?
+      .ok_or_else(|| decoding::Error::missing_field("info"))?
+      .try_into_dictionary()?;
+    while let Some(pair) = dict.next_pair()? {
+      if let (b"info", value) = pair {
+        return value.try_into_dictionary().context("info")?.into_raw();
+      }
+    }
+    Err(decoding::Error::missing_field("info"))
+  }
+}
+
 impl ToBencode for TorrentFile {
-  const MAX_DEPTH: usize = 10;
+  const MAX_DEPTH: usize = TorrentInfo::MAX_DEPTH + 1;
   fn encode(&self, encoder: encoding::SingleItemEncoder) -> Result<(), encoding::Error> {
     encoder.emit_unsorted_dict(|d| {
       if let Some(ref announce_url) = self.announce_url {
@@ -225,6 +356,9 @@ impl ToBencode for TorrentFile {
         d.emit_pair(b"created by", created_by)?;
       }
       d.emit_pair(b"info", &self.info)?;
+      if let Some(ref piece_layers) = self.piece_layers {
+        d.emit_pair(b"piece layers", piece_layers)?;
+      }
       if let Some(ref url_list) = self.url_list {
         d.emit_pair(b"url-list", url_list)?;
       }
@@ -237,6 +371,9 @@ impl ToBencode for TorrentFile {
       if let Some(ref httpseeds) = self.httpseeds {
         d.emit_pair(b"httpseeds", httpseeds)?;
       }
+      for (key, value) in &self.extensions {
+        d.emit_pair(key, value)?;
+      }
       Ok(())
     })
   }
@@ -259,8 +396,18 @@ pub struct TorrentInfo {
   pub name: String,
   #[serde(rename = "piece length")]
   pub piece_length: i64,
+  // SHA1 piece hashes, absent in BitTorrent v2 only torrents.
   #[serde(rename = "pieces")]
-  pub pieces: Vec<u8>,
+  #[serde(default)]
+  pub pieces: Option<Vec<u8>>,
+  // 2 for BitTorrent v2 and hybrid torrents.
+  #[serde(rename = "meta version")]
+  #[serde(default)]
+  pub meta_version: Option<i64>,
+  // BitTorrent v2 file tree, keyed by path element.
+  #[serde(rename = "file tree")]
+  #[serde(default)]
+  pub file_tree: Option<BTreeMap<String, FileTree>>,
   #[serde(rename = "root hash")]
   #[serde(default)]
   pub root_hash: Option<String>,
@@ -272,6 +419,9 @@ pub struct TorrentInfo {
   pub files: Option<Vec<File>>,
   #[serde(default)]
   pub profiles: Option<Vec<TorrentProfile>>,
+  // keys unknown to us, re-emitted as they were
+  #[serde(skip)]
+  pub extensions: Extensions,
 }
 
 impl FromBencode for TorrentInfo {
@@ -280,12 +430,15 @@ impl FromBencode for TorrentInfo {
     Self: Sized,
   {
     let mut dict = object.try_into_dictionary()?;
+    let mut extensions = Extensions::new();
     let mut file_duration = None;
     let mut file_media = None;
     let mut length = None;
     let mut name = None;
     let mut piece_length = None;
     let mut pieces = None;
+    let mut meta_version = None;
+    let mut file_tree = None;
     let mut root_hash = None;
     let mut md5sum = None;
     let mut private = None;
@@ -323,6 +476,16 @@ impl FromBencode for TorrentInfo {
             .context("pieces")
             .map(|bytes| Some(bytes.0))?;
         },
+        (b"meta version", value) => {
+          meta_version = i64::decode_bencode_object(value)
+            .context("meta version")
+            .map(Some)?;
+        },
+        (b"file tree", value) => {
+          file_tree = BTreeMap::<String, FileTree>::decode_bencode_object(value)
+            .context("file tree")
+            .map(Some)?;
+        },
         (b"root hash", value) => {
           root_hash = String::decode_bencode_object(value)
             .context("root hash")
@@ -348,10 +511,11 @@ impl FromBencode for TorrentInfo {
             .context("profiles")
             .map(Some)?;
         },
-        (unknown_field, _) => {
-          return Err(decoding::Error::unexpected_field(String::from_utf8_lossy(
-            unknown_field,
-          )));
+        (unknown_field, value) => {
+          extensions.insert(
+            unknown_field.to_vec(),
+            Value::decode_bencode_object(value).context(String::from_utf8_lossy(unknown_field))?,
+          );
         },
       }
     }
@@ -361,18 +525,119 @@ impl FromBencode for TorrentInfo {
       length,
       name: name.ok_or_else(|| decoding::Error::missing_field("name"))?,
       piece_length: piece_length.ok_or_else(|| decoding::Error::missing_field("piece length"))?,
-      pieces: pieces.ok_or_else(|| decoding::Error::missing_field("pieces"))?,
+      pieces,
+      meta_version,
+      file_tree,
       root_hash,
       md5sum,
       private,
       files,
       profiles,
+      extensions,
     })
   }
 }
 
+impl TorrentInfo {
+  /// Whether torrent has BitTorrent v1 metadata (BEP 3).
+  pub fn is_v1(&self) -> bool {
+    self.pieces.is_some()
+  }
+
+  /// Whether torrent has BitTorrent v2 metadata (BEP 52).
+  /// Hybrid torrents are both v1 and v2.
+  pub fn is_v2(&self) -> bool {
+    self.meta_version == Some(2) && self.file_tree.is_some()
+  }
+
+  /// Number of pieces.
+  /// v2 pieces are aligned to files, so v2 only torrents have pieces of each file counted separately.
+  pub fn piece_count(&self) -> i64 {
+    match self.pieces {
+      Some(ref pieces) => (pieces.len() / 20) as i64,
+      None if self.piece_length > 0 => self
+        .file_list()
+        .iter()
+        .map(|(_, length)| length / self.piece_length + i64::from(length % self.piece_length != 0))
+        .fold(0, i64::saturating_add),
+      None => 0,
+    }
+  }
+
+  /// Paths (starting with `name`) and lengths of all files in torrent.
+  /// v1 metadata is preferred over v2 file tree for hybrid torrents.
+  pub fn file_list(&self) -> Vec<(Vec<String>, i64)> {
+    if let Some(length) = self.length {
+      vec![(vec![self.name.clone()], length)]
+    } else if let Some(ref files) = self.files {
+      files
+        .iter()
+        .map(|file| {
+          let mut path = vec![self.name.clone()];
+          path.extend(file.path.iter().cloned());
+          (path, file.length as i64)
+        })
+        .collect()
+    } else if let Some(ref file_tree) = self.file_tree {
+      let mut file_list = Vec::new();
+      match file_tree.get(&self.name) {
+        // Single file v2 torrent has its name as only entry of file tree.
+        Some(FileTree::File(entry)) if file_tree.len() == 1 => {
+          file_list.push((vec![self.name.clone()], entry.length))
+        },
+        _ => {
+          for (name, node) in file_tree {
+            node.collect_files(vec![self.name.clone(), name.clone()], &mut file_list);
+          }
+        },
+      }
+      file_list
+    } else {
+      vec![]
+    }
+  }
+
+  /// BEP 52 file tree entry of file at `path`, as in [`Self::file_list`].
+  pub fn file_tree_entry(&self, path: &[String]) -> Option<&FileTreeEntry> {
+    let file_tree = self.file_tree.as_ref()?;
+    let node = match path {
+      [name] => file_tree.get(name)?,
+      [name, first, rest @ ..] if *name == self.name => {
+        rest
+          .iter()
+          .try_fold(file_tree.get(first)?, |node, name| match node {
+            FileTree::Directory(children) => children.get(name),
+            FileTree::File(_) => None,
+          })?
+      },
+      _ => return None,
+    };
+    match node {
+      FileTree::File(entry) => Some(entry),
+      FileTree::Directory(_) => None,
+    }
+  }
+
+  /// Total length of all files in torrent, `None` if it overflows.
+  pub fn total_length(&self) -> Option<i64> {
+    if let Some(length) = self.length {
+      Some(length)
+    } else if let Some(ref files) = self.files {
+      checked_sum(files.iter().map(|file| Some(file.length as i64)))
+    } else if let Some(ref file_tree) = self.file_tree {
+      checked_sum(file_tree.values().map(FileTree::total_length))
+    } else {
+      Some(0)
+    }
+  }
+}
+
+fn checked_sum(mut lengths: impl Iterator<Item = Option<i64>>) -> Option<i64> {
+  lengths.try_fold(0_i64, |sum, length| sum.checked_add(length?))
+}
+
 impl ToBencode for TorrentInfo {
-  const MAX_DEPTH: usize = 10;
+  const MAX_DEPTH: usize = FileTree::MAX_DEPTH + 2;
   fn encode(&self, encoder: encoding::SingleItemEncoder) -> Result<(), encoding::Error> {
     encoder.emit_unsorted_dict(|d| {
       if let Some(ref file_duration) = self.file_duration {
@@ -386,7 +651,15 @@ impl ToBencode for TorrentInfo {
       }
       d.emit_pair(b"name", &self.name)?;
       d.emit_pair(b"piece length", self.piece_length)?;
-      d.emit_pair(b"pieces", AsString(&self.pieces))?;
+      if let Some(ref pieces) = self.pieces {
+        d.emit_pair(b"pieces", AsString(pieces))?;
+      }
+      if let Some(ref meta_version) = self.meta_version {
+        d.emit_pair(b"meta version", meta_version)?;
+      }
+      if let Some(ref file_tree) = self.file_tree {
+        d.emit_pair(b"file tree", file_tree)?;
+      }
       if let Some(ref root_hash) = self.root_hash {
         d.emit_pair(b"root hash", root_hash)?;
       }
@@ -402,6 +675,183 @@ impl ToBencode for TorrentInfo {
       if let Some(ref profiles) = self.profiles {
         d.emit_pair(b"profiles", profiles)?;
       }
+      for (key, value) in &self.extensions {
+        d.emit_pair(key, value)?;
+      }
+      Ok(())
+    })
+  }
+}
+
+/// Node of BitTorrent v2 file tree (BEP 52).
+/// File is a dictionary with a single empty key, anything else is a directory.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub enum FileTree {
+  File(FileTreeEntry),
+  Directory(BTreeMap<String, FileTree>),
+}
+
+impl FileTree {
+  /// Appends paths and lengths of all files in this node, which is at `path`.
+  fn collect_files(&self, path: Vec<String>, file_list: &mut Vec<(Vec<String>, i64)>) {
+    match self {
+      Self::File(entry) => file_list.push((path, entry.length)),
+      Self::Directory(children) => {
+        for (name, node) in children {
+          let mut path = path.clone();
+          path.push(name.clone());
+          node.collect_files(path, file_list);
+        }
+      },
+    }
+  }
+
+  /// Total length of all files in this node, `None` if it overflows.
+  pub fn total_length(&self) -> Option<i64> {
+    match self {
+      Self::File(entry) => Some(entry.length),
+      Self::Directory(children) => checked_sum(children.values().map(Self::total_length)),
+    }
+  }
+}
+
+impl FromBencode for FileTree {
+  fn decode_bencode_object(object: decoding::Object) -> Result<Self, decoding::Error>
+  where
+    Self: Sized,
+  {
+    let mut dict = object.try_into_dictionary()?;
+    let mut entry = None;
+    let mut children = BTreeMap::new();
+    while let Some(pair) = dict.next_pair()? {
+      match pair {
+        (b"", value) => {
+          entry = FileTreeEntry::decode_bencode_object(value)
+            .context("file")
+            .map(Some)?;
+        },
+        (name, value) => {
+          let name = String::decode_bencode_object(Object::Bytes(name))?;
+          let node = FileTree::decode_bencode_object(value).context(&name)?;
+          children.insert(name, node);
+        },
+      }
+    }
+    match entry {
+      Some(entry) if children.is_empty() => Ok(Self::File(entry)),
+      // File entry can't have siblings.
+      Some(_) => Err(decoding::Error::unexpected_field("file tree")),
+      None => Ok(Self::Directory(children)),
+    }
+  }
+}
+
+impl ToBencode for FileTree {
+  // Recursive type, so depth can't be derived from children.
+  const MAX_DEPTH: usize = 32;
+  fn encode(&self, encoder: encoding::SingleItemEncoder) -> Result<(), encoding::Error> {
+    match self {
+      Self::File(entry) => encoder.emit_dict(|mut d| d.emit_pair(b"", entry)),
+      Self::Directory(children) => children.encode(encoder),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct FileTreeEntry {
+  pub length: i64,
+  // Merkle root of file, absent for empty files.
+  #[serde(rename = "pieces root")]
+  #[serde(default)]
+  pub pieces_root: Option<Vec<u8>>,
+  // keys unknown to us, re-emitted as they were
+  #[serde(skip)]
+  pub extensions: Extensions,
+}
+
+impl FromBencode for FileTreeEntry {
+  fn decode_bencode_object(object: decoding::Object) -> Result<Self, decoding::Error>
+  where
+    Self: Sized,
+  {
+    let mut dict = object.try_into_dictionary()?;
+    let mut extensions = Extensions::new();
+    let mut length = None;
+    let mut pieces_root = None;
+    while let Some(pair) = dict.next_pair()? {
+      match pair {
+        (b"length", value) => {
+          length = i64::decode_bencode_object(value)
+            .context("length")
+            .map(Some)?;
+        },
+        (b"pieces root", value) => {
+          pieces_root = AsString::decode_bencode_object(value)
+            .context("pieces root")
+            .map(|bytes| Some(bytes.0))?;
+        },
+        (unknown_field, value) => {
+          extensions.insert(
+            unknown_field.to_vec(),
+            Value::decode_bencode_object(value).context(String::from_utf8_lossy(unknown_field))?,
+          );
+        },
+      }
+    }
+    Ok(Self {
+      length: length.ok_or_else(|| decoding::Error::missing_field("length"))?,
+      pieces_root,
+      extensions,
+    })
+  }
+}
+
+impl ToBencode for FileTreeEntry {
+  const MAX_DEPTH: usize = 10;
+  fn encode(&self, encoder: encoding::SingleItemEncoder) -> Result<(), encoding::Error> {
+    encoder.emit_unsorted_dict(|d| {
+      d.emit_pair(b"length", self.length)?;
+      if let Some(ref pieces_root) = self.pieces_root {
+        d.emit_pair(b"pieces root", AsString(pieces_root))?;
+      }
+      for (key, value) in &self.extensions {
+        d.emit_pair(key, value)?;
+      }
+      Ok(())
+    })
+  }
+}
+
+/// BitTorrent v2 piece layers (BEP 52).
+/// Maps file pieces root to concatenated hashes of its pieces.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct PieceLayers(pub BTreeMap<Vec<u8>, Vec<u8>>);
+
+impl FromBencode for PieceLayers {
+  fn decode_bencode_object(object: decoding::Object) -> Result<Self, decoding::Error>
+  where
+    Self: Sized,
+  {
+    let mut dict = object.try_into_dictionary()?;
+    let mut layers = BTreeMap::new();
+    while let Some((pieces_root, value)) = dict.next_pair()? {
+      let pieces_root = pieces_root.to_vec();
+      let layer = AsString::decode_bencode_object(value)
+        .context("piece layer")?
+        .0;
+      layers.insert(pieces_root, layer);
+    }
+    Ok(Self(layers))
+  }
+}
+
+impl ToBencode for PieceLayers {
+  const MAX_DEPTH: usize = 10;
+  fn encode(&self, encoder: encoding::SingleItemEncoder) -> Result<(), encoding::Error> {
+    encoder.emit_dict(|mut d| {
+      for (pieces_root, layer) in &self.0 {
+        d.emit_pair(pieces_root, AsString(layer))?;
+      }
       Ok(())
     })
   }
@@ -414,6 +864,10 @@ pub struct File {
   pub path: Vec<String>,
   #[serde(default)]
   pub md5sum: Option<String>,
+  // keys unknown to us, re-emitted as they were
+  #[serde(skip)]
+  #[cfg_attr(feature = "testx", dummy(default))]
+  pub extensions: Extensions,
 }
 
 impl FromBencode for File {
@@ -422,6 +876,7 @@ impl FromBencode for File {
     Self: Sized,
   {
     let mut dict = object.try_into_dictionary()?;
+    let mut extensions = Extensions::new();
     let mut length = None;
     let mut path = None;
     let mut md5sum = None;
@@ -442,10 +897,11 @@ impl FromBencode for File {
             .context("md5sum")
             .map(Some)?;
         },
-        (unknown_field, _) => {
-          return Err(decoding::Error::unexpected_field(String::from_utf8_lossy(
-            unknown_field,
-          )));
+        (unknown_field, value) => {
+          extensions.insert(
+            unknown_field.to_vec(),
+            Value::decode_bencode_object(value).context(String::from_utf8_lossy(unknown_field))?,
+          );
         },
       }
     }
@@ -453,6 +909,7 @@ impl FromBencode for File {
       length: length.ok_or_else(|| decoding::Error::missing_field("length"))?,
       path: path.ok_or_else(|| decoding::Error::missing_field("path"))?,
       md5sum,
+      extensions,
     })
   }
 }
@@ -466,6 +923,9 @@ impl ToBencode for File {
       if let Some(ref md5sum) = self.md5sum {
         d.emit_pair(b"md5sum", md5sum)?;
       }
+      for (key, value) in &self.extensions {
+        d.emit_pair(key, value)?;
+      }
       Ok(())
     })
   }
@@ -597,6 +1057,16 @@ impl ToBencode for TorrentProfile {
   }
 }
 
+/// Reason is shown to peers announcing trashed torrent.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Validate, ToSchema)]
+pub struct TorrentTrashDTO {
+  #[validate(length(
+    min = "TORRENT_DELETE_REASON_MIN_LEN",
+    max = "TORRENT_DELETE_REASON_MAX_LEN"
+  ))]
+  pub reason: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Validate, ToSchema)]
 pub struct TorrentPatchDTO {
   #[serde(default)]
@@ -604,3 +1074,222 @@ pub struct TorrentPatchDTO {
   #[serde(default)]
   pub genre: Option<Genre>,
 }
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TorrentSort {
+  Seeders,
+  Completed,
+  Rating,
+  #[default]
+  Date,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+  Asc,
+  #[default]
+  Desc,
+}
+
+/// Position in torrent listing, after the last torrent of previous page.
+/// Clients should treat it as opaque and only pass it back.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TorrentCursor {
+  /// Sort key of the last torrent, negated for descending order.
+  pub key: f64,
+  /// Tiebreaker for torrents with equal sort key.
+  pub info_hash: InfoHash<SHA1_LENGTH>,
+}
+
+impl fmt::Display for TorrentCursor {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_fmt(format_args!("{}_{}", self.key, self.info_hash))
+  }
+}
+
+impl FromStr for TorrentCursor {
+  type Err = &'static str;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    const INVALID: &str = "Neveljaven kazalec strani.";
+    let (key, info_hash) = s.split_once('_').ok_or(INVALID)?;
+    if info_hash.len() != 2 * SHA1_LENGTH {
+      return Err(INVALID);
+    }
+    let info_hash = (0..info_hash.len())
+      .step_by(2)
+      .map(|i| {
+        info_hash
+          .get(i..i + 2)
+          .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+      })
+      .collect::<Option<Vec<u8>>>()
+      .ok_or(INVALID)?;
+    Ok(TorrentCursor {
+      key: key.parse().map_err(|_| INVALID)?,
+      info_hash: InfoHash::from(info_hash),
+    })
+  }
+}
+
+/// Query of torrent listing, all filters are optional and combined.
+#[serde_as]
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TorrentSearchDTO {
+  /// Full-text search over file name and nfo, in web search syntax.
+  pub q: Option<String>,
+  pub genre: Option<Genre>,
+  pub speedlevel: Option<SpeedLevel>,
+  pub is_freeleech: Option<bool>,
+  pub uploaded_by: Option<Uuid>,
+  /// Inclusive.
+  pub uploaded_after: Option<DateTime<Utc>>,
+  /// Exclusive.
+  pub uploaded_before: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub sort: TorrentSort,
+  #[serde(default)]
+  pub order: SortOrder,
+  /// `next_cursor` of previous page.
+  #[serde_as(as = "Option<DisplayFromStr>")]
+  #[serde(default)]
+  #[param(value_type = Option<String>)]
+  pub cursor: Option<TorrentCursor>,
+  #[validate(range(min = 1, max = "TORRENT_PAGE_SIZE_MAX"))]
+  pub limit: Option<i64>,
+}
+
+/// Torrent in listings, without `raw`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct TorrentSummaryDTO {
+  pub info_hash: InfoHash<SHA1_LENGTH>,
+  pub info_hash_v2: Option<InfoHash<SHA256_LENGTH>>,
+  pub announce_url: Option<String>,
+  pub length: i64,
+  pub file_name: String,
+  pub nfo: Option<String>,
+  pub is_freeleech: bool,
+  pub genre: Option<Genre>,
+  pub leech_count: i32,
+  pub seed_count: i32,
+  pub completed_count: i32,
+  pub speedlevel: SpeedLevel,
+  pub creation_date: DateTime<Utc>,
+  pub created_by: Option<String>,
+  pub uploaded_at: DateTime<Utc>,
+  pub uploaded_by: Uuid,
+  pub modded_at: Option<DateTime<Utc>>,
+  pub modded_by: Option<Uuid>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct TorrentPageDTO {
+  pub torrents: Vec<TorrentSummaryDTO>,
+  /// Cursor of next page, `None` on last page.
+  #[serde_as(as = "Option<DisplayFromStr>")]
+  #[schema(value_type = Option<String>)]
+  pub next_cursor: Option<TorrentCursor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct MagnetDTO {
+  /// Magnet URI with user's personalized announce url as tracker.
+  pub magnet: String,
+}
+
+/// Directory tree of files in torrent.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct TorrentFilesDTO {
+  /// Total length of all files.
+  pub length: i64,
+  pub file_count: i64,
+  /// Single file of torrent or its root directory.
+  pub root: FileNodeDTO,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct FileNodeDTO {
+  pub name: String,
+  /// Length of file or total length of all files in directory.
+  pub length: i64,
+  /// BEP 52 merkle root of file, only present for files of v2 torrents.
+  #[serde_as(as = "Option<Hex>")]
+  #[schema(value_type = Option<String>)]
+  pub pieces_root: Option<Vec<u8>>,
+  /// Entries of directory, `None` for files.
+  pub children: Option<Vec<FileNodeDTO>>,
+}
+
+impl FileNodeDTO {
+  fn directory(name: String) -> Self {
+    Self {
+      name,
+      length: 0,
+      pieces_root: None,
+      children: Some(Vec::new()),
+    }
+  }
+
+  /// Inserts file at `path` relative to this directory, creating directories on the way.
+  fn insert(&mut self, path: &[String], length: i64, pieces_root: Option<Vec<u8>>) {
+    self.length += length;
+    let children = self.children.get_or_insert_with(Vec::new);
+    match path {
+      [] => {},
+      [name] => children.push(Self {
+        name: name.clone(),
+        length,
+        pieces_root,
+        children: None,
+      }),
+      [name, rest @ ..] => {
+        let position = children
+          .iter()
+          .position(|child| child.name == *name && child.children.is_some());
+        let directory = match position {
+          Some(position) => &mut children[position],
+          None => {
+            children.push(Self::directory(name.clone()));
+            children.last_mut().expect("Directory was just inserted")
+          },
+        };
+        directory.insert(rest, length, pieces_root);
+      },
+    }
+  }
+}
+
+impl From<&TorrentInfo> for TorrentFilesDTO {
+  fn from(info: &TorrentInfo) -> Self {
+    let file_list = info.file_list();
+    let mut root = FileNodeDTO::directory(info.name.clone());
+    for (path, length) in &file_list {
+      let pieces_root = info
+        .file_tree_entry(path)
+        .and_then(|entry| entry.pieces_root.clone());
+      match path.as_slice() {
+        // Single file torrent, file itself is the root.
+        [_] => {
+          root = FileNodeDTO {
+            name: info.name.clone(),
+            length: *length,
+            pieces_root,
+            children: None,
+          }
+        },
+        [_, rest @ ..] => root.insert(rest, *length, pieces_root),
+        [] => {},
+      }
+    }
+    Self {
+      length: root.length,
+      file_count: file_list.len() as i64,
+      root,
+    }
+  }
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use laguna_backend_model::genre::Genre;
+use laguna_backend_model::torrent_revision::TorrentRevision;
+use laguna_backend_tracker_common::info_hash::InfoHash;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct TorrentRevisionDTO<const N: usize> {
+  pub id: Uuid,
+  pub info_hash: InfoHash<N>,
+  pub old_nfo: Option<String>,
+  pub new_nfo: Option<String>,
+  pub old_genre: Option<Genre>,
+  pub new_genre: Option<Genre>,
+  pub modded_at: DateTime<Utc>,
+  pub modded_by: Option<Uuid>,
+}
+
+impl<const N: usize> From<TorrentRevision<N>> for TorrentRevisionDTO<N> {
+  fn from(value: TorrentRevision<N>) -> Self {
+    Self {
+      id: value.id,
+      info_hash: value.info_hash,
+      old_nfo: value.old_nfo,
+      new_nfo: value.new_nfo,
+      old_genre: value.old_genre,
+      new_genre: value.new_genre,
+      modded_at: value.modded_at,
+      modded_by: value.modded_by,
+    }
+  }
+}
@@ -0,0 +1,50 @@
+use laguna_backend_model::consts::{TOTP_CODE_LEN, TOTP_RECOVERY_CODE_LEN};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Pending TOTP enrollment, only ever sent to its owner once.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct TotpEnrollDTO {
+  /// Base32 encoded shared secret, for authenticators that can't scan `otpauth_uri`.
+  pub secret: String,
+  pub otpauth_uri: String,
+  /// Single use codes, valid once enrollment is confirmed.
+  pub recovery_codes: Vec<String>,
+}
+
+/// Code from authenticator or a recovery code, which only works once TOTP is enabled.
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct TotpCodeDTO {
+  #[validate(
+    non_control_character,
+    length(min = "TOTP_CODE_LEN", max = "TOTP_RECOVERY_CODE_LEN")
+  )]
+  pub code: String,
+}
+
+/// Returned by login instead of tokens when second factor is required.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct LoginChallengeDTO {
+  pub token: String,
+  /// User has to enroll TOTP before it can log in, see `/api/user/auth/login/totp/enroll`.
+  pub enrollment_required: bool,
+}
+
+/// Second login step, code is either from authenticator or a recovery code.
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct LoginTotpDTO {
+  #[validate(non_control_character)]
+  pub token: String,
+  #[validate(
+    non_control_character,
+    length(min = "TOTP_CODE_LEN", max = "TOTP_RECOVERY_CODE_LEN")
+  )]
+  pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct LoginTotpEnrollDTO {
+  #[validate(non_control_character)]
+  pub token: String,
+}
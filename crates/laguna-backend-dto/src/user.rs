@@ -4,6 +4,7 @@ use chrono::Utc;
 use laguna_backend_model::behaviour::Behaviour;
 use laguna_backend_model::consts::{USERNAME_MAX_LEN, USERNAME_MIN_LEN};
 use laguna_backend_model::role::Role;
+use laguna_backend_model::user::Passkey;
 use laguna_backend_model::user::User;
 use laguna_backend_model::user::UserSafe;
 use serde::{Deserialize, Serialize};
@@ -73,3 +74,9 @@ pub struct UserPatchDTO {
   pub avatar_url: Option<String>,
   pub is_profile_private: bool,
 }
+
+/// User's passkey, only ever sent to its owner.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, ToSchema)]
+pub struct PasskeyDTO {
+  pub passkey: Passkey,
+}
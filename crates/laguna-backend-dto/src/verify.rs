@@ -0,0 +1,21 @@
+use laguna_backend_model::consts::{EMAIL_MAX_LEN, EMAIL_MIN_LEN};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Token from verification mail.
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct VerifyDTO {
+  #[validate(non_control_character)]
+  pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct VerifyResendDTO {
+  #[validate(
+    non_control_character,
+    email,
+    length(min = "EMAIL_MIN_LEN", max = "EMAIL_MAX_LEN")
+  )]
+  pub email: String,
+}
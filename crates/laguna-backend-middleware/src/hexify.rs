@@ -6,7 +6,6 @@ use actix_web::http::Uri;
 use actix_web::web::Bytes;
 use actix_web::Error;
 use percent_encoding::percent_decode_str;
-use qstring::QString;
 
 #[derive(Debug, Clone)]
 pub struct HexifyMiddlewareFactory;
@@ -55,45 +54,24 @@ where
   forward_ready!(service);
 
   fn call(&self, mut req: ServiceRequest) -> Self::Future {
-    // TODO: Get rid of QString and use actix's methods
-    let s = QString::from(req.query_string());
+    // Work on raw query, decoded binary values can't be found in it again.
+    // Scrape requests may contain multiple info hashes, hence all pairs are checked.
+    let query = req
+      .query_string()
+      .split('&')
+      .map(|pair| match pair.split_once('=') {
+        Some(("info_hash", info_hash)) if info_hash.contains('%') => {
+          format!("info_hash={}", hexify(info_hash))
+        },
+        Some(("peer_id", peer_id)) => format!("peer_id={}", hexify(peer_id)),
+        _ => pair.to_string(),
+      })
+      .collect::<Vec<String>>()
+      .join("&");
 
-    if let Some(info_hash) = s.get("info_hash") {
-      if info_hash.contains('%') {
-        let hex_info_hash = percent_decode_str(info_hash)
-          .map(|b| format!("{:02x}", b))
-          .collect::<String>();
-        let mut parts = req.head().uri.clone().into_parts();
-        let query = parts.path_and_query.as_ref().and_then(|pq| pq.query());
-        let path = match query {
-          Some(q) => Bytes::from(format!(
-            "{}?{}",
-            req.path(),
-            q.replace(info_hash, &hex_info_hash)
-          )),
-          None => Bytes::from(req.path().to_string()),
-        };
-        parts.path_and_query = Some(PathAndQuery::from_maybe_shared(path).unwrap());
-        let uri = Uri::from_parts(parts).unwrap();
-        req.match_info_mut().get_mut().update(&uri);
-        req.head_mut().uri = uri;
-      }
-    }
-
-    if let Some(peer_id) = s.get("peer_id") {
-      let hex_peer_id = percent_decode_str(peer_id)
-        .map(|b| format!("{:02x}", b))
-        .collect::<String>();
+    if query != req.query_string() {
       let mut parts = req.head().uri.clone().into_parts();
-      let query = parts.path_and_query.as_ref().and_then(|pq| pq.query());
-      let path = match query {
-        Some(q) => Bytes::from(format!(
-          "{}?{}",
-          req.path(),
-          q.replace(peer_id, &hex_peer_id)
-        )),
-        None => Bytes::from(req.path().to_string()),
-      };
+      let path = Bytes::from(format!("{}?{}", req.path(), query));
       parts.path_and_query = Some(PathAndQuery::from_maybe_shared(path).unwrap());
       let uri = Uri::from_parts(parts).unwrap();
       req.match_info_mut().get_mut().update(&uri);
@@ -103,3 +81,9 @@ where
     self.service.call(req)
   }
 }
+
+fn hexify(value: &str) -> String {
+  percent_decode_str(value)
+    .map(|b| format!("{:02x}", b))
+    .collect::<String>()
+}
@@ -8,9 +8,28 @@ pub const PASSWORD_MIN_LEN: usize = 6;
 pub const EMAIL_MAX_LEN: usize = 50;
 pub const EMAIL_MIN_LEN: usize = 5;
 
+pub const PASSKEY_LENGTH: usize = 16;
+
+pub const TOTP_CODE_LEN: usize = 6;
+pub const TOTP_STEP_SECONDS: u64 = 30;
+pub const TOTP_RECOVERY_CODE_LEN: usize = 16;
+pub const TOTP_RECOVERY_CODE_COUNT: usize = 10;
+
 // Torrent constants
 pub const TORRENT_TITLE_MAX_LEN: usize = 100;
 pub const TORRENT_TITLE_MIN_LEN: usize = 5;
 
 pub const TORRENT_FILENAME_MAX_LEN: usize = 100;
 pub const TORRENT_FILENAME_MIN_LEN: usize = 5;
+
+pub const TORRENT_PAGE_SIZE_DEFAULT: i64 = 25;
+pub const TORRENT_PAGE_SIZE_MAX: i64 = 100;
+
+pub const TORRENT_FILE_COUNT_MAX: usize = 10_000;
+
+pub const TORRENT_DELETE_REASON_MAX_LEN: usize = 255;
+pub const TORRENT_DELETE_REASON_MIN_LEN: usize = 1;
+
+// Tracker constants
+/// Same as for UDP scrape, where it is bound by datagram size.
+pub const SCRAPE_INFO_HASH_COUNT_MAX: usize = 74;
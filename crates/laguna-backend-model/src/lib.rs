@@ -9,9 +9,13 @@ pub mod genre;
 pub mod peer;
 pub mod rating;
 pub mod role;
+pub mod session;
 pub mod speedlevel;
 pub mod swarm;
 pub mod torrent;
 pub mod torrent_rating;
+pub mod torrent_revision;
+pub mod torrent_scrape;
+pub mod totp;
 pub mod user;
 pub mod views;
@@ -3,18 +3,18 @@ use serde::{Deserialize, Serialize};
 use sqlx::types::ipnetwork::IpNetwork;
 use utoipa::ToSchema;
 
-use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH};
+use laguna_backend_tracker_common::info_hash::InfoHash;
 use laguna_backend_tracker_common::peer::PeerId;
 use uuid::Uuid;
 
 use crate::behaviour::Behaviour;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Hash, sqlx::FromRow, ToSchema)]
-pub struct Peer {
+pub struct Peer<const N: usize> {
   pub uuid: Uuid,
   pub id: PeerId,
   pub md5_hash: Option<String>,
-  pub info_hash: InfoHash<SHA1_LENGTH>,
+  pub info_hash: InfoHash<N>,
   pub ip: IpNetwork,
   pub port: i32,
   // origin is first peer in swarm, usually uploader's peer
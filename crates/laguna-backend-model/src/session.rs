@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::ipnetwork::IpNetwork;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Login session, identified by SHA-256 hash of its current refresh token.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, FromRow)]
+pub struct Session {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub token_hash: Vec<u8>,
+  pub created_at: DateTime<Utc>,
+  pub last_used_at: DateTime<Utc>,
+  pub expires_at: DateTime<Utc>,
+  pub user_agent: Option<String>,
+  pub ip: Option<IpNetwork>,
+  pub revoked_at: Option<DateTime<Utc>>,
+}
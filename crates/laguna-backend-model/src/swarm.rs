@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use crate::peer::Peer;
 
 // should be hashset but sqlx doesn't support it
-pub type Swarm = HashSet<Peer>;
+pub type Swarm<const N: usize> = HashSet<Peer<N>>;
 
 /*
 pub struct Swarm {
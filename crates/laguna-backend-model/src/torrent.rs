@@ -5,7 +5,7 @@ use chrono::{DateTime, Utc};
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 
-use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH};
+use laguna_backend_tracker_common::info_hash::{InfoHash, SHA256_LENGTH};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -17,8 +17,12 @@ use validator::Validate;
 
 #[derive(Derivative, Serialize, Deserialize, PartialEq, Eq, Clone, FromRow, Validate, ToSchema)]
 #[derivative(Debug)]
-pub struct Torrent {
-  pub info_hash: InfoHash<SHA1_LENGTH>,
+pub struct Torrent<const N: usize> {
+  /// v1 info hash (or truncated v2 info hash if torrent is v2 only) for 20 byte `N`,
+  /// v2 info hash for 32 byte `N`.
+  pub info_hash: InfoHash<N>,
+  /// Full v2 info hash of v2 and hybrid torrents.
+  pub info_hash_v2: Option<InfoHash<SHA256_LENGTH>>,
   #[derivative(Debug = "ignore")]
   pub raw: Vec<u8>,
   pub announce_url: Option<String>,
@@ -41,4 +45,8 @@ pub struct Torrent {
   pub uploaded_by: Uuid,
   pub modded_at: Option<DateTime<Utc>>,
   pub modded_by: Option<Uuid>,
+  /// Set when torrent is trashed (soft deleted).
+  pub deleted_at: Option<DateTime<Utc>>,
+  pub deleted_by: Option<Uuid>,
+  pub delete_reason: Option<String>,
 }
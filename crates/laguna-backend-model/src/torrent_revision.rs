@@ -0,0 +1,20 @@
+use crate::genre::Genre;
+use chrono::{DateTime, Utc};
+use laguna_backend_tracker_common::info_hash::InfoHash;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Single change of torrent's editable metadata, with values before and after it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, FromRow, ToSchema)]
+pub struct TorrentRevision<const N: usize> {
+  pub id: Uuid,
+  pub info_hash: InfoHash<N>,
+  pub old_nfo: Option<String>,
+  pub new_nfo: Option<String>,
+  pub old_genre: Option<Genre>,
+  pub new_genre: Option<Genre>,
+  pub modded_at: DateTime<Utc>,
+  pub modded_by: Option<Uuid>,
+}
@@ -0,0 +1,15 @@
+use laguna_backend_tracker_common::info_hash::InfoHash;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Scrape information of a torrent as reported by tracker.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema, sqlx::FromRow)]
+pub struct TorrentScrape<const N: usize> {
+  pub info_hash: InfoHash<N>,
+  /// Number of active peers with whole torrent.
+  pub complete: i64,
+  /// Number of active peers still downloading.
+  pub incomplete: i64,
+  /// Number of times torrent was downloaded to completion.
+  pub downloaded: i64,
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// TOTP second factor of user, pending until `confirmed_at` is set.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, FromRow)]
+pub struct Totp {
+  pub user_id: Uuid,
+  /// Base32 encoded shared secret.
+  pub secret: String,
+  pub created_at: DateTime<Utc>,
+  pub confirmed_at: Option<DateTime<Utc>>,
+  pub last_used_step: Option<i64>,
+}
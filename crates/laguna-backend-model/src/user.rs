@@ -1,13 +1,56 @@
 use crate::behaviour::Behaviour;
 
+use crate::consts::PASSKEY_LENGTH;
 use crate::role::Role;
 use actix_jwt_auth_middleware::FromRequest;
 use chrono::{DateTime, Utc};
 
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
+use serde_with::hex::Hex;
+use serde_with::serde_as;
 use sqlx::FromRow;
+use std::array::TryFromSliceError;
+use std::fmt;
+use std::fmt::Debug;
+use utoipa::ToSchema;
 use uuid::Uuid;
+
+/// Secret identifying user in announce URLs (`/peer/{passkey}/announce`).
+/// Generated on DB, can be reset by user, which invalidates all previously downloaded torrents.
+#[serde_as]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, sqlx::Type, ToSchema)]
+#[sqlx(transparent)]
+pub struct Passkey(#[serde_as(as = "Hex")] pub [u8; PASSKEY_LENGTH]);
+
+impl TryFrom<Vec<u8>> for Passkey {
+  type Error = TryFromSliceError;
+
+  fn try_from(vec: Vec<u8>) -> Result<Self, Self::Error> {
+    <[u8; PASSKEY_LENGTH]>::try_from(vec.as_slice()).map(Passkey)
+  }
+}
+
+impl fmt::Display for Passkey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(
+      self
+        .0
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()
+        .as_str(),
+    )
+  }
+}
+
+/// Redacted, so that passkey doesn't end up in logs.
+impl Debug for Passkey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("Passkey(***)")
+  }
+}
+
 /// User DB object.
 #[derive(Serialize, Deserialize, Debug, Clone, FromRequest, FromRow)]
 pub struct User {
@@ -31,6 +74,7 @@ pub struct User {
   pub is_donator: bool,
   pub has_verified_email: bool,
   pub is_profile_private: bool,
+  pub passkey: Passkey,
 }
 
 pub struct UserSafe {
@@ -21,37 +21,67 @@ use chrono::Duration;
 use jwt_compact::{alg::Hs256, alg::Hs256Key, TimeOptions};
 use laguna_backend_api::login;
 use laguna_backend_api::login::login;
+use laguna_backend_api::mailer::mailer_from_settings;
 use laguna_backend_api::meta;
 use laguna_backend_api::meta::{get_app_info, healthcheck};
+use laguna_backend_api::password;
+use laguna_backend_api::password::{password_forgot, password_reset, user_me_password_change};
 use laguna_backend_api::peer;
-use laguna_backend_api::peer::peer_announce;
+use laguna_backend_api::peer::{peer_announce, peer_scrape};
 use laguna_backend_api::rating;
 use laguna_backend_api::rating::{rating_create, rating_delete, rating_torrent_average};
 use laguna_backend_api::register;
 use laguna_backend_api::register::register;
+use laguna_backend_api::session;
+use laguna_backend_api::session::{
+  session_logout, session_refresh, user_me_session_revoke, user_me_sessions,
+};
 use laguna_backend_api::stats;
 use laguna_backend_api::stats::{
   stats_joint_get, stats_peer_get, stats_torrent_get, stats_user_get,
 };
+use laguna_backend_api::throttle;
+use laguna_backend_api::throttle::user_unlock;
 use laguna_backend_api::torrent;
 use laguna_backend_api::torrent::{
-  torrent_delete, torrent_get, torrent_get_raw, torrent_patch, torrent_put, torrent_swarm,
+  torrent_delete, torrent_files, torrent_get, torrent_get_raw, torrent_magnet, torrent_patch,
+  torrent_purge, torrent_put, torrent_restore, torrent_revision_revert, torrent_revisions,
+  torrent_search, torrent_swarm,
+};
+use laguna_backend_api::totp;
+use laguna_backend_api::totp::{
+  login_totp, login_totp_enroll, user_me_totp_confirm, user_me_totp_disable, user_me_totp_enroll,
 };
 use laguna_backend_api::user;
 use laguna_backend_api::user::{
-  user_get, user_me_delete, user_me_get, user_patch, user_patch_me, user_role_change,
-  user_torrents_get,
+  user_ban, user_get, user_me_delete, user_me_get, user_me_passkey_reset, user_patch,
+  user_patch_me, user_role_change, user_torrents_get, user_unban,
 };
+use laguna_backend_api::verify;
+use laguna_backend_api::verify::{verify_email, verify_email_resend};
+use laguna_backend_api::ws;
+use laguna_backend_api::ws::peer_ws;
 use laguna_backend_dto::already_exists::AlreadyExistsDTO;
 use laguna_backend_dto::login::LoginDTO;
 use laguna_backend_dto::meta::AppInfoDTO;
+use laguna_backend_dto::password::{PasswordChangeDTO, PasswordForgotDTO, PasswordResetDTO};
 use laguna_backend_dto::peer::PeerDTO;
 use laguna_backend_dto::rating::RatingDTO;
 use laguna_backend_dto::register::RegisterDTO;
 use laguna_backend_dto::role::RoleChangeDTO;
-use laguna_backend_dto::torrent::{TorrentDTO, TorrentPatchDTO, TorrentPutDTO};
+use laguna_backend_dto::session::SessionDTO;
+use laguna_backend_dto::torrent::{
+  FileNodeDTO, MagnetDTO, SortOrder, TorrentDTO, TorrentFileDTO, TorrentFilesDTO, TorrentPageDTO,
+  TorrentPatchDTO, TorrentPutDTO, TorrentSort, TorrentSummaryDTO, TorrentTrashDTO,
+};
 use laguna_backend_dto::torrent_rating::TorrentRatingDTO;
-use laguna_backend_dto::user::{UserDTO, UserPatchDTO};
+use laguna_backend_dto::torrent_revision::TorrentRevisionDTO;
+use laguna_backend_dto::totp::{
+  LoginChallengeDTO, LoginTotpDTO, LoginTotpEnrollDTO, TotpCodeDTO, TotpEnrollDTO,
+};
+use laguna_backend_dto::user::{PasskeyDTO, UserDTO, UserPatchDTO};
+use laguna_backend_dto::verify::{VerifyDTO, VerifyResendDTO};
+use laguna_backend_model::user::Passkey;
 use laguna_backend_model::views::stats::{JointStats, PeerStats, TorrentStats, UserStats};
 
 use laguna_backend_middleware::auth::AuthorizationMiddlewareFactory;
@@ -62,12 +92,12 @@ use laguna_backend_model::genre::Genre;
 use laguna_backend_model::peer::Peer;
 use laguna_backend_model::role::Role;
 use laguna_backend_model::speedlevel::SpeedLevel;
-use laguna_backend_model::torrent::Torrent;
 use laguna_backend_model::torrent_rating::TorrentRating;
 use laguna_backend_tracker_common::announce::AnnounceEvent;
 use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH, SHA256_LENGTH};
-use laguna_backend_tracker_common::peer::{PeerBin, PeerDict, PeerId, PeerStream};
+use laguna_backend_tracker_common::peer::{PeerBin, PeerBin6, PeerDict, PeerId, PeerStream};
 use laguna_backend_tracker_http::announce::{Announce, AnnounceReply};
+use laguna_backend_tracker_http::scrape::{Scrape, ScrapeFile, ScrapeReply};
 use laguna_config::make_overridable_with_env_vars;
 use laguna_config::{Settings, LAGUNA_CONFIG};
 use secrecy::ExposeSecret;
@@ -123,19 +153,34 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
   let secret_key = setup_secret_key(&settings);
   let (token_signer, authority) = crate::setup_authority!(secret_key, settings);
   let argon_context = setup_argon_context(&settings);
+  let mailer = mailer_from_settings(&settings.application.mail);
 
   move |service_config: &mut ServiceConfig| {
     service_config
       .app_data(web::Data::new(argon_context.clone()))
       // AuthenticationService by default doesnt include token_signer into app_data, hence we get it from setup_authority!() which is kinda hacky.
       .app_data(web::Data::new(token_signer.clone()))
+      // Refresh tokens carry session id, so they are signed and verified with secret key directly.
+      .app_data(web::Data::new(secret_key.clone()))
       .app_data(web::Data::new(
         settings.application.tracker.announce_url.clone(),
       ))
+      .app_data(web::Data::new(settings.application.tracker.clone()))
+      .app_data(web::Data::new(settings.application.auth.clone()))
+      .app_data(web::Data::new(settings.application.frontend.clone()))
+      .app_data(web::Data::from(mailer.clone()))
       .service(
         web::scope("/api/user/auth")
           .route("/register", web::post().to(register))
-          .route("/login", web::post().to(login)),
+          .route("/login", web::post().to(login))
+          .route("/login/totp", web::post().to(login_totp))
+          .route("/login/totp/enroll", web::post().to(login_totp_enroll))
+          .route("/refresh", web::post().to(session_refresh))
+          .route("/logout", web::post().to(session_logout))
+          .route("/verify", web::post().to(verify_email))
+          .route("/verify/resend", web::post().to(verify_email_resend))
+          .route("/password/forgot", web::post().to(password_forgot))
+          .route("/password/reset", web::post().to(password_reset)),
       )
       .service(
         web::scope("/misc")
@@ -149,7 +194,28 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
           .route(
             "/v2/announce",
             web::get().to(peer_announce::<SHA256_LENGTH>),
-          ),
+          )
+          .route("/scrape", web::get().to(peer_scrape::<SHA1_LENGTH>))
+          .route("/v2/scrape", web::get().to(peer_scrape::<SHA256_LENGTH>))
+          .route("/ws", web::get().to(peer_ws))
+          // Legacy routes above have to be matched first, "v2" is not a passkey.
+          .route(
+            "/{passkey}/announce",
+            web::get().to(peer_announce::<SHA1_LENGTH>),
+          )
+          .route(
+            "/{passkey}/v2/announce",
+            web::get().to(peer_announce::<SHA256_LENGTH>),
+          )
+          .route(
+            "/{passkey}/scrape",
+            web::get().to(peer_scrape::<SHA1_LENGTH>),
+          )
+          .route(
+            "/{passkey}/v2/scrape",
+            web::get().to(peer_scrape::<SHA256_LENGTH>),
+          )
+          .route("/{passkey}/ws", web::get().to(peer_ws)),
       )
       // https://github.com/cloud-annotations/docusaurus-openapi/issues/231
       .service(
@@ -179,13 +245,42 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
                   .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
               )
               .route("/{id}/role_change", web::patch().to(user_role_change))
+              .route(
+                "/{id}/ban",
+                web::post()
+                  .to(user_ban)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
+              .route(
+                "/{id}/ban",
+                web::delete()
+                  .to(user_unban)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
+              .route(
+                "/{id}/lock",
+                web::delete()
+                  .to(user_unlock)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Admin)),
+              )
               .route("/me", web::get().to(user_me_get))
               .route("/{id}", web::get().to(user_get))
               .route("/me", web::delete().to(user_me_delete))
+              .route("/me/passkey", web::post().to(user_me_passkey_reset))
+              .route("/me/password", web::post().to(user_me_password_change))
+              .route("/me/sessions", web::get().to(user_me_sessions))
+              .route("/me/totp", web::post().to(user_me_totp_enroll))
+              .route("/me/totp", web::delete().to(user_me_totp_disable))
+              .route("/me/totp/confirm", web::post().to(user_me_totp_confirm))
+              .route(
+                "/me/sessions/{id}",
+                web::delete().to(user_me_session_revoke),
+              )
               .route("/{id}/torrents", web::get().to(user_torrents_get)),
           )
           .service(
             web::scope("/torrent")
+              .route("/", web::get().to(torrent_search))
               .route("/{info_hash}", web::get().to(torrent_get::<SHA1_LENGTH>))
               .route(
                 "/{info_hash}/raw",
@@ -199,6 +294,22 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
                 "/v2/{info_hash}/raw",
                 web::get().to(torrent_get_raw::<SHA256_LENGTH>),
               )
+              .route(
+                "/{info_hash}/files",
+                web::get().to(torrent_files::<SHA1_LENGTH>),
+              )
+              .route(
+                "/v2/{info_hash}/files",
+                web::get().to(torrent_files::<SHA256_LENGTH>),
+              )
+              .route(
+                "/{info_hash}/magnet",
+                web::get().to(torrent_magnet::<SHA1_LENGTH>),
+              )
+              .route(
+                "/v2/{info_hash}/magnet",
+                web::get().to(torrent_magnet::<SHA256_LENGTH>),
+              )
               .route(
                 "/",
                 web::put()
@@ -241,6 +352,30 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
                   .to(torrent_patch::<SHA256_LENGTH>)
                   .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
               )
+              .route(
+                "/{info_hash}/revisions",
+                web::get()
+                  .to(torrent_revisions::<SHA1_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
+              .route(
+                "/v2/{info_hash}/revisions",
+                web::get()
+                  .to(torrent_revisions::<SHA256_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
+              .route(
+                "/{info_hash}/revisions/{id}/revert",
+                web::post()
+                  .to(torrent_revision_revert::<SHA1_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
+              .route(
+                "/v2/{info_hash}/revisions/{id}/revert",
+                web::post()
+                  .to(torrent_revision_revert::<SHA256_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
               .route(
                 "/{info_hash}",
                 web::delete()
@@ -253,6 +388,30 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
                   .to(torrent_delete::<SHA256_LENGTH>)
                   .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
               )
+              .route(
+                "/{info_hash}/restore",
+                web::post()
+                  .to(torrent_restore::<SHA1_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
+              .route(
+                "/v2/{info_hash}/restore",
+                web::post()
+                  .to(torrent_restore::<SHA256_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Mod)),
+              )
+              .route(
+                "/{info_hash}/purge",
+                web::delete()
+                  .to(torrent_purge::<SHA1_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Admin)),
+              )
+              .route(
+                "/v2/{info_hash}/purge",
+                web::delete()
+                  .to(torrent_purge::<SHA256_LENGTH>)
+                  .wrap(AuthorizationMiddlewareFactory::new(Role::Admin)),
+              )
               .route(
                 "/{info_hash}/swarm",
                 web::get().to(torrent_swarm::<SHA1_LENGTH>),
@@ -274,19 +433,44 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
     schemas(
       UserDTO,
       UserPatchDTO,
+      PasskeyDTO,
+      Passkey,
       TorrentPutDTO,
-      TorrentDTO,
-      Torrent,
+      TorrentDTO::<SHA1_LENGTH>,
+      TorrentDTO::<SHA256_LENGTH>,
+      TorrentRevisionDTO::<SHA1_LENGTH>,
+      TorrentRevisionDTO::<SHA256_LENGTH>,
+      TorrentFileDTO,
+      TorrentFilesDTO,
+      MagnetDTO,
+      FileNodeDTO,
       Genre,
       TorrentPatchDTO,
+      TorrentTrashDTO,
+      TorrentSummaryDTO,
+      TorrentPageDTO,
+      TorrentSort,
+      SortOrder,
       RatingDTO::<SHA1_LENGTH>,
       RatingDTO::<SHA256_LENGTH>,
       TorrentRatingDTO,
       TorrentRating,
       RegisterDTO,
       LoginDTO,
+      VerifyDTO,
+      VerifyResendDTO,
+      PasswordChangeDTO,
+      PasswordForgotDTO,
+      PasswordResetDTO,
+      SessionDTO,
+      TotpEnrollDTO,
+      TotpCodeDTO,
+      LoginChallengeDTO,
+      LoginTotpDTO,
+      LoginTotpEnrollDTO,
       AppInfoDTO,
-      PeerDTO,
+      PeerDTO::<SHA1_LENGTH>,
+      PeerDTO::<SHA256_LENGTH>,
       AlreadyExistsDTO,
       Role,
       Behaviour,
@@ -298,11 +482,19 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
       Announce::<SHA256_LENGTH>,
       AnnounceEvent,
       AnnounceReply,
+      Scrape::<SHA1_LENGTH>,
+      Scrape::<SHA256_LENGTH>,
+      ScrapeFile::<SHA1_LENGTH>,
+      ScrapeFile::<SHA256_LENGTH>,
+      ScrapeReply::<SHA1_LENGTH>,
+      ScrapeReply::<SHA256_LENGTH>,
       RoleChangeDTO,
-      Peer,
+      Peer::<SHA1_LENGTH>,
+      Peer::<SHA256_LENGTH>,
       PeerStream,
       PeerDict,
       PeerBin,
+      PeerBin6,
       JointStats,
       PeerStats,
       UserStats,
@@ -312,30 +504,64 @@ pub fn get_config_fn(settings: Settings) -> impl FnOnce(&mut ServiceConfig) {
   paths(
     user::user_me_get,
     user::user_me_delete,
+    user::user_me_passkey_reset,
+    password::user_me_password_change,
+    session::user_me_sessions,
+    session::user_me_session_revoke,
+    totp::user_me_totp_enroll,
+    totp::user_me_totp_confirm,
+    totp::user_me_totp_disable,
     user::user_patch_me,
     user::user_get,
     user::user_patch,
     user::user_torrents_get,
+    user::user_ban,
+    user::user_unban,
+    throttle::user_unlock,
+    torrent::torrent_search,
     torrent::torrent_get::<SHA1_LENGTH>,
+    torrent::torrent_files::<SHA1_LENGTH>,
+    torrent::torrent_magnet::<SHA1_LENGTH>,
     torrent::torrent_put::<SHA1_LENGTH>,
     torrent::torrent_patch::<SHA1_LENGTH>,
+    torrent::torrent_revisions::<SHA1_LENGTH>,
+    torrent::torrent_revision_revert::<SHA1_LENGTH>,
     torrent::torrent_delete::<SHA1_LENGTH>,
+    torrent::torrent_restore::<SHA1_LENGTH>,
+    torrent::torrent_purge::<SHA1_LENGTH>,
     torrent::torrent_swarm::<SHA1_LENGTH>,
     rating::rating_create::<SHA1_LENGTH>,
     rating::rating_delete::<SHA1_LENGTH>,
     rating::rating_torrent_average::<SHA1_LENGTH>,
     peer::peer_announce::<SHA1_LENGTH>,
+    peer::peer_scrape::<SHA1_LENGTH>,
     torrent::torrent_put::<SHA256_LENGTH>,
     torrent::torrent_get::<SHA256_LENGTH>,
+    torrent::torrent_files::<SHA256_LENGTH>,
+    torrent::torrent_magnet::<SHA256_LENGTH>,
     torrent::torrent_patch::<SHA256_LENGTH>,
+    torrent::torrent_revisions::<SHA256_LENGTH>,
+    torrent::torrent_revision_revert::<SHA256_LENGTH>,
     torrent::torrent_delete::<SHA256_LENGTH>,
+    torrent::torrent_restore::<SHA256_LENGTH>,
+    torrent::torrent_purge::<SHA256_LENGTH>,
     torrent::torrent_swarm::<SHA256_LENGTH>,
     rating::rating_create::<SHA256_LENGTH>,
     rating::rating_delete::<SHA256_LENGTH>,
     rating::rating_torrent_average::<SHA256_LENGTH>,
     peer::peer_announce::<SHA256_LENGTH>,
+    peer::peer_scrape::<SHA256_LENGTH>,
+    ws::peer_ws,
     register::register,
     login::login,
+    totp::login_totp,
+    totp::login_totp_enroll,
+    session::session_refresh,
+    session::session_logout,
+    verify::verify_email,
+    verify::verify_email_resend,
+    password::password_forgot,
+    password::password_reset,
     meta::get_app_info,
     meta::healthcheck,
     stats::stats_joint_get,
@@ -372,22 +598,25 @@ macro_rules! setup_authority {
   ($secret_key:ident, $settings:ident) => {{
     use ::laguna_backend_middleware::consts::ACCESS_TOKEN_HEADER_NAME;
     use ::laguna_backend_middleware::consts::REFRESH_TOKEN_HEADER_NAME;
+    let token_signer = TokenSigner::<UserDTO, Hs256>::new()
+      .signing_key($secret_key.clone())
+      .algorithm(Hs256)
+      .access_token_name(ACCESS_TOKEN_HEADER_NAME)
+      .refresh_token_name(REFRESH_TOKEN_HEADER_NAME)
+      .access_token_lifetime(Duration::seconds(
+        $settings.application.auth.access_token_lifetime_seconds,
+      ))
+      .refresh_token_lifetime(Duration::seconds(
+        $settings.application.auth.refresh_token_lifetime_seconds,
+      ))
+      .build()
+      .expect("Cannot create token signer");
     (
-      TokenSigner::<UserDTO, Hs256>::new()
-        .signing_key($secret_key.clone())
-        .algorithm(Hs256)
-        .access_token_name(ACCESS_TOKEN_HEADER_NAME)
-        .refresh_token_name(REFRESH_TOKEN_HEADER_NAME)
-        .access_token_lifetime(Duration::seconds(
-          $settings.application.auth.access_token_lifetime_seconds,
-        ))
-        .refresh_token_lifetime(Duration::seconds(
-          $settings.application.auth.refresh_token_lifetime_seconds,
-        ))
-        .build()
-        .expect("Cannot create token signer"),
+      token_signer.clone(),
+      // Expired access tokens are renewed with refresh token, if refresh authorizer allows it.
       Authority::<UserDTO, Hs256, _, _>::new()
-        .refresh_authorizer(|| async move { Ok(()) })
+        .refresh_authorizer(::laguna_backend_api::session::refresh_authorizer)
+        .token_signer(Some(token_signer))
         .enable_header_tokens(true)
         .enable_cookie_tokens(true)
         .time_options(TimeOptions::from_leeway(Duration::seconds(5)))
@@ -440,7 +669,8 @@ pub fn setup_cors(settings: &Settings) -> Cors {
   let fe_ip = settings.application.frontend.address().ip();
   let fe_port = settings.application.frontend.address().port();
   let fe_addr = format!(
-    "http://{}:{}",
+    "{}://{}:{}",
+    settings.application.frontend.scheme,
     if fe_ip.is_loopback() {
       String::from("localhost")
     } else {
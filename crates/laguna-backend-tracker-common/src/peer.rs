@@ -1,7 +1,7 @@
 use std::array::TryFromSliceError;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 use bendy::encoding::{AsString, ToBencode};
 use serde::{Deserialize, Serialize};
@@ -13,6 +13,7 @@ pub const PEER_ID_LENGTH: usize = 20;
 pub const PEER_CLIENT_LENGTH: usize = 2;
 
 pub const PEER_BIN_DICT_LENGTH: usize = 6;
+pub const PEER_BIN6_DICT_LENGTH: usize = 18;
 
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, sqlx::Type, ToSchema)]
@@ -499,29 +500,43 @@ pub struct PeerDict {
 pub struct PeerBin(pub [u8; PEER_BIN_DICT_LENGTH]);
 
 impl PeerBin {
-  pub fn from_socket(ip_addr: IpAddr, port: u16) -> Self {
+  pub fn from_socket(ip_addr: Ipv4Addr, port: u16) -> Self {
     let mut buf = [0; PEER_BIN_DICT_LENGTH];
-    let octets = match ip_addr {
-      IpAddr::V4(ip) => ip.octets(),
-      _ => unreachable!(),
-    };
-    buf[..4].copy_from_slice(&octets);
+    buf[..4].copy_from_slice(&ip_addr.octets());
     buf[4..].copy_from_slice(&port.to_be_bytes());
     Self(buf)
   }
 }
 
+/// IPv6 peer binary representation.
+/// First 16 bytes are IP address, last 2 bytes are port.
+/// Network byte order (big endian).
+/// Sent in `peers6` next to IPv4 `peers` in compact announce replies.
+/// See: <http://bittorrent.org/beps/bep_0007.html>
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PeerBin6(pub [u8; PEER_BIN6_DICT_LENGTH]);
+
+impl PeerBin6 {
+  pub fn from_socket(ip_addr: Ipv6Addr, port: u16) -> Self {
+    let mut buf = [0; PEER_BIN6_DICT_LENGTH];
+    buf[..16].copy_from_slice(&ip_addr.octets());
+    buf[16..].copy_from_slice(&port.to_be_bytes());
+    Self(buf)
+  }
+}
+
 impl ToBencode for PeerDict {
   const MAX_DEPTH: usize = 10;
   fn encode(
     &self,
     encoder: bendy::encoding::SingleItemEncoder,
   ) -> Result<(), bendy::encoding::Error> {
+    // Keys have to be emitted in sorted order.
     encoder.emit_dict(|mut d| {
+      d.emit_pair(b"ip", self.ip.to_string())?;
       if let Some(peer_id) = &self.peer_id {
         d.emit_pair(b"peer id", AsString(peer_id.0))?;
       }
-      d.emit_pair(b"ip", self.ip.to_string())?;
       d.emit_pair(b"port", self.port)?;
       Ok(())
     })
@@ -538,6 +553,16 @@ impl ToBencode for PeerBin {
   }
 }
 
+impl ToBencode for PeerBin6 {
+  const MAX_DEPTH: usize = 10;
+  fn encode(
+    &self,
+    encoder: bendy::encoding::SingleItemEncoder,
+  ) -> Result<(), bendy::encoding::Error> {
+    encoder.emit(&AsString(self.0))
+  }
+}
+
 impl ToBencode for PeerStream {
   const MAX_DEPTH: usize = 10;
   fn encode(
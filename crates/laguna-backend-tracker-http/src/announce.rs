@@ -1,6 +1,6 @@
 use std::net::IpAddr;
 
-use bendy::encoding::{self, SingleItemEncoder, ToBencode};
+use bendy::encoding::{self, AsString, SingleItemEncoder, ToBencode};
 use laguna_backend_tracker_common::{
   announce::AnnounceEvent,
   info_hash::InfoHash,
@@ -15,7 +15,8 @@ use utoipa::ToSchema;
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Announce<const N: usize> {
-  pub down_hash: DownloadHash,
+  /// Legacy per-download hash, announce URLs now carry user's passkey in path instead.
+  pub down_hash: Option<DownloadHash>,
   pub info_hash: InfoHash<N>,
   pub peer_id: PeerId,
   pub ip: Option<IpAddr>,
@@ -55,6 +56,9 @@ pub struct AnnounceReply {
   pub complete: u64,
   pub incomplete: u64,
   pub peers: PeerStream,
+  /// Compact IPv6 peers, see [`laguna_backend_tracker_common::peer::PeerBin6`].
+  /// Only present in compact replies, IPv6 peers are in `peers` otherwise.
+  pub peers6: Option<Vec<u8>>,
 }
 
 impl ToBencode for AnnounceReply {
@@ -77,6 +81,9 @@ impl ToBencode for AnnounceReply {
       d.emit_pair(b"complete", self.complete)?;
       d.emit_pair(b"incomplete", self.incomplete)?;
       d.emit_pair(b"peers", &self.peers)?;
+      if let Some(ref peers6) = self.peers6 {
+        d.emit_pair(b"peers6", AsString(peers6))?;
+      }
       Ok(())
     })
   }
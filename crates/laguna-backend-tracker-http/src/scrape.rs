@@ -0,0 +1,99 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use bendy::encoding::{self, SingleItemEncoder, ToBencode};
+use laguna_backend_tracker_common::info_hash::InfoHash;
+
+use serde::de::{IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use laguna_backend_model::download::DownloadHash;
+use utoipa::ToSchema;
+
+/// Scrape request (BEP 48).
+/// `info_hash` can be repeated in query, hence `Deserialize` is implemented by hand.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Scrape<const N: usize> {
+  /// Legacy per-download hash, see [`crate::announce::Announce::down_hash`].
+  pub down_hash: Option<DownloadHash>,
+  pub info_hashes: Vec<InfoHash<N>>,
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Scrape<N> {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    struct ScrapeVisitor<const N: usize>(PhantomData<Scrape<N>>);
+
+    impl<'de, const N: usize> Visitor<'de> for ScrapeVisitor<N> {
+      type Value = Scrape<N>;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("scrape query with info_hash parameters")
+      }
+
+      fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+      where
+        A: MapAccess<'de>,
+      {
+        let mut down_hash = None;
+        let mut info_hashes = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+          match key.as_str() {
+            "down_hash" => down_hash = Some(map.next_value()?),
+            "info_hash" => info_hashes.push(map.next_value()?),
+            _ => {
+              map.next_value::<IgnoredAny>()?;
+            },
+          }
+        }
+        Ok(Scrape {
+          down_hash,
+          info_hashes,
+        })
+      }
+    }
+
+    deserializer.deserialize_map(ScrapeVisitor(PhantomData))
+  }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScrapeFile<const N: usize> {
+  pub info_hash: InfoHash<N>,
+  /// Number of seeders.
+  pub complete: i64,
+  /// Number of leechers.
+  pub incomplete: i64,
+  /// Number of times torrent was downloaded.
+  pub downloaded: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScrapeReply<const N: usize> {
+  pub files: Vec<ScrapeFile<N>>,
+}
+
+impl<const N: usize> ToBencode for ScrapeReply<N> {
+  const MAX_DEPTH: usize = 10;
+  fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+    encoder.emit_dict(|mut d| {
+      d.emit_pair_with(b"files", |encoder| {
+        // Files are keyed by raw info hash.
+        encoder.emit_unsorted_dict(|d| {
+          for file in &self.files {
+            d.emit_pair_with(&file.info_hash.0, |encoder| {
+              encoder.emit_dict(|mut d| {
+                d.emit_pair(b"complete", file.complete)?;
+                d.emit_pair(b"downloaded", file.downloaded)?;
+                d.emit_pair(b"incomplete", file.incomplete)
+              })
+            })?;
+          }
+          Ok(())
+        })
+      })
+    })
+  }
+}
@@ -0,0 +1,66 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Length of a connection id validity window.
+/// A connection id is accepted in the window it was issued in and in the next one,
+/// so clients can use it for at least 1 minute and at most 2 minutes (as per BEP 15).
+pub const CONNECTION_ID_WINDOW_SECONDS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionId(pub i64);
+
+/// Issues and validates connection ids without storing any per-client state.
+/// Connection id is a keyed hash of client's socket address and current time window,
+/// hence it can be validated by recomputing it.
+pub struct ConnectionIdIssuer {
+  secret: [u8; 32],
+}
+
+impl ConnectionIdIssuer {
+  pub fn new() -> Self {
+    let mut secret = [0; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    Self { secret }
+  }
+
+  pub fn issue(&self, addr: SocketAddr) -> ConnectionId {
+    self.issue_in_window(addr, current_window())
+  }
+
+  pub fn validate(&self, connection_id: ConnectionId, addr: SocketAddr) -> bool {
+    let window = current_window();
+    connection_id == self.issue_in_window(addr, window)
+      || connection_id == self.issue_in_window(addr, window.saturating_sub(1))
+  }
+
+  fn issue_in_window(&self, addr: SocketAddr, window: u64) -> ConnectionId {
+    let ip_octets = match addr.ip() {
+      IpAddr::V4(ip) => ip.octets().to_vec(),
+      IpAddr::V6(ip) => ip.octets().to_vec(),
+    };
+    let digest = Sha256::new()
+      .chain_update(self.secret)
+      .chain_update(ip_octets)
+      .chain_update(addr.port().to_be_bytes())
+      .chain_update(window.to_be_bytes())
+      .finalize();
+    ConnectionId(i64::from_be_bytes(digest[..8].try_into().unwrap()))
+  }
+}
+
+impl Default for ConnectionIdIssuer {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn current_window() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .expect("System time is before UNIX epoch")
+    .as_secs()
+    / CONNECTION_ID_WINDOW_SECONDS
+}
@@ -1,3 +1,23 @@
 #![doc(html_logo_url = "https://sloveniaengineering.github.io/laguna-backend/logo.png")]
 #![doc(html_favicon_url = "https://sloveniaengineering.github.io/laguna-backend/favicon.ico")]
 #![doc(issue_tracker_base_url = "https://github.com/SloveniaEngineering/laguna-backend")]
+//! UDP tracker protocol.
+//! See: <http://bittorrent.org/beps/bep_0015.html>
+//! and: <http://bittorrent.org/beps/bep_0041.html>
+pub mod connection;
+pub mod request;
+pub mod response;
+
+/// Magic constant every connect request starts with.
+pub const PROTOCOL_ID: i64 = 0x41727101980;
+
+pub const ACTION_CONNECT: i32 = 0;
+pub const ACTION_ANNOUNCE: i32 = 1;
+pub const ACTION_SCRAPE: i32 = 2;
+pub const ACTION_ERROR: i32 = 3;
+
+/// Max. number of info hashes in a single scrape request (fits into one datagram).
+pub const MAX_SCRAPE_TORRENTS: usize = 74;
+
+/// Max. size of a UDP tracker datagram we are willing to read.
+pub const MAX_PACKET_SIZE: usize = 1496;
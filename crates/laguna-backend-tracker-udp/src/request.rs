@@ -0,0 +1,261 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::net::Ipv4Addr;
+
+use laguna_backend_tracker_common::announce::AnnounceEvent;
+use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH};
+use laguna_backend_tracker_common::peer::{PeerId, PEER_ID_LENGTH};
+
+use crate::connection::ConnectionId;
+use crate::{ACTION_ANNOUNCE, ACTION_CONNECT, ACTION_SCRAPE, MAX_SCRAPE_TORRENTS, PROTOCOL_ID};
+
+/// BEP 41 option types.
+const OPTION_END_OF_OPTIONS: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_URL_DATA: u8 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum UdpRequest {
+  Connect(ConnectRequest),
+  Announce(AnnounceRequest),
+  Scrape(ScrapeRequest),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConnectRequest {
+  pub transaction_id: i32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AnnounceRequest {
+  pub connection_id: ConnectionId,
+  pub transaction_id: i32,
+  pub info_hash: InfoHash<SHA1_LENGTH>,
+  pub peer_id: PeerId,
+  pub downloaded: i64,
+  pub left: i64,
+  pub uploaded: i64,
+  pub event: AnnounceEvent,
+  /// `None` if client wants us to use the sender address.
+  pub ip: Option<Ipv4Addr>,
+  pub key: u32,
+  /// `None` if client wants the default amount of peers.
+  pub num_want: Option<i32>,
+  pub port: u16,
+  /// Path and query of the announce URL (BEP 41), this is where passkey is.
+  pub url_data: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScrapeRequest {
+  pub connection_id: ConnectionId,
+  pub transaction_id: i32,
+  pub info_hashes: Vec<InfoHash<SHA1_LENGTH>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdpRequestError {
+  /// Packet cannot be identified at all, such packets should be ignored.
+  Malformed,
+  UnknownAction {
+    transaction_id: i32,
+    action: i32,
+  },
+  UnknownEvent {
+    transaction_id: i32,
+    event: i32,
+  },
+  Invalid {
+    transaction_id: i32,
+  },
+  TooManyInfoHashes {
+    transaction_id: i32,
+  },
+  /// Connection id was not issued to this address or it has expired.
+  InvalidConnectionId {
+    transaction_id: i32,
+  },
+}
+
+impl UdpRequestError {
+  /// Transaction id to reply to, `None` if packet should be ignored.
+  pub fn transaction_id(&self) -> Option<i32> {
+    match self {
+      Self::Malformed => None,
+      Self::UnknownAction { transaction_id, .. }
+      | Self::UnknownEvent { transaction_id, .. }
+      | Self::Invalid { transaction_id }
+      | Self::TooManyInfoHashes { transaction_id }
+      | Self::InvalidConnectionId { transaction_id } => Some(*transaction_id),
+    }
+  }
+}
+
+impl fmt::Display for UdpRequestError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Malformed => f.write_str("Paket je poškodovan."),
+      Self::UnknownAction { action, .. } => f.write_fmt(format_args!("Neznana akcija {}.", action)),
+      Self::UnknownEvent { event, .. } => f.write_fmt(format_args!("Neznan dogodek {}.", event)),
+      Self::Invalid { .. } => f.write_str("Paket ni v pravilni obliki."),
+      Self::TooManyInfoHashes { .. } => f.write_fmt(format_args!(
+        "Scrape lahko vsebuje največ {} info_hash.",
+        MAX_SCRAPE_TORRENTS
+      )),
+      Self::InvalidConnectionId { .. } => f.write_str("Neveljaven ali potekel connection_id."),
+    }
+  }
+}
+
+impl UdpRequest {
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, UdpRequestError> {
+    let mut reader = Reader::new(bytes);
+    let (connection_id, action, transaction_id) = match (reader.i64(), reader.i32(), reader.i32()) {
+      (Some(connection_id), Some(action), Some(transaction_id)) => {
+        (connection_id, action, transaction_id)
+      },
+      _ => return Err(UdpRequestError::Malformed),
+    };
+    let invalid = UdpRequestError::Invalid { transaction_id };
+    match action {
+      ACTION_CONNECT if connection_id == PROTOCOL_ID => {
+        Ok(Self::Connect(ConnectRequest { transaction_id }))
+      },
+      ACTION_CONNECT => Err(UdpRequestError::Malformed),
+      ACTION_ANNOUNCE => {
+        let info_hash = InfoHash(reader.array::<SHA1_LENGTH>().ok_or(invalid)?);
+        let peer_id = PeerId(reader.array::<PEER_ID_LENGTH>().ok_or(invalid)?);
+        let downloaded = reader.i64().ok_or(invalid)?;
+        let left = reader.i64().ok_or(invalid)?;
+        let uploaded = reader.i64().ok_or(invalid)?;
+        let event = match reader.i32().ok_or(invalid)? {
+          0 => AnnounceEvent::Empty,
+          1 => AnnounceEvent::Completed,
+          2 => AnnounceEvent::Started,
+          3 => AnnounceEvent::Stopped,
+          event => {
+            return Err(UdpRequestError::UnknownEvent {
+              transaction_id,
+              event,
+            })
+          },
+        };
+        let ip = match reader.u32().ok_or(invalid)? {
+          0 => None,
+          ip => Some(Ipv4Addr::from(ip)),
+        };
+        let key = reader.u32().ok_or(invalid)?;
+        let num_want = match reader.i32().ok_or(invalid)? {
+          num_want if num_want < 0 => None,
+          num_want => Some(num_want),
+        };
+        let port = reader.u16().ok_or(invalid)?;
+        Ok(Self::Announce(AnnounceRequest {
+          connection_id: ConnectionId(connection_id),
+          transaction_id,
+          info_hash,
+          peer_id,
+          downloaded,
+          left,
+          uploaded,
+          event,
+          ip,
+          key,
+          num_want,
+          port,
+          url_data: reader.url_data(),
+        }))
+      },
+      ACTION_SCRAPE => {
+        let mut info_hashes = Vec::new();
+        while let Some(info_hash) = reader.array::<SHA1_LENGTH>() {
+          info_hashes.push(InfoHash(info_hash));
+        }
+        if info_hashes.is_empty() {
+          return Err(invalid);
+        }
+        if info_hashes.len() > MAX_SCRAPE_TORRENTS {
+          return Err(UdpRequestError::TooManyInfoHashes { transaction_id });
+        }
+        Ok(Self::Scrape(ScrapeRequest {
+          connection_id: ConnectionId(connection_id),
+          transaction_id,
+          info_hashes,
+        }))
+      },
+      action => Err(UdpRequestError::UnknownAction {
+        transaction_id,
+        action,
+      }),
+    }
+  }
+}
+
+/// Big endian (network byte order) reader over a datagram.
+struct Reader<'a> {
+  bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+  fn new(bytes: &'a [u8]) -> Self {
+    Self { bytes }
+  }
+
+  fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+    if self.bytes.len() < N {
+      return None;
+    }
+    let (head, tail) = self.bytes.split_at(N);
+    self.bytes = tail;
+    Some(head.try_into().unwrap())
+  }
+
+  fn u8(&mut self) -> Option<u8> {
+    self.array::<1>().map(|b| b[0])
+  }
+
+  fn u16(&mut self) -> Option<u16> {
+    self.array().map(u16::from_be_bytes)
+  }
+
+  fn u32(&mut self) -> Option<u32> {
+    self.array().map(u32::from_be_bytes)
+  }
+
+  fn i32(&mut self) -> Option<i32> {
+    self.array().map(i32::from_be_bytes)
+  }
+
+  fn i64(&mut self) -> Option<i64> {
+    self.array().map(i64::from_be_bytes)
+  }
+
+  /// Reads BEP 41 options and concatenates all URLData options.
+  /// Truncated options are ignored.
+  fn url_data(&mut self) -> Option<String> {
+    let mut url_data = Vec::new();
+    while let Some(option) = self.u8() {
+      match option {
+        OPTION_END_OF_OPTIONS => break,
+        OPTION_NOP => continue,
+        _ => {
+          let Some(length) = self.u8() else { break };
+          let length = length as usize;
+          if self.bytes.len() < length {
+            break;
+          }
+          let (data, tail) = self.bytes.split_at(length);
+          self.bytes = tail;
+          if option == OPTION_URL_DATA {
+            url_data.extend_from_slice(data);
+          }
+        },
+      }
+    }
+    if url_data.is_empty() {
+      None
+    } else {
+      Some(String::from_utf8_lossy(&url_data).into_owned())
+    }
+  }
+}
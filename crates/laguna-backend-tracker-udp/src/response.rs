@@ -0,0 +1,89 @@
+use laguna_backend_tracker_common::peer::PeerBin;
+
+use crate::connection::ConnectionId;
+use crate::{ACTION_ANNOUNCE, ACTION_CONNECT, ACTION_ERROR, ACTION_SCRAPE};
+
+#[derive(Debug)]
+pub enum UdpResponse {
+  Connect {
+    transaction_id: i32,
+    connection_id: ConnectionId,
+  },
+  Announce {
+    transaction_id: i32,
+    interval: i32,
+    leechers: i32,
+    seeders: i32,
+    peers: Vec<PeerBin>,
+  },
+  Scrape {
+    transaction_id: i32,
+    torrents: Vec<ScrapeStats>,
+  },
+  Error {
+    transaction_id: i32,
+    message: String,
+  },
+}
+
+/// Scrape information of a single torrent, in order of info hashes in request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScrapeStats {
+  pub seeders: i32,
+  pub completed: i32,
+  pub leechers: i32,
+}
+
+impl UdpResponse {
+  /// Encodes response in network byte order.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match self {
+      Self::Connect {
+        transaction_id,
+        connection_id,
+      } => {
+        bytes.extend_from_slice(&ACTION_CONNECT.to_be_bytes());
+        bytes.extend_from_slice(&transaction_id.to_be_bytes());
+        bytes.extend_from_slice(&connection_id.0.to_be_bytes());
+      },
+      Self::Announce {
+        transaction_id,
+        interval,
+        leechers,
+        seeders,
+        peers,
+      } => {
+        bytes.extend_from_slice(&ACTION_ANNOUNCE.to_be_bytes());
+        bytes.extend_from_slice(&transaction_id.to_be_bytes());
+        bytes.extend_from_slice(&interval.to_be_bytes());
+        bytes.extend_from_slice(&leechers.to_be_bytes());
+        bytes.extend_from_slice(&seeders.to_be_bytes());
+        for peer in peers {
+          bytes.extend_from_slice(&peer.0);
+        }
+      },
+      Self::Scrape {
+        transaction_id,
+        torrents,
+      } => {
+        bytes.extend_from_slice(&ACTION_SCRAPE.to_be_bytes());
+        bytes.extend_from_slice(&transaction_id.to_be_bytes());
+        for torrent in torrents {
+          bytes.extend_from_slice(&torrent.seeders.to_be_bytes());
+          bytes.extend_from_slice(&torrent.completed.to_be_bytes());
+          bytes.extend_from_slice(&torrent.leechers.to_be_bytes());
+        }
+      },
+      Self::Error {
+        transaction_id,
+        message,
+      } => {
+        bytes.extend_from_slice(&ACTION_ERROR.to_be_bytes());
+        bytes.extend_from_slice(&transaction_id.to_be_bytes());
+        bytes.extend_from_slice(message.as_bytes());
+      },
+    }
+    bytes
+  }
+}
@@ -0,0 +1,88 @@
+//! WebTorrent clients send binary fields (info hash, peer id, offer id) as JSON strings
+//! where each character is a single byte (latin1).
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn to_binary_string(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| char::from(*b)).collect()
+}
+
+/// Returns `None` if string contains non-latin1 characters or is not exactly `N` bytes long.
+pub fn from_binary_string<const N: usize>(s: &str) -> Option<[u8; N]> {
+  s.chars()
+    .map(|c| u8::try_from(c).ok())
+    .collect::<Option<Vec<u8>>>()
+    .and_then(|bytes| bytes.try_into().ok())
+}
+
+pub fn serialize<S, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  serializer.serialize_str(&to_binary_string(bytes))
+}
+
+pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let s = String::deserialize(deserializer)?;
+  from_binary_string(&s)
+    .ok_or_else(|| D::Error::custom(format!("expected binary string of {} bytes", N)))
+}
+
+pub mod info_hash {
+  use laguna_backend_tracker_common::info_hash::InfoHash;
+  use serde::{Deserializer, Serializer};
+
+  pub fn serialize<S, const N: usize>(
+    info_hash: &InfoHash<N>,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    super::serialize(&info_hash.0, serializer)
+  }
+
+  pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<InfoHash<N>, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    super::deserialize(deserializer).map(InfoHash)
+  }
+}
+
+pub mod peer_id {
+  use laguna_backend_tracker_common::peer::PeerId;
+  use serde::{Deserializer, Serializer};
+
+  pub fn serialize<S>(peer_id: &PeerId, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    super::serialize(&peer_id.0, serializer)
+  }
+
+  pub fn deserialize<'de, D>(deserializer: D) -> Result<PeerId, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    super::deserialize(deserializer).map(PeerId)
+  }
+}
+
+pub mod option_peer_id {
+  use laguna_backend_tracker_common::peer::PeerId;
+  use serde::{Deserialize, Deserializer};
+
+  pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<PeerId>, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(with = "super::peer_id")] PeerId);
+    Option::<Wrapper>::deserialize(deserializer)
+      .map(|wrapper| wrapper.map(|Wrapper(peer_id)| peer_id))
+  }
+}
@@ -1,3 +1,7 @@
 #![doc(html_logo_url = "https://sloveniaengineering.github.io/laguna-backend/logo.png")]
 #![doc(html_favicon_url = "https://sloveniaengineering.github.io/laguna-backend/favicon.ico")]
 #![doc(issue_tracker_base_url = "https://github.com/SloveniaEngineering/laguna-backend")]
+//! WebTorrent (WebSocket) tracker protocol.
+//! See: <https://github.com/webtorrent/bittorrent-tracker/blob/master/lib/server/parse-websocket.js>
+pub mod binary;
+pub mod message;
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use laguna_backend_tracker_common::announce::AnnounceEvent;
+use laguna_backend_tracker_common::info_hash::{InfoHash, SHA1_LENGTH};
+use laguna_backend_tracker_common::peer::PeerId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::binary;
+
+pub const OFFER_ID_LENGTH: usize = 20;
+
+/// Max. number of offers a client can send in a single announce.
+pub const MAX_OFFERS: usize = 10;
+
+/// Identifies WebRTC offer, so that answer can be matched to it by the offering peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OfferId(#[serde(with = "binary")] pub [u8; OFFER_ID_LENGTH]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WsAction {
+  Announce,
+  Scrape,
+}
+
+/// Message sent by WebTorrent client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum WsRequest {
+  Announce(WsAnnounceRequest),
+  Scrape(WsScrapeRequest),
+}
+
+/// Announce is also used to send an answer to an offer,
+/// in that case `answer`, `to_peer_id` and `offer_id` are set and transfer stats are not.
+#[derive(Debug, Deserialize)]
+pub struct WsAnnounceRequest {
+  #[serde(with = "binary::info_hash")]
+  pub info_hash: InfoHash<SHA1_LENGTH>,
+  #[serde(with = "binary::peer_id")]
+  pub peer_id: PeerId,
+  pub uploaded: Option<i64>,
+  pub downloaded: Option<i64>,
+  pub left: Option<i64>,
+  pub event: Option<AnnounceEvent>,
+  pub numwant: Option<usize>,
+  pub offers: Option<Vec<WsOffer>>,
+  /// WebRTC answer (SDP), relayed as is.
+  pub answer: Option<Value>,
+  #[serde(default, deserialize_with = "binary::option_peer_id::deserialize")]
+  pub to_peer_id: Option<PeerId>,
+  pub offer_id: Option<OfferId>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsOffer {
+  /// WebRTC offer (SDP), relayed as is.
+  pub offer: Value,
+  pub offer_id: OfferId,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsScrapeRequest {
+  pub info_hash: WsScrapeInfoHashes,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum WsScrapeInfoHashes {
+  One(#[serde(with = "binary::info_hash")] InfoHash<SHA1_LENGTH>),
+  Many(Vec<WsScrapeInfoHash>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsScrapeInfoHash(#[serde(with = "binary::info_hash")] pub InfoHash<SHA1_LENGTH>);
+
+impl WsScrapeInfoHashes {
+  pub fn into_vec(self) -> Vec<InfoHash<SHA1_LENGTH>> {
+    match self {
+      Self::One(info_hash) => vec![info_hash],
+      Self::Many(info_hashes) => info_hashes
+        .into_iter()
+        .map(|WsScrapeInfoHash(info_hash)| info_hash)
+        .collect(),
+    }
+  }
+}
+
+/// Message sent by tracker.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum WsResponse {
+  Announce(WsAnnounceResponse),
+  Offer(WsOfferMessage),
+  Answer(WsAnswerMessage),
+  Scrape(WsScrapeResponse),
+  Error(WsErrorResponse),
+}
+
+#[derive(Debug, Serialize)]
+pub struct WsAnnounceResponse {
+  pub action: WsAction,
+  #[serde(with = "binary::info_hash")]
+  pub info_hash: InfoHash<SHA1_LENGTH>,
+  pub interval: u64,
+  pub complete: u64,
+  pub incomplete: u64,
+}
+
+/// Offer relayed from `peer_id` to another peer in swarm.
+#[derive(Debug, Serialize)]
+pub struct WsOfferMessage {
+  pub action: WsAction,
+  #[serde(with = "binary::info_hash")]
+  pub info_hash: InfoHash<SHA1_LENGTH>,
+  #[serde(with = "binary::peer_id")]
+  pub peer_id: PeerId,
+  pub offer_id: OfferId,
+  pub offer: Value,
+}
+
+/// Answer relayed from `peer_id` back to the offering peer.
+#[derive(Debug, Serialize)]
+pub struct WsAnswerMessage {
+  pub action: WsAction,
+  #[serde(with = "binary::info_hash")]
+  pub info_hash: InfoHash<SHA1_LENGTH>,
+  #[serde(with = "binary::peer_id")]
+  pub peer_id: PeerId,
+  pub offer_id: OfferId,
+  pub answer: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WsScrapeResponse {
+  pub action: WsAction,
+  /// Keyed by binary string of info hash.
+  pub files: BTreeMap<String, WsScrapeFile>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct WsScrapeFile {
+  pub complete: i64,
+  pub incomplete: i64,
+  pub downloaded: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WsErrorResponse {
+  #[serde(rename = "failure reason")]
+  pub failure_reason: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub action: Option<WsAction>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    serialize_with = "serialize_option_info_hash"
+  )]
+  pub info_hash: Option<InfoHash<SHA1_LENGTH>>,
+}
+
+fn serialize_option_info_hash<S>(
+  info_hash: &Option<InfoHash<SHA1_LENGTH>>,
+  serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+  S: serde::Serializer,
+{
+  match info_hash {
+    Some(info_hash) => binary::info_hash::serialize(info_hash, serializer),
+    None => serializer.serialize_none(),
+  }
+}
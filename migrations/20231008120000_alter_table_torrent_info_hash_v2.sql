@@ -0,0 +1,4 @@
+-- BitTorrent v2 (BEP 52) info hash (full SHA256).
+-- Hybrid torrents are stored under their v1 info hash, v2 only torrents under truncated v2 info hash.
+ALTER TABLE "Torrent"
+    ADD COLUMN IF NOT EXISTS info_hash_v2 BYTEA UNIQUE CHECK (length(info_hash_v2) = 32);
\ No newline at end of file
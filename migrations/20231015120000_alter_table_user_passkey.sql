@@ -0,0 +1,3 @@
+-- Per-user passkey embedded in announce URLs, replaces per-download down_hash.
+ALTER TABLE "User"
+    ADD COLUMN IF NOT EXISTS passkey BYTEA UNIQUE NOT NULL DEFAULT uuid_send(uuid_generate_v4()) CHECK (length(passkey) = 16);
\ No newline at end of file
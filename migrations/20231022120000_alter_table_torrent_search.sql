@@ -0,0 +1,8 @@
+-- Full-text search over file name and nfo.
+-- Dots, dashes and underscores separate words in file names, eg. Big.Buck.Bunny.mp4.
+ALTER TABLE "Torrent"
+    ADD COLUMN IF NOT EXISTS search TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', translate(file_name, '._-', '   ') || ' ' || COALESCE(nfo, ''))
+        ) STORED;
+CREATE INDEX IF NOT EXISTS torrent_search_idx ON "Torrent" USING GIN (search);
+CREATE INDEX IF NOT EXISTS torrent_uploaded_at_idx ON "Torrent" (uploaded_at);
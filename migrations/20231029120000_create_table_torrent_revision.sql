@@ -0,0 +1,15 @@
+-- Every change of editable torrent metadata (nfo, genre), so moderation can be audited and reverted.
+CREATE TABLE IF NOT EXISTS "TorrentRevision"
+(
+    id        UUID PRIMARY KEY         NOT NULL DEFAULT uuid_generate_v4(),
+    info_hash BYTEA                    NOT NULL CHECK (length(info_hash) = 20),
+    old_nfo   TEXT,
+    new_nfo   TEXT,
+    old_genre Genre,
+    new_genre Genre,
+    modded_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    modded_by UUID                     NOT NULL,
+    FOREIGN KEY (info_hash) REFERENCES "Torrent" (info_hash) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (modded_by) REFERENCES "User" (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+CREATE INDEX IF NOT EXISTS torrent_revision_info_hash_idx ON "TorrentRevision" (info_hash, modded_at);
@@ -0,0 +1,5 @@
+-- Trashed (soft deleted) torrents are hidden from listings and can't be announced, but can be restored.
+ALTER TABLE "Torrent"
+    ADD COLUMN IF NOT EXISTS deleted_at    TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS deleted_by    UUID REFERENCES "User" (id) ON DELETE SET NULL ON UPDATE CASCADE,
+    ADD COLUMN IF NOT EXISTS delete_reason TEXT;
@@ -0,0 +1,3 @@
+-- Refresh tokens issued before last password change are rejected.
+ALTER TABLE "User"
+    ADD COLUMN IF NOT EXISTS password_changed_at TIMESTAMPTZ;
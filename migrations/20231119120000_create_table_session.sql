@@ -0,0 +1,18 @@
+-- Login sessions, each one holds hash of its current refresh token.
+-- Refresh tokens without active session are rejected, so sessions can be revoked.
+-- Tokens have second precision, so same token may be issued twice within a second and hash is not unique.
+CREATE TABLE IF NOT EXISTS "Session"
+(
+    id           UUID PRIMARY KEY         NOT NULL DEFAULT uuid_generate_v4(),
+    user_id      UUID                     NOT NULL,
+    token_hash   BYTEA                    NOT NULL CHECK (length(token_hash) = 32),
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at   TIMESTAMP WITH TIME ZONE NOT NULL,
+    user_agent   TEXT,
+    ip           INET,
+    revoked_at   TIMESTAMP WITH TIME ZONE,
+    FOREIGN KEY (user_id) REFERENCES "User" (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+CREATE INDEX IF NOT EXISTS session_user_id_idx ON "Session" (user_id);
+CREATE INDEX IF NOT EXISTS session_token_hash_idx ON "Session" (token_hash);
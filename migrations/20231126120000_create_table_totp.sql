@@ -0,0 +1,22 @@
+-- TOTP (RFC 6238) second factor, enrollment is pending until first code is confirmed.
+CREATE TABLE IF NOT EXISTS "Totp"
+(
+    user_id        UUID PRIMARY KEY         NOT NULL,
+    secret         VARCHAR(64)              NOT NULL,
+    created_at     TIMESTAMP WITH TIME ZONE NOT NULL,
+    confirmed_at   TIMESTAMP WITH TIME ZONE,
+    -- Time step of last accepted code, so codes can't be replayed.
+    last_used_step BIGINT,
+    FOREIGN KEY (user_id) REFERENCES "User" (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- Single use codes for when authenticator is lost, only SHA-256 hashes are stored.
+CREATE TABLE IF NOT EXISTS "TotpRecoveryCode"
+(
+    id        UUID PRIMARY KEY         NOT NULL DEFAULT uuid_generate_v4(),
+    user_id   UUID                     NOT NULL,
+    code_hash BYTEA                    NOT NULL CHECK (length(code_hash) = 32),
+    used_at   TIMESTAMP WITH TIME ZONE,
+    FOREIGN KEY (user_id) REFERENCES "Totp" (user_id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+CREATE INDEX IF NOT EXISTS totp_recovery_code_user_id_idx ON "TotpRecoveryCode" (user_id);
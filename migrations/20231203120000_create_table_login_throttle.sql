@@ -0,0 +1,12 @@
+-- Failed logins of account or from IP address, used to lock out brute-force attempts.
+CREATE TABLE IF NOT EXISTS "LoginThrottle"
+(
+    id             UUID PRIMARY KEY         NOT NULL DEFAULT uuid_generate_v4(),
+    user_id        UUID UNIQUE,
+    ip             INET UNIQUE,
+    failed_count   INTEGER                  NOT NULL,
+    last_failed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    locked_until   TIMESTAMP WITH TIME ZONE,
+    CHECK ((user_id IS NULL) <> (ip IS NULL)),
+    FOREIGN KEY (user_id) REFERENCES "User" (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Refresh tokens carry session id and nonce, so each one is unique.
+DROP INDEX IF EXISTS session_token_hash_idx;
+ALTER TABLE "Session"
+    ADD CONSTRAINT session_token_hash_key UNIQUE (token_hash);
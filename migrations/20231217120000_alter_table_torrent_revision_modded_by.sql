@@ -0,0 +1,5 @@
+-- Revisions outlive their moderator's account, same as "Torrent".modded_by.
+ALTER TABLE "TorrentRevision"
+    ALTER COLUMN modded_by DROP NOT NULL,
+    DROP CONSTRAINT IF EXISTS "TorrentRevision_modded_by_fkey",
+    ADD CONSTRAINT "TorrentRevision_modded_by_fkey" FOREIGN KEY (modded_by) REFERENCES "User" (id) ON DELETE SET NULL ON UPDATE CASCADE;
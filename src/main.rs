@@ -3,16 +3,19 @@
 #![doc(issue_tracker_base_url = "https://github.com/SloveniaEngineering/laguna-backend")]
 #![doc = include_str!("../README.md")]
 #![forbid(unsafe_code)]
-#![forbid(unsafe_code)]
 
 use actix_settings::ApplySettings;
 
 use actix_web::middleware::Logger;
 use actix_web::middleware::NormalizePath;
 use actix_web::middleware::TrailingSlash;
+use actix_web::rt::net::UdpSocket;
 use actix_web::web;
 use actix_web::HttpServer;
 
+use laguna::api::reaper::stale_peer_reaper;
+use laguna::api::udp::udp_tracker;
+use laguna::api::ws::WsSwarms;
 use laguna::dto::meta::AppInfoDTO;
 use laguna::setup::get_settings;
 use std::env;
@@ -23,9 +26,29 @@ use laguna::setup::setup_db;
 
 #[actix_web::main]
 async fn main() -> Result<(), sqlx::Error> {
+  let settings = get_settings();
+  if settings.application.tracker.udp_enabled {
+    let socket = UdpSocket::bind(settings.application.tracker.udp_address())
+      .await
+      .expect("Cannot bind UDP tracker socket");
+    actix_web::rt::spawn(udp_tracker(
+      socket,
+      setup_db(&settings).await?,
+      settings.application.tracker.clone(),
+    ));
+  }
+
+  actix_web::rt::spawn(stale_peer_reaper(
+    setup_db(&settings).await?,
+    settings.application.tracker.clone(),
+  ));
+
+  // Shared by all workers.
+  let ws_swarms = web::Data::new(WsSwarms::default());
   HttpServer::new(move || {
     setup()
       .data_factory(|| async move { setup_db(&get_settings()).await })
+      .app_data(ws_swarms.clone())
       .app_data(web::Data::new(AppInfoDTO {
         version: env::var("CARGO_PKG_VERSION").expect("CARGO_PKG_VERSION not set"),
         authors: env::var("CARGO_PKG_AUTHORS")